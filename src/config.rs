@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,6 +12,476 @@ pub enum ConfigError {
     Filesystem(String),
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+    #[error("Failed to parse config file {}: {message}", path.display())]
+    Parse { path: PathBuf, message: String },
+    #[error("Invalid value for `{key}`: {message}")]
+    InvalidValue { key: String, message: String },
+}
+
+/// Name of the project-local configuration file
+pub const PROJECT_CONFIG_FILE: &str = ".nvim-mcp.toml";
+
+const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error"];
+
+/// Settings read from a TOML configuration file.
+///
+/// Every field is optional so that several files can be layered on top of each other.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    /// Directory for socket files
+    pub socket_path: Option<String>,
+    pub log: LogConfig,
+    pub lsp: LspConfig,
+    pub tools: ToolsConfig,
+    pub safety: SafetyConfig,
+}
+
+/// `[log]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Path to the log file
+    pub file: Option<PathBuf>,
+    /// Log level (trace, debug, info, warn, error)
+    pub level: Option<String>,
+    /// Path to the JSONL audit log of mutating tool calls
+    pub audit_file: Option<PathBuf>,
+}
+
+/// `[lsp]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LspConfig {
+    /// LSP client name per filetype, used when a tool call omits `lsp_client_name`
+    pub default_clients: HashMap<String, String>,
+    /// Timeout for LSP requests in milliseconds
+    pub timeout_ms: Option<u64>,
+    /// Timeout per LSP method, e.g. `"textDocument/references"`
+    pub request_timeouts: HashMap<String, u64>,
+    /// Settings per LSP client name
+    pub clients: HashMap<String, LspClientConfig>,
+}
+
+/// `[lsp.clients.<name>]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LspClientConfig {
+    /// Timeout for requests sent to this client in milliseconds
+    pub timeout_ms: Option<u64>,
+    /// Timeout per LSP method for this client
+    pub request_timeouts: HashMap<String, u64>,
+}
+
+impl LspConfig {
+    /// Timeout for an LSP request, from the most specific setting available:
+    /// client and method, client, method, then the global timeout.
+    pub fn request_timeout_ms(&self, client_name: &str, method: &str) -> Option<u64> {
+        let client = self.clients.get(client_name);
+        client
+            .and_then(|c| c.request_timeouts.get(method).copied())
+            .or_else(|| client.and_then(|c| c.timeout_ms))
+            .or_else(|| self.request_timeouts.get(method).copied())
+            .or(self.timeout_ms)
+    }
+}
+
+/// `[tools]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsConfig {
+    /// Names or glob patterns of the only tools exposed to MCP clients, all tools when unset
+    pub enabled: Option<Vec<String>>,
+    /// Names or glob patterns of tools that are not exposed to MCP clients
+    pub disabled: Vec<String>,
+}
+
+/// `[safety]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    /// Only expose tools that don't modify the editor state
+    pub read_only: Option<bool>,
+    pub exec_lua: ExecLuaPolicy,
+    pub confirm: ConfirmConfig,
+    pub paths: PathPolicy,
+}
+
+impl SafetyConfig {
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.unwrap_or(false)
+    }
+
+    /// Layer `other` on top of `self`. Settings only get stricter, so a project
+    /// file can't undo the protections of the user configuration.
+    fn merge(self, other: SafetyConfig) -> Self {
+        Self {
+            read_only: stricter_flag(self.read_only, other.read_only),
+            exec_lua: self.exec_lua.merge(other.exec_lua),
+            confirm: self.confirm.merge(other.confirm),
+            paths: self.paths.merge(other.paths),
+        }
+    }
+}
+
+/// Merge a flag that protects more when set, `true` in either layer wins
+fn stricter_flag(base: Option<bool>, other: Option<bool>) -> Option<bool> {
+    match (base, other) {
+        (Some(base), Some(other)) => Some(base || other),
+        (base, other) => other.or(base),
+    }
+}
+
+/// Patterns rejected by the `exec_lua` policy unless configured otherwise
+pub const DEFAULT_DENIED_LUA_PATTERNS: &[&str] = &[
+    "os.execute",
+    "io.popen",
+    "vim.fn.system",
+    "vim.fn.systemlist",
+    "vim.fn.jobstart",
+    "vim.system",
+];
+
+/// `[safety.exec_lua]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecLuaPolicy {
+    /// Check code passed to `exec_lua` and commands passed to `exec_command` against this policy
+    pub enabled: Option<bool>,
+    /// `vim.api` functions the code may call, all of them when unset
+    pub allowed_api: Option<Vec<String>>,
+    /// Code patterns that are rejected, e.g. `os.execute`
+    pub denied_patterns: Option<Vec<String>>,
+    /// Run the code in a restricted environment that enforces the policy at runtime
+    pub sandbox: Option<bool>,
+}
+
+impl ExecLuaPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub fn is_sandboxed(&self) -> bool {
+        self.sandbox.unwrap_or(false)
+    }
+
+    /// Configured denied patterns, or the default ones
+    pub fn denied_patterns(&self) -> Vec<String> {
+        match &self.denied_patterns {
+            Some(patterns) => patterns.clone(),
+            None => DEFAULT_DENIED_LUA_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
+
+    /// Layer `other` on top of `self`: the allowed functions are narrowed and
+    /// the denied patterns extended, never relaxed
+    fn merge(self, other: ExecLuaPolicy) -> Self {
+        // A layer without patterns keeps the defaults, which the other layer can't drop
+        let denied_patterns = match (&self.denied_patterns, &other.denied_patterns) {
+            (None, None) => None,
+            (Some(base), None) => Some(base.clone()),
+            (None, Some(_)) | (Some(_), Some(_)) => {
+                let mut patterns = self.denied_patterns();
+                for pattern in other.denied_patterns() {
+                    if !patterns.contains(&pattern) {
+                        patterns.push(pattern);
+                    }
+                }
+                Some(patterns)
+            }
+        };
+        let allowed_api = match (self.allowed_api, other.allowed_api) {
+            (Some(base), Some(other)) => Some(
+                other
+                    .into_iter()
+                    .filter(|name| base.contains(name))
+                    .collect(),
+            ),
+            (base, other) => other.or(base),
+        };
+        Self {
+            enabled: stricter_flag(self.enabled, other.enabled),
+            allowed_api,
+            denied_patterns,
+            sandbox: stricter_flag(self.sandbox, other.sandbox),
+        }
+    }
+}
+
+/// `[safety.paths]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathPolicy {
+    /// Reject documents and edits outside of the allowed roots
+    pub confine: Option<bool>,
+    /// Allowed roots, the git root of the Neovim working directory when unset
+    pub allowed_roots: Option<Vec<PathBuf>>,
+}
+
+impl PathPolicy {
+    pub fn is_confined(&self) -> bool {
+        self.confine.unwrap_or(false)
+    }
+
+    /// Layer `other` on top of `self`: confinement can't be turned off and the
+    /// roots of a confined layer can only be narrowed to directories inside them
+    fn merge(self, other: PathPolicy) -> Self {
+        let confined = self.is_confined();
+        let allowed_roots = match (self.allowed_roots, other.allowed_roots) {
+            (Some(base), Some(other)) => Some(
+                other
+                    .into_iter()
+                    .filter(|root| base.iter().any(|base| root.starts_with(base)))
+                    .collect(),
+            ),
+            // The default root is only known at runtime, so it is kept
+            (None, Some(_)) if confined => None,
+            (base, other) => other.or(base),
+        };
+        Self {
+            confine: stricter_flag(self.confine, other.confine),
+            allowed_roots,
+        }
+    }
+}
+
+/// Time to wait for the user to answer a confirmation prompt
+pub const DEFAULT_CONFIRM_TIMEOUT_MS: u64 = 120_000;
+
+/// How a tool call is confirmed before it modifies the editor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmMode {
+    /// Run without asking
+    #[default]
+    Off,
+    /// Ask the user in Neovim with a preview of the change
+    Editor,
+    /// Ask the MCP client user through elicitation, or in Neovim when the
+    /// client doesn't support it
+    Client,
+}
+
+/// `[safety.confirm]` section
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmConfig {
+    /// How long to wait for an answer before denying the change
+    pub timeout_ms: Option<u64>,
+    /// Confirmation mode per tool name
+    pub tools: HashMap<String, ConfirmMode>,
+    /// Only ask for workspace edits touching more than this many files
+    pub min_files: Option<usize>,
+    /// Only ask for `exec_lua` code that may modify buffers or files
+    pub lua_writes_only: Option<bool>,
+}
+
+impl ConfirmConfig {
+    pub fn mode(&self, tool: &str) -> ConfirmMode {
+        self.tools.get(tool).copied().unwrap_or_default()
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms.unwrap_or(DEFAULT_CONFIRM_TIMEOUT_MS)
+    }
+
+    pub fn min_files(&self) -> usize {
+        self.min_files.unwrap_or(0)
+    }
+
+    pub fn is_lua_writes_only(&self) -> bool {
+        self.lua_writes_only.unwrap_or(false)
+    }
+
+    /// Layer `other` on top of `self`: tools can be added or asked about but not
+    /// turned off, and the thresholds skipping confirmations can't be raised
+    fn merge(mut self, other: ConfirmConfig) -> Self {
+        for (tool, mode) in other.tools {
+            let current = self.tools.entry(tool).or_default();
+            if *current == ConfirmMode::Off {
+                *current = mode;
+            }
+        }
+        self.tools.retain(|_, mode| *mode != ConfirmMode::Off);
+        self.timeout_ms = other.timeout_ms.or(self.timeout_ms);
+        self.min_files = other
+            .min_files
+            .map(|other| other.min(self.min_files()))
+            .or(self.min_files);
+        self.lua_writes_only = other
+            .lua_writes_only
+            .map(|other| other && self.is_lua_writes_only())
+            .or(self.lua_writes_only);
+        self
+    }
+}
+
+impl FileConfig {
+    /// Read and validate a configuration file
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::Filesystem(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        let config: FileConfig = toml::from_str(&content).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string().trim_end().to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Layer `other` on top of `self`, values from `other` take precedence except
+    /// for `[safety]`, which only gets stricter
+    pub fn merge(mut self, other: FileConfig) -> Self {
+        self.socket_path = other.socket_path.or(self.socket_path);
+        self.log.file = other.log.file.or(self.log.file);
+        self.log.level = other.log.level.or(self.log.level);
+        self.log.audit_file = other.log.audit_file.or(self.log.audit_file);
+        self.lsp.default_clients.extend(other.lsp.default_clients);
+        self.lsp.timeout_ms = other.lsp.timeout_ms.or(self.lsp.timeout_ms);
+        self.lsp.request_timeouts.extend(other.lsp.request_timeouts);
+        for (name, client) in other.lsp.clients {
+            let entry = self.lsp.clients.entry(name).or_default();
+            entry.timeout_ms = client.timeout_ms.or(entry.timeout_ms);
+            entry.request_timeouts.extend(client.request_timeouts);
+        }
+        self.tools.enabled = other.tools.enabled.or(self.tools.enabled);
+        for tool in other.tools.disabled {
+            if !self.tools.disabled.contains(&tool) {
+                self.tools.disabled.push(tool);
+            }
+        }
+        self.safety = self.safety.merge(other.safety);
+        self
+    }
+
+    /// Validate values that can't be expressed by the TOML schema
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(level) = &self.log.level {
+            validate_log_level("log.level", level)?;
+        }
+        validate_timeout("lsp.timeout_ms", self.lsp.timeout_ms)?;
+        for (method, timeout) in &self.lsp.request_timeouts {
+            validate_timeout(&format!("lsp.request_timeouts.{method:?}"), Some(*timeout))?;
+        }
+        for (name, client) in &self.lsp.clients {
+            validate_timeout(&format!("lsp.clients.{name}.timeout_ms"), client.timeout_ms)?;
+            for (method, timeout) in &client.request_timeouts {
+                validate_timeout(
+                    &format!("lsp.clients.{name}.request_timeouts.{method:?}"),
+                    Some(*timeout),
+                )?;
+            }
+        }
+        for (filetype, client) in &self.lsp.default_clients {
+            if client.trim().is_empty() {
+                return Err(ConfigError::InvalidValue {
+                    key: format!("lsp.default_clients.{filetype}"),
+                    message: "LSP client name cannot be empty".to_string(),
+                });
+            }
+        }
+        let exec_lua = &self.safety.exec_lua;
+        if exec_lua
+            .allowed_api
+            .iter()
+            .flatten()
+            .any(|f| !f.starts_with("nvim_"))
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "safety.exec_lua.allowed_api".to_string(),
+                message: "entries must be `vim.api` function names like nvim_buf_get_lines"
+                    .to_string(),
+            });
+        }
+        if exec_lua
+            .denied_patterns
+            .iter()
+            .flatten()
+            .any(|p| p.trim().is_empty())
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "safety.exec_lua.denied_patterns".to_string(),
+                message: "patterns cannot be empty".to_string(),
+            });
+        }
+        validate_timeout("safety.confirm.timeout_ms", self.safety.confirm.timeout_ms)?;
+        if self
+            .safety
+            .paths
+            .allowed_roots
+            .iter()
+            .flatten()
+            .any(|root| !root.is_absolute())
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "safety.paths.allowed_roots".to_string(),
+                message: "roots must be absolute paths".to_string(),
+            });
+        }
+        validate_tool_patterns("tools.enabled", self.tools.enabled.iter().flatten())?;
+        validate_tool_patterns("tools.disabled", &self.tools.disabled)?;
+        Ok(())
+    }
+}
+
+fn validate_timeout(key: &str, timeout_ms: Option<u64>) -> Result<(), ConfigError> {
+    if timeout_ms == Some(0) {
+        return Err(ConfigError::InvalidValue {
+            key: key.to_string(),
+            message: "must be greater than 0".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_tool_patterns<'a>(
+    key: &str,
+    patterns: impl IntoIterator<Item = &'a String>,
+) -> Result<(), ConfigError> {
+    for pattern in patterns {
+        if pattern.trim().is_empty() {
+            return Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                message: "tool names cannot be empty".to_string(),
+            });
+        }
+        if let Err(e) = glob::Pattern::new(pattern) {
+            return Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                message: format!("invalid pattern {pattern:?}: {e}"),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_log_level(key: &str, level: &str) -> Result<(), ConfigError> {
+    if LOG_LEVELS.contains(&level.to_lowercase().as_str()) {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidValue {
+            key: key.to_string(),
+            message: format!("unknown log level {level:?}, expected one of {LOG_LEVELS:?}"),
+        })
+    }
+}
+
+/// Settings given on the command line or through environment variables.
+/// These take precedence over configuration files.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Explicit configuration file, replaces the discovered ones
+    pub config_file: Option<PathBuf>,
+    pub socket_path: Option<String>,
+    pub log_file: Option<PathBuf>,
+    pub log_level: Option<String>,
+    pub audit_log: Option<PathBuf>,
+    /// Force read-only mode regardless of the configuration files
+    pub read_only: bool,
 }
 
 /// Configuration for the Neovim MCP server
@@ -17,6 +490,11 @@ pub struct ServerConfig {
     pub socket_path: PathBuf,
     pub log_file: Option<PathBuf>,
     pub log_level: String,
+    /// Append-only JSONL record of mutating tool calls
+    pub audit_log: Option<PathBuf>,
+    pub lsp: LspConfig,
+    pub tools: ToolsConfig,
+    pub safety: SafetyConfig,
 }
 
 impl ServerConfig {
@@ -32,9 +510,99 @@ impl ServerConfig {
             socket_path,
             log_file,
             log_level,
+            audit_log: None,
+            lsp: LspConfig::default(),
+            tools: ToolsConfig::default(),
+            safety: SafetyConfig::default(),
         })
     }
 
+    /// Load configuration files and apply command line overrides.
+    ///
+    /// Precedence from highest to lowest: command line, environment variables,
+    /// project-local `.nvim-mcp.toml`, `$XDG_CONFIG_HOME/nvim-mcp/config.toml`.
+    pub fn load(overrides: ConfigOverrides) -> Result<Self, ConfigError> {
+        let paths = match &overrides.config_file {
+            Some(path) => vec![path.clone()],
+            None => Self::config_file_paths(),
+        };
+        Self::load_from(&paths, overrides)
+    }
+
+    /// Load the given configuration files, later files taking precedence
+    pub fn load_from(paths: &[PathBuf], overrides: ConfigOverrides) -> Result<Self, ConfigError> {
+        // The first file is taken as is, so that it can relax the default safety settings
+        let mut file_config: Option<FileConfig> = None;
+        for path in paths {
+            let layer = FileConfig::from_path(path)?;
+            file_config = Some(match file_config {
+                Some(base) => base.merge(layer),
+                None => layer,
+            });
+        }
+        Self::from_file_config(file_config.unwrap_or_default(), overrides)
+    }
+
+    /// Resolve the final configuration from a merged file configuration
+    pub fn from_file_config(
+        file_config: FileConfig,
+        overrides: ConfigOverrides,
+    ) -> Result<Self, ConfigError> {
+        let log_level = overrides
+            .log_level
+            .or(file_config.log.level)
+            .unwrap_or_else(|| "info".to_string());
+        validate_log_level("log_level", &log_level)?;
+
+        let mut config = Self::new(
+            overrides.socket_path.or(file_config.socket_path),
+            overrides.log_file.or(file_config.log.file),
+            log_level,
+        )?;
+        config.audit_log = overrides.audit_log.or(file_config.log.audit_file);
+        config.lsp = file_config.lsp;
+        config.tools = file_config.tools;
+        config.safety = file_config.safety;
+        if overrides.read_only {
+            config.safety.read_only = Some(true);
+        }
+        Ok(config)
+    }
+
+    /// Existing configuration files in increasing order of precedence
+    pub fn config_file_paths() -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Some(user_config) = Self::user_config_path()
+            && user_config.is_file()
+        {
+            paths.push(user_config);
+        }
+        if let Some(project_config) = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| Self::find_project_config(&cwd))
+        {
+            paths.push(project_config);
+        }
+        paths
+    }
+
+    /// Path of the user configuration file
+    pub fn user_config_path() -> Option<PathBuf> {
+        let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ if cfg!(target_os = "windows") => PathBuf::from(std::env::var_os("APPDATA")?),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_home.join("nvim-mcp").join("config.toml"))
+    }
+
+    /// Find the nearest project-local configuration file in `dir` or its ancestors
+    pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(PROJECT_CONFIG_FILE))
+            .find(|p| p.is_file())
+    }
+
     /// Resolve socket path from optional user input or platform defaults
     pub fn resolve_socket_path(provided: Option<String>) -> Result<PathBuf, ConfigError> {
         match provided {
@@ -20,6 +20,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::cmp_owned)]
     fn test_server_config_with_default_path() {
         let config = ServerConfig::new(None, None, "debug".to_string());
 
@@ -46,4 +47,398 @@ mod tests {
         let resolved = ServerConfig::resolve_socket_path(Some(socket_path.clone())).unwrap();
         assert_eq!(resolved.to_string_lossy(), socket_path);
     }
+
+    fn write_config(dir: &TempDir, name: &str, content: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_config_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket_path = temp_dir.path().join("sockets");
+        let path = write_config(
+            &temp_dir,
+            "config.toml",
+            &format!(
+                r#"
+socket_path = "{}"
+
+[log]
+level = "debug"
+
+[lsp]
+timeout_ms = 3000
+default_clients = {{ rust = "rust_analyzer" }}
+
+[tools]
+disabled = ["exec_lua"]
+
+[safety]
+read_only = true
+"#,
+                socket_path.display()
+            ),
+        );
+
+        let config = ServerConfig::load_from(&[path], ConfigOverrides::default()).unwrap();
+
+        assert_eq!(config.socket_path, socket_path);
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.lsp.timeout_ms, Some(3000));
+        assert_eq!(
+            config.lsp.default_clients.get("rust").map(String::as_str),
+            Some("rust_analyzer")
+        );
+        assert_eq!(config.tools.disabled, vec!["exec_lua".to_string()]);
+        assert!(config.safety.is_read_only());
+    }
+
+    #[test]
+    fn test_project_config_overrides_user_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = write_config(
+            &temp_dir,
+            "user.toml",
+            r#"
+[log]
+level = "warn"
+
+[lsp]
+timeout_ms = 1000
+default_clients = { rust = "rust_analyzer", lua = "lua_ls" }
+
+[tools]
+disabled = ["exec_lua"]
+"#,
+        );
+        let project = write_config(
+            &temp_dir,
+            "project.toml",
+            r#"
+[lsp]
+default_clients = { lua = "emmylua_ls" }
+
+[tools]
+disabled = ["lsp_rename"]
+"#,
+        );
+
+        let config = ServerConfig::load_from(&[user, project], ConfigOverrides::default()).unwrap();
+
+        assert_eq!(config.log_level, "warn");
+        assert_eq!(config.lsp.timeout_ms, Some(1000));
+        assert_eq!(config.lsp.default_clients["rust"], "rust_analyzer");
+        assert_eq!(config.lsp.default_clients["lua"], "emmylua_ls");
+        assert_eq!(
+            config.tools.disabled,
+            vec!["exec_lua".to_string(), "lsp_rename".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_project_config_cannot_relax_safety() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = write_config(
+            &temp_dir,
+            "user.toml",
+            r#"
+[safety]
+read_only = true
+
+[safety.exec_lua]
+enabled = true
+allowed_api = ["nvim_get_current_buf", "nvim_buf_get_lines"]
+
+[safety.paths]
+confine = true
+allowed_roots = ["/work"]
+
+[safety.confirm]
+tools = { lsp_rename = "client" }
+min_files = 1
+"#,
+        );
+        let project = write_config(
+            &temp_dir,
+            "project.toml",
+            r#"
+[safety]
+read_only = false
+
+[safety.exec_lua]
+enabled = false
+allowed_api = ["nvim_get_current_buf", "nvim_exec_lua"]
+denied_patterns = []
+sandbox = true
+
+[safety.paths]
+confine = false
+allowed_roots = ["/", "/work/project"]
+
+[safety.confirm]
+tools = { lsp_rename = "off", exec_lua = "editor" }
+min_files = 10
+lua_writes_only = true
+"#,
+        );
+
+        let config = ServerConfig::load_from(&[user, project], ConfigOverrides::default()).unwrap();
+        let safety = config.safety;
+
+        assert!(safety.is_read_only());
+        assert!(safety.exec_lua.is_enabled());
+        assert_eq!(
+            safety.exec_lua.allowed_api,
+            Some(vec!["nvim_get_current_buf".to_string()])
+        );
+        assert_eq!(
+            safety.exec_lua.denied_patterns(),
+            ExecLuaPolicy::default().denied_patterns()
+        );
+        // Stricter settings are taken from the project file
+        assert!(safety.exec_lua.is_sandboxed());
+        assert!(safety.paths.is_confined());
+        assert_eq!(
+            safety.paths.allowed_roots,
+            Some(vec![std::path::PathBuf::from("/work/project")])
+        );
+        assert_eq!(safety.confirm.mode("lsp_rename"), ConfirmMode::Client);
+        assert_eq!(safety.confirm.mode("exec_lua"), ConfirmMode::Editor);
+        assert_eq!(safety.confirm.min_files(), 1);
+        // Unset in the user configuration means the default, which is stricter
+        assert!(!safety.confirm.is_lua_writes_only());
+    }
+
+    #[test]
+    fn test_overrides_take_precedence_over_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_config(&temp_dir, "config.toml", "[log]\nlevel = \"warn\"\n");
+        let socket_path = temp_dir
+            .path()
+            .join("override")
+            .to_string_lossy()
+            .to_string();
+
+        let config = ServerConfig::load_from(
+            &[path],
+            ConfigOverrides {
+                socket_path: Some(socket_path.clone()),
+                log_level: Some("trace".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(config.log_level, "trace");
+        assert_eq!(config.socket_path.to_string_lossy(), socket_path);
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_config(&temp_dir, "config.toml", "[lsp]\ntimeout = 10\n");
+
+        let err = ServerConfig::load_from(std::slice::from_ref(&path), ConfigOverrides::default())
+            .unwrap_err();
+
+        match err {
+            ConfigError::Parse { path: p, message } => {
+                assert_eq!(p, path);
+                assert!(message.contains("timeout"), "unexpected message: {message}");
+            }
+            other => panic!("Expected parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_values_name_offending_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let cases = [
+            ("[log]\nlevel = \"loud\"\n", "log.level"),
+            ("[lsp]\ntimeout_ms = 0\n", "lsp.timeout_ms"),
+            (
+                "[lsp]\ndefault_clients = { rust = \"\" }\n",
+                "lsp.default_clients.rust",
+            ),
+            (
+                "[safety.confirm]\ntimeout_ms = 0\n",
+                "safety.confirm.timeout_ms",
+            ),
+            (
+                "[safety.paths]\nallowed_roots = [\"src\"]\n",
+                "safety.paths.allowed_roots",
+            ),
+        ];
+
+        for (content, expected_key) in cases {
+            let path = write_config(&temp_dir, "config.toml", content);
+            match ServerConfig::load_from(&[path], ConfigOverrides::default()) {
+                Err(ConfigError::InvalidValue { key, .. }) => assert_eq!(key, expected_key),
+                other => panic!("Expected invalid value for {expected_key}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_project_config_in_ancestors() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_config(&temp_dir, PROJECT_CONFIG_FILE, "");
+        let nested = temp_dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(ServerConfig::find_project_config(&nested), Some(path));
+    }
+
+    #[test]
+    fn test_lsp_request_timeout_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_config(
+            &temp_dir,
+            "config.toml",
+            r#"
+[lsp]
+timeout_ms = 2000
+request_timeouts = { "textDocument/references" = 5000 }
+
+[lsp.clients.ts_ls]
+timeout_ms = 20000
+request_timeouts = { "textDocument/rename" = 60000 }
+"#,
+        );
+
+        let config = ServerConfig::load_from(&[path], ConfigOverrides::default()).unwrap();
+        let lsp = &config.lsp;
+
+        assert_eq!(
+            lsp.request_timeout_ms("ts_ls", "textDocument/rename"),
+            Some(60000)
+        );
+        assert_eq!(
+            lsp.request_timeout_ms("ts_ls", "textDocument/references"),
+            Some(20000)
+        );
+        assert_eq!(
+            lsp.request_timeout_ms("rust_analyzer", "textDocument/references"),
+            Some(5000)
+        );
+        assert_eq!(
+            lsp.request_timeout_ms("rust_analyzer", "textDocument/hover"),
+            Some(2000)
+        );
+        assert_eq!(
+            LspConfig::default().request_timeout_ms("rust_analyzer", "textDocument/hover"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_lsp_timeouts_name_offending_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let cases = [
+            (
+                "[lsp.request_timeouts]\n\"textDocument/hover\" = 0\n",
+                "lsp.request_timeouts.\"textDocument/hover\"",
+            ),
+            (
+                "[lsp.clients.ts_ls]\ntimeout_ms = 0\n",
+                "lsp.clients.ts_ls.timeout_ms",
+            ),
+        ];
+
+        for (content, expected_key) in cases {
+            let path = write_config(&temp_dir, "config.toml", content);
+            match ServerConfig::load_from(&[path], ConfigOverrides::default()) {
+                Err(ConfigError::InvalidValue { key, .. }) => assert_eq!(key, expected_key),
+                other => panic!("Expected invalid value for {expected_key}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_only_override() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_config(&temp_dir, "config.toml", "[safety]\nread_only = false\n");
+
+        let config = ServerConfig::load_from(
+            &[path],
+            ConfigOverrides {
+                read_only: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(config.safety.is_read_only());
+    }
+
+    #[test]
+    fn test_exec_lua_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = write_config(
+            &temp_dir,
+            "user.toml",
+            "[safety.exec_lua]\nenabled = true\nsandbox = true\n",
+        );
+        let project = write_config(
+            &temp_dir,
+            "project.toml",
+            "[safety.exec_lua]\nallowed_api = [\"nvim_get_current_buf\"]\n",
+        );
+
+        let config = ServerConfig::load_from(&[user, project], ConfigOverrides::default()).unwrap();
+        let policy = &config.safety.exec_lua;
+
+        assert!(policy.is_enabled());
+        assert!(policy.is_sandboxed());
+        assert_eq!(
+            policy.allowed_api,
+            Some(vec!["nvim_get_current_buf".to_string()])
+        );
+        assert!(policy.denied_patterns().contains(&"os.execute".to_string()));
+
+        let path = write_config(
+            &temp_dir,
+            "invalid.toml",
+            "[safety.exec_lua]\nallowed_api = [\"os.execute\"]\n",
+        );
+        match ServerConfig::load_from(&[path], ConfigOverrides::default()) {
+            Err(ConfigError::InvalidValue { key, .. }) => {
+                assert_eq!(key, "safety.exec_lua.allowed_api")
+            }
+            other => panic!("Expected invalid value, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_confirm_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = write_config(
+            &temp_dir,
+            "config.toml",
+            "[safety.confirm]\ntimeout_ms = 30000\nmin_files = 3\n\
+             tools = { lsp_rename = \"editor\", exec_lua = \"client\" }\n",
+        );
+
+        let config = ServerConfig::load_from(&[path], ConfigOverrides::default()).unwrap();
+        let confirm = &config.safety.confirm;
+
+        assert_eq!(confirm.timeout_ms(), 30000);
+        assert_eq!(confirm.mode("lsp_rename"), ConfirmMode::Editor);
+        assert_eq!(confirm.mode("exec_lua"), ConfirmMode::Client);
+        assert_eq!(confirm.mode("lsp_apply_edit"), ConfirmMode::Off);
+        assert_eq!(confirm.min_files(), 3);
+        assert!(!confirm.is_lua_writes_only());
+
+        let path = write_config(
+            &temp_dir,
+            "invalid.toml",
+            "[safety.confirm]\ntools = { exec_lua = \"maybe\" }\n",
+        );
+        assert!(matches!(
+            ServerConfig::load_from(&[path], ConfigOverrides::default()),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
 }
@@ -1,10 +1,10 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use rmcp::{ServiceExt, transport::stdio};
-use std::{path::PathBuf, sync::OnceLock};
+use std::{net::SocketAddr, path::PathBuf, sync::OnceLock};
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
-use nvim_mcp::{ConfigError, NeovimMcpServer, ServerConfig};
+use nvim_mcp::{ConfigError, ConfigOverrides, NeovimMcpServer, ServerConfig};
 
 static LONG_VERSION: OnceLock<String> = OnceLock::new();
 
@@ -28,31 +28,69 @@ fn long_version() -> &'static str {
         .as_str()
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Transport {
+    /// Serve a single MCP client over stdin/stdout
+    Stdio,
+    /// Serve multiple MCP clients over streamable HTTP
+    Http,
+}
+
 #[derive(Parser)]
 #[command(version, long_version=long_version(), about, long_about = None)]
 struct Cli {
+    /// Path to a configuration file. Replaces the user and project configuration files
+    #[arg(long, env = "NVIM_MCP_CONFIG")]
+    config: Option<PathBuf>,
+
     /// Path to the log file. If not specified, logs to stderr
-    #[arg(long)]
+    #[arg(long, env = "NVIM_MCP_LOG_FILE")]
     log_file: Option<PathBuf>,
 
-    /// Log level (trace, debug, info, warn, error)
-    #[arg(long, default_value = "info")]
-    log_level: String,
+    /// Log level (trace, debug, info, warn, error) [default: info]
+    #[arg(long, env = "NVIM_MCP_LOG_LEVEL")]
+    log_level: Option<String>,
+
+    /// Append every tool call that modifies the editor to this JSONL file
+    #[arg(long, env = "NVIM_MCP_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
 
     /// Directory for socket files. Defaults to platform-specific location
-    #[arg(long)]
+    #[arg(long, env = "NVIM_MCP_SOCKET_PATH")]
     socket_path: Option<String>,
+
+    /// Hide and reject tools that modify the editor or files on disk
+    #[arg(long, env = "NVIM_MCP_READ_ONLY")]
+    read_only: bool,
+
+    /// Transport used to serve MCP clients
+    #[arg(long, value_enum, default_value = "stdio")]
+    transport: Transport,
+
+    /// Address to bind the HTTP transport to
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
+    // Resolve configuration files, environment variables and command line options
+    let config = ServerConfig::load(ConfigOverrides {
+        config_file: cli.config,
+        socket_path: cli.socket_path,
+        log_file: cli.log_file,
+        log_level: cli.log_level,
+        audit_log: cli.audit_log,
+        read_only: cli.read_only,
+    })
+    .map_err(|e: ConfigError| format!("Configuration error: {}", e))?;
+
     // Initialize tracing/logging
-    let env_filter = EnvFilter::from_default_env().add_directive(cli.log_level.parse()?);
+    let env_filter = EnvFilter::from_default_env().add_directive(config.log_level.parse()?);
 
-    let log_file_clone = cli.log_file.clone();
-    let _guard = if let Some(log_file) = log_file_clone {
+    let _guard = if let Some(log_file) = &config.log_file {
         // Log to file
         let file_appender = tracing_appender::rolling::never(
             log_file
@@ -84,20 +122,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         None
     };
 
-    // Create server configuration with lazy evaluation
-    let config = ServerConfig::new(cli.socket_path, cli.log_file, cli.log_level)
-        .map_err(|e: ConfigError| format!("Configuration error: {}", e))?;
-
     info!(
         "Starting nvim-mcp Neovim server with socket_path: {}",
         config.socket_path.display()
     );
-    let server = NeovimMcpServer::new(config.socket_path);
-    let service = server.serve(stdio()).await.inspect_err(|e| {
-        error!("Error starting Neovim server: {}", e);
-    })?;
-    info!("Neovim server started, waiting for connections...");
-    service.waiting().await?;
+    let server = NeovimMcpServer::with_config(&config)
+        .map_err(|e: ConfigError| format!("Configuration error: {}", e))?;
+    let nvim_clients = server.nvim_clients.clone();
+
+    match cli.transport {
+        Transport::Stdio => {
+            let service = server.serve(stdio()).await.inspect_err(|e| {
+                error!("Error starting Neovim server: {}", e);
+            })?;
+            info!("Neovim server started, waiting for connections...");
+            service.waiting().await?;
+        }
+        Transport::Http => {
+            let router = server.into_http_router();
+            let listener = tokio::net::TcpListener::bind(cli.bind)
+                .await
+                .inspect_err(|e| {
+                    error!("Error binding HTTP transport to {}: {}", cli.bind, e);
+                })?;
+            info!(
+                "Neovim server listening on http://{}/mcp, waiting for connections...",
+                cli.bind
+            );
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await?;
+        }
+    }
+
+    // Terminate Neovim instances spawned by this server
+    let connection_ids: Vec<String> = nvim_clients.iter().map(|e| e.key().clone()).collect();
+    for connection_id in connection_ids {
+        if let Some((_, mut client)) = nvim_clients.remove(&connection_id) {
+            let _ = client.disconnect().await;
+        }
+    }
 
     info!("Server shutdown complete");
 
@@ -5,16 +5,25 @@ use std::fmt::{self, Display};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use async_trait::async_trait;
-use nvim_rs::{Handler, Neovim, create::tokio as create};
+use dashmap::DashMap;
+use nvim_rs::{Handler, Neovim, compat::tokio::Compat, create::tokio as create, error::CallError};
 use rmpv::Value;
 use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
-use tokio::{io::AsyncWrite, net::TcpStream};
+use tokio::{
+    io::{AsyncWrite, WriteHalf},
+    net::TcpStream,
+    sync::{broadcast, oneshot},
+};
 use tracing::{debug, info, instrument};
 
 use super::{connection::NeovimConnection, error::NeovimError};
+use crate::config::LspConfig;
 
 /// Common trait for Neovim client operations
 #[async_trait]
@@ -31,6 +40,29 @@ pub trait NeovimClientTrait: Sync {
     /// Execute Lua code in Neovim
     async fn execute_lua(&self, code: &str) -> Result<Value, NeovimError>;
 
+    /// Run Ex commands with `nvim_exec2` and return their output
+    async fn exec_command(&self, command: &str) -> Result<String, NeovimError>;
+
+    /// Execute Lua code in an environment built from an allowlist of safe functions.
+    /// Denied names, `vim.api` functions outside of `allowed_api` and anything else
+    /// missing from the environment are reported as policy violations.
+    async fn execute_lua_sandboxed(
+        &self,
+        code: &str,
+        allowed_api: Option<&[String]>,
+        denied_names: &[String],
+    ) -> Result<Value, NeovimError>;
+
+    /// Ask the user in Neovim to approve a change, showing a preview of it.
+    /// Fails with `ConfirmationDenied` when the user denies or doesn't answer in time,
+    /// and right away with [`NO_UI_REASON`] when Neovim is headless.
+    async fn confirm(
+        &self,
+        title: &str,
+        preview: ConfirmPreview,
+        timeout_ms: u64,
+    ) -> Result<(), NeovimError>;
+
     /// Set up diagnostics changed autocmd
     async fn setup_diagnostics_changed_autocmd(&self) -> Result<(), NeovimError>;
 
@@ -40,6 +72,91 @@ pub trait NeovimClientTrait: Sync {
     /// Get diagnostics for the entire workspace
     async fn get_workspace_diagnostics(&self) -> Result<Vec<Diagnostic>, NeovimError>;
 
+    /// Get the filetype of a document
+    async fn get_filetype(&self, document: &DocumentIdentifier) -> Result<String, NeovimError>;
+
+    /// Get the working directory of Neovim and the git root containing it
+    async fn get_project_root(&self) -> Result<ProjectRoot, NeovimError>;
+
+    /// Get the text of a document, preferring the unsaved contents of its buffer
+    async fn read_document(
+        &self,
+        document: &DocumentIdentifier,
+    ) -> Result<DocumentText, NeovimError>;
+
+    /// Get the cursor position in the current window
+    async fn get_cursor(&self) -> Result<CursorPosition, NeovimError>;
+
+    /// Get the current or last visual selection of the current buffer
+    async fn get_selection(&self) -> Result<Selection, NeovimError>;
+
+    /// Get the cursor of the current window and up to `context_lines` lines
+    /// above and below it, without the enclosing symbol
+    async fn get_cursor_context(&self, context_lines: u64) -> Result<CursorContext, NeovimError>;
+
+    /// Get the quickfix list, or the location list of a window (0 for the current one)
+    async fn get_quickfix_list(&self, window_id: Option<u64>) -> Result<QuickfixList, NeovimError>;
+
+    /// Populate the quickfix list, or the location list of a window (0 for the
+    /// current one), optionally opening its window
+    async fn set_quickfix_list(
+        &self,
+        window_id: Option<u64>,
+        title: &str,
+        entries: &[QuickfixEntry],
+        action: QuickfixAction,
+        open: bool,
+    ) -> Result<(), NeovimError>;
+
+    /// Search the files of the working directory, reading modified buffers
+    /// instead of their files on disk
+    async fn search_workspace(&self, options: &SearchOptions) -> Result<SearchResult, NeovimError>;
+
+    /// Find files under the project root by fuzzy query or glob, skipping
+    /// files ignored by git
+    async fn find_files(&self, query: &str, max_results: u64) -> Result<FoundFiles, NeovimError>;
+
+    /// Run a tree-sitter query against a document, optionally limited to the
+    /// nodes overlapping lines `start_line` to `end_line` (zero-based, inclusive)
+    async fn treesitter_query(
+        &self,
+        document: &DocumentIdentifier,
+        query: &str,
+        start_line: Option<u64>,
+        end_line: Option<u64>,
+        max_results: u64,
+    ) -> Result<TreesitterCaptures, NeovimError>;
+
+    /// Get the named tree-sitter node at a position and its ancestors
+    async fn treesitter_node_at(
+        &self,
+        document: &DocumentIdentifier,
+        position: &Position,
+    ) -> Result<TreesitterAncestry, NeovimError>;
+
+    /// Build a document outline from the tree-sitter `tags` query of the
+    /// document's language, or its `locals` query
+    async fn treesitter_outline(
+        &self,
+        document: &DocumentIdentifier,
+    ) -> Result<DocumentOutline, NeovimError>;
+
+    /// Get the mode, windows, cursors and layout of the editor in a single round trip
+    async fn get_editor_state(&self) -> Result<EditorState, NeovimError>;
+
+    /// Receive the IDs of buffers attached with [`NeovimClientTrait::attach_buffer`]
+    /// whenever their text changes
+    fn subscribe_buffer_changes(&self) -> Option<broadcast::Receiver<u64>>;
+
+    /// Watch a buffer for text changes with `nvim_buf_attach`
+    async fn attach_buffer(&self, buffer_id: u64) -> Result<(), NeovimError>;
+
+    /// Stop watching a buffer attached with [`NeovimClientTrait::attach_buffer`]
+    async fn detach_buffer(&self, buffer_id: u64) -> Result<(), NeovimError>;
+
+    /// Get the diffs of modified buffers against their files on disk
+    async fn get_unsaved_changes(&self) -> Result<Vec<UnsavedChange>, NeovimError>;
+
     /// Get LSP clients
     async fn lsp_get_clients(&self) -> Result<Vec<LspClient>, NeovimError>;
 
@@ -185,21 +302,61 @@ pub trait NeovimClientTrait: Sync {
     ) -> Result<String, NeovimError>;
 }
 
+/// Senders waiting for responses notified by Neovim, keyed by response ID
+pub type PendingResponses = Arc<DashMap<u64, oneshot::Sender<String>>>;
+
+/// Number of buffer change events kept for slow receivers
+const BUFFER_CHANGES_CAPACITY: usize = 64;
+
 pub struct NeovimHandler<T> {
+    responses: PendingResponses,
+    buffer_changes: broadcast::Sender<u64>,
     _marker: std::marker::PhantomData<T>,
 }
 
 impl<T> NeovimHandler<T> {
     pub fn new() -> Self {
         NeovimHandler {
+            responses: PendingResponses::default(),
+            buffer_changes: broadcast::channel(BUFFER_CHANGES_CAPACITY).0,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// Senders for the responses delivered to this handler
+    pub fn responses(&self) -> PendingResponses {
+        self.responses.clone()
+    }
+
+    /// Sender of the IDs of attached buffers whose text changed
+    pub fn buffer_changes(&self) -> broadcast::Sender<u64> {
+        self.buffer_changes.clone()
+    }
+
+    /// Forward a response to the request waiting for it
+    fn handle_response(&self, args: &[Value]) {
+        let (Some(response_id), Some(response)) = (
+            args.first().and_then(Value::as_u64),
+            args.get(1).and_then(Value::as_str),
+        ) else {
+            debug!("Malformed response notification: {args:?}");
+            return;
+        };
+
+        match self.responses.remove(&response_id) {
+            Some((_, sender)) => {
+                let _ = sender.send(response.to_string());
+            }
+            None => debug!("No pending request for response {response_id}"),
+        }
+    }
 }
 
 impl<T> Clone for NeovimHandler<T> {
     fn clone(&self) -> Self {
         NeovimHandler {
+            responses: self.responses.clone(),
+            buffer_changes: self.buffer_changes.clone(),
             _marker: std::marker::PhantomData,
         }
     }
@@ -213,7 +370,18 @@ where
     type Writer = T;
 
     async fn handle_notify(&self, name: String, args: Vec<Value>, _neovim: Neovim<T>) {
-        info!("handling notification: {name:?}, {args:?}");
+        match name.as_ref() {
+            "NVIM_MCP_LspResponse" | "NVIM_MCP_ConfirmResponse" | "NVIM_MCP_SearchResponse" => {
+                self.handle_response(&args)
+            }
+            "NVIM_MCP_BufferChanged" => {
+                if let Some(buffer_id) = args.first().and_then(Value::as_u64) {
+                    // No receivers just means nobody is subscribed
+                    let _ = self.buffer_changes.send(buffer_id);
+                }
+            }
+            _ => info!("handling notification: {name:?}, {args:?}"),
+        }
     }
 
     async fn handle_request(
@@ -230,7 +398,7 @@ where
     }
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct Diagnostic {
     pub message: String,
     pub code: Option<serde_json::Value>,
@@ -245,7 +413,7 @@ pub struct Diagnostic {
     pub user_data: Option<UserData>,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct UserData {
     pub lsp: LSPDiagnostic,
     #[serde(flatten)]
@@ -272,6 +440,18 @@ pub struct BufferInfo {
     pub id: u64,
     pub name: String,
     pub line_count: u64,
+    #[serde(default)]
+    pub filetype: String,
+    /// Whether the buffer text is loaded in memory
+    #[serde(default)]
+    pub loaded: bool,
+    /// Whether the buffer shows up in `:ls`, loaded or not
+    #[serde(default)]
+    pub listed: bool,
+    /// Size of the buffer text in bytes, for unloaded buffers the size of the
+    /// file on disk, 0 when neither is available
+    #[serde(default)]
+    pub size: u64,
 }
 
 /// Text documents are identified using a URI.
@@ -339,6 +519,525 @@ where
     deserializer.deserialize_any(StringOrStruct(PhantomData))
 }
 
+/// Working directory of a Neovim instance and the project it belongs to
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProjectRoot {
+    /// Current working directory, `project_relative_path` is resolved against it
+    pub cwd: PathBuf,
+    /// Git root containing `cwd`, or `cwd` itself outside of a repository
+    pub root: PathBuf,
+}
+
+/// Text of a document, taken from its buffer when the file is loaded
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DocumentText {
+    /// Buffer holding the document, `None` when it was read from disk
+    pub buffer_id: Option<u64>,
+    pub name: String,
+    pub filetype: String,
+    /// Whether the buffer has unsaved changes
+    pub modified: bool,
+    pub text: String,
+}
+
+/// Cursor of the current window
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CursorPosition {
+    pub buffer_id: u64,
+    pub name: String,
+    pub filetype: String,
+    /// Position in UTF-16 code units, ready for LSP requests
+    pub position: Position,
+    /// Word under the cursor
+    pub word: String,
+}
+
+/// Unsaved changes of a modified buffer
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UnsavedChange {
+    pub buffer_id: u64,
+    pub name: String,
+    /// Unified diff from the file on disk to the buffer contents
+    pub diff: String,
+}
+
+/// Kind of visual selection
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    /// Characterwise (`v`)
+    Char,
+    /// Linewise (`V`)
+    Line,
+    /// Blockwise (`CTRL-V`)
+    Block,
+}
+
+/// Current or last visual selection of the current buffer
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Selection {
+    pub document: DocumentIdentifier,
+    pub name: String,
+    pub filetype: String,
+    pub mode: SelectionMode,
+    /// Whether visual mode is still active, otherwise this is the last selection
+    pub active: bool,
+    /// Selected range in UTF-16 code units with an exclusive end, spanning
+    /// every line of a blockwise selection
+    pub range: Range,
+    /// Selected text, the selected part of each line for blockwise selections
+    pub text: String,
+}
+
+/// Cursor of the current window with the lines around it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CursorContext {
+    pub cursor: CursorPosition,
+    pub document: DocumentIdentifier,
+    /// Line number of the first of `lines`, zero-based
+    pub first_line: u64,
+    pub lines: Vec<String>,
+    /// Innermost document symbol containing the cursor
+    #[serde(default)]
+    pub symbol: Option<EnclosingSymbol>,
+}
+
+/// Type of a quickfix item
+#[derive(
+    Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickfixItemType {
+    Error,
+    Warning,
+    Info,
+    Note,
+}
+
+/// Quickfix or location list item resolved to a file position
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QuickfixItem {
+    /// Absolute path of the file, none for items without a position
+    pub path: Option<PathBuf>,
+    pub buffer_id: Option<u64>,
+    /// Position of the item, none for items without a position. The range is
+    /// empty when the end is unknown.
+    pub range: Option<Range>,
+    pub text: String,
+    #[serde(rename = "type")]
+    pub item_type: Option<QuickfixItemType>,
+}
+
+/// Quickfix list or location list of a window
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QuickfixList {
+    pub title: String,
+    /// Position of the current item starting from 1, 0 for an empty list
+    pub current: u64,
+    #[serde(default)]
+    pub items: Vec<QuickfixItem>,
+}
+
+/// Entry added to a quickfix or location list
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum QuickfixEntry {
+    /// LSP location, as returned by `lsp_references` or `lsp_definition`
+    Location(Location),
+    /// Diagnostic, as returned by `buffer_diagnostics`
+    Diagnostic(Box<Diagnostic>),
+    /// Range in a document
+    Position {
+        document: DocumentIdentifier,
+        range: Range,
+        #[serde(default)]
+        text: String,
+        #[serde(default, rename = "type")]
+        item_type: Option<QuickfixItemType>,
+    },
+}
+
+/// How entries are added to a quickfix or location list
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickfixAction {
+    /// Create a new list after the current one, older lists stay available with `:colder`
+    #[default]
+    New,
+    /// Replace the items and title of the current list
+    Replace,
+    /// Add the items to the current list
+    Append,
+}
+
+impl QuickfixAction {
+    /// `{action}` argument of `setqflist()`
+    fn as_flag(self) -> &'static str {
+        match self {
+            Self::New => " ",
+            Self::Replace => "r",
+            Self::Append => "a",
+        }
+    }
+}
+
+fn default_max_results() -> u64 {
+    200
+}
+
+/// What to search for in the files of the working directory
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SearchOptions {
+    /// Regular expression, or literal text with `fixed_strings`
+    pub query: String,
+    /// Match the query as literal text instead of a regular expression
+    #[serde(default)]
+    pub fixed_strings: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// Only search files matching one of these globs, e.g. `*.rs` or `src/**`
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Skip files matching any of these globs
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Maximum number of matches to return
+    #[serde(default = "default_max_results")]
+    pub max_results: u64,
+}
+
+/// Search match, usable wherever an LSP location is accepted
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SearchMatch {
+    #[serde(flatten)]
+    pub location: Location,
+    /// Text of the matching line
+    pub text: String,
+}
+
+/// Matches of a workspace search, sorted by file and position
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SearchResult {
+    /// `ripgrep` when `'grepprg'` runs rg, otherwise `vimgrep`
+    pub backend: String,
+    #[serde(default)]
+    pub matches: Vec<SearchMatch>,
+    /// Whether matches beyond `max_results` were left out
+    pub truncated: bool,
+}
+
+/// Files of the project matching a query
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct FoundFiles {
+    pub cwd: PathBuf,
+    /// Git root containing `cwd`, or `cwd` itself, whose files were searched
+    pub root: PathBuf,
+    /// `git` when the files came from `git ls-files`, otherwise `filesystem`
+    pub backend: String,
+    /// Paths relative to `cwd`, ready to use as `project_relative_path`;
+    /// best matches first for fuzzy queries, sorted for globs
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// Whether files beyond `max_results` were left out
+    pub truncated: bool,
+}
+
+/// Node of a tree-sitter syntax tree
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TreesitterNode {
+    /// Node type from the grammar, e.g. `function_item` or `identifier`
+    #[serde(rename = "type")]
+    pub node_type: String,
+    /// Whether the node is named, as opposed to anonymous tokens such as `(`
+    pub named: bool,
+    /// Field the node fills in its parent, e.g. `name` or `body`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub range: Range,
+    /// Source text of the node, cut after 200 bytes
+    pub text: String,
+}
+
+/// Node captured by a tree-sitter query
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TreesitterCapture {
+    /// Capture name without the `@`
+    pub capture: String,
+    #[serde(flatten)]
+    pub node: TreesitterNode,
+}
+
+/// Captures of a tree-sitter query in document order
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TreesitterCaptures {
+    /// Tree-sitter language of the document
+    pub language: String,
+    #[serde(default)]
+    pub captures: Vec<TreesitterCapture>,
+    /// Whether captures beyond `max_results` were left out
+    pub truncated: bool,
+}
+
+/// Syntax tree nodes enclosing a position, innermost first and ending with the root
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TreesitterAncestry {
+    /// Tree-sitter language of the document
+    pub language: String,
+    pub nodes: Vec<TreesitterNode>,
+}
+
+/// Snapshot of what the user is looking at
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EditorState {
+    /// Mode short name as reported by `nvim_get_mode`, e.g. `n`, `i` or `v`
+    pub mode: String,
+    /// Whether Neovim is waiting for input, e.g. in the middle of a mapping
+    pub blocking: bool,
+    pub current_buffer: u64,
+    pub current_window: u64,
+    /// Number of the current tab page, starting from 1
+    pub current_tab: u64,
+    /// Alternate buffer (`#`), if any
+    pub alternate_buffer: Option<u64>,
+    pub cwd: PathBuf,
+    pub windows: Vec<WindowState>,
+    /// Window layout of the current tab page
+    pub layout: WindowLayout,
+}
+
+/// Window and its cursor
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WindowState {
+    pub window_id: u64,
+    pub buffer_id: u64,
+    /// Name of the buffer shown in the window
+    pub name: String,
+    /// Number of the tab page containing the window, starting from 1
+    pub tab: u64,
+    /// Cursor line, zero-based
+    pub line: u64,
+    /// Cursor column in bytes, zero-based
+    pub column: u64,
+    /// First visible line, zero-based
+    pub first_visible_line: u64,
+    /// Last visible line, zero-based
+    pub last_visible_line: u64,
+    pub width: u64,
+    pub height: u64,
+    pub floating: bool,
+}
+
+/// Tree of split windows, as reported by `winlayout()`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WindowLayout {
+    Leaf {
+        window_id: u64,
+    },
+    /// Windows side by side
+    Row {
+        children: Vec<WindowLayout>,
+    },
+    /// Windows stacked vertically
+    Column {
+        children: Vec<WindowLayout>,
+    },
+}
+
+impl WindowLayout {
+    /// Parse the nested `["leaf", id]`, `["row", [...]]` and `["col", [...]]` lists of `winlayout()`
+    pub fn from_winlayout(value: &serde_json::Value) -> Option<Self> {
+        let (kind, content) = match value.as_array()?.as_slice() {
+            [kind, content] => (kind.as_str()?, content),
+            _ => return None,
+        };
+        let children = || {
+            content
+                .as_array()?
+                .iter()
+                .map(Self::from_winlayout)
+                .collect::<Option<Vec<_>>>()
+        };
+        match kind {
+            "leaf" => Some(Self::Leaf {
+                window_id: content.as_u64()?,
+            }),
+            "row" => Some(Self::Row {
+                children: children()?,
+            }),
+            "col" => Some(Self::Column {
+                children: children()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// `getwininfo()` entry
+#[derive(Debug, serde::Deserialize)]
+struct WinInfo {
+    winid: u64,
+    bufnr: u64,
+    tabnr: u64,
+    topline: u64,
+    botline: u64,
+    width: u64,
+    height: u64,
+}
+
+/// Window cursor returned by `get_window_cursors.lua`
+#[derive(Debug, serde::Deserialize)]
+struct WindowCursor {
+    window_id: u64,
+    name: String,
+    line: u64,
+    column: u64,
+    floating: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ModeInfo {
+    mode: String,
+    blocking: bool,
+}
+
+impl EditorState {
+    /// Calls batched in a single `nvim_call_atomic` request, parsed by [`EditorState::from_atomic_results`]
+    fn atomic_calls() -> Vec<Value> {
+        let call = |name: &str, args: Vec<Value>| Value::Array(vec![name.into(), args.into()]);
+        let function = |name: &str, args: Vec<Value>| {
+            call("nvim_call_function", vec![name.into(), args.into()])
+        };
+        vec![
+            call("nvim_get_mode", vec![]),
+            function("getcwd", vec![]),
+            function("bufnr", vec!["%".into()]),
+            function("win_getid", vec![]),
+            function("tabpagenr", vec![]),
+            function("bufnr", vec!["#".into()]),
+            function("getwininfo", vec![]),
+            function("winlayout", vec![]),
+            call(
+                "nvim_exec_lua",
+                vec![
+                    include_str!("lua/get_window_cursors.lua").into(),
+                    Vec::<Value>::new().into(),
+                ],
+            ),
+        ]
+    }
+
+    /// Build the state from the results of [`EditorState::atomic_calls`], converted to JSON
+    pub fn from_atomic_results(results: Vec<serde_json::Value>) -> Result<Self, NeovimError> {
+        fn parse<T: serde::de::DeserializeOwned>(
+            value: serde_json::Value,
+            what: &str,
+        ) -> Result<T, NeovimError> {
+            serde_json::from_value(value)
+                .map_err(|e| NeovimError::Api(format!("Failed to parse {what}: {e}")))
+        }
+
+        let [
+            mode,
+            cwd,
+            current_buffer,
+            current_window,
+            current_tab,
+            alternate_buffer,
+            wininfo,
+            layout,
+            cursors,
+        ]: [serde_json::Value; 9] = results.try_into().map_err(|results: Vec<_>| {
+            NeovimError::Api(format!(
+                "Expected 9 editor state results, got {}",
+                results.len()
+            ))
+        })?;
+
+        let mode: ModeInfo = parse(mode, "mode")?;
+        let wininfo: Vec<WinInfo> = parse(wininfo, "window info")?;
+        let cursors: Vec<WindowCursor> = parse(cursors, "window cursors")?;
+        let windows = wininfo
+            .into_iter()
+            .filter_map(|info| {
+                let cursor = cursors.iter().find(|c| c.window_id == info.winid)?;
+                Some(WindowState {
+                    window_id: info.winid,
+                    buffer_id: info.bufnr,
+                    name: cursor.name.clone(),
+                    tab: info.tabnr,
+                    line: cursor.line,
+                    column: cursor.column,
+                    first_visible_line: info.topline.saturating_sub(1),
+                    last_visible_line: info.botline.saturating_sub(1),
+                    width: info.width,
+                    height: info.height,
+                    floating: cursor.floating,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            mode: mode.mode,
+            blocking: mode.blocking,
+            current_buffer: parse(current_buffer, "current buffer")?,
+            current_window: parse(current_window, "current window")?,
+            current_tab: parse(current_tab, "current tab")?,
+            // bufnr('#') is -1 without an alternate buffer
+            alternate_buffer: alternate_buffer.as_u64(),
+            cwd: parse(cwd, "working directory")?,
+            windows,
+            layout: WindowLayout::from_winlayout(&layout)
+                .ok_or_else(|| NeovimError::Api(format!("Invalid window layout: {layout}")))?,
+        })
+    }
+}
+
+/// Convert a value returned by the Neovim API to JSON, buffer, window and
+/// tab page handles become their integer IDs
+pub fn msgpack_to_json(value: Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    match value {
+        Value::Nil => Json::Null,
+        Value::Boolean(b) => Json::Bool(b),
+        Value::Integer(i) => i
+            .as_i64()
+            .map(Json::from)
+            .or_else(|| i.as_u64().map(Json::from))
+            .unwrap_or(Json::Null),
+        Value::F32(f) => Json::from(f64::from(f)),
+        Value::F64(f) => Json::from(f),
+        Value::String(s) => Json::String(String::from_utf8_lossy(s.as_bytes()).into_owned()),
+        Value::Binary(b) => Json::String(String::from_utf8_lossy(&b).into_owned()),
+        Value::Array(values) => Json::Array(values.into_iter().map(msgpack_to_json).collect()),
+        Value::Map(entries) => Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Value::String(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+                        key => msgpack_to_json(key).to_string(),
+                    };
+                    (key, msgpack_to_json(value))
+                })
+                .collect(),
+        ),
+        Value::Ext(_, data) => rmpv::decode::read_value(&mut data.as_slice())
+            .map(msgpack_to_json)
+            .unwrap_or(Json::Null),
+    }
+}
+
 /// Universal identifier for text documents supporting multiple reference types
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -548,6 +1247,32 @@ pub struct TextEdit {
     annotation_id: Option<String>,
 }
 
+/// Change shown to the user when asking for confirmation
+#[derive(Debug, Clone)]
+pub enum ConfirmPreview {
+    /// Diff of the files touched by a workspace edit
+    WorkspaceEdit {
+        client_name: String,
+        workspace_edit: WorkspaceEdit,
+    },
+    /// Source code highlighted with `filetype`
+    Code { filetype: String, code: String },
+}
+
+impl ConfirmPreview {
+    /// Plain text description of the change for prompts outside of Neovim
+    pub fn describe(&self) -> String {
+        match self {
+            ConfirmPreview::WorkspaceEdit { workspace_edit, .. } => {
+                let uris = workspace_edit.affected_uris();
+                let files: Vec<String> = uris.iter().map(|uri| format!("- {uri}")).collect();
+                format!("Changes {} file(s):\n{}", uris.len(), files.join("\n"))
+            }
+            ConfirmPreview::Code { filetype, code } => format!("```{filetype}\n{code}\n```"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkspaceEdit {
@@ -584,6 +1309,32 @@ pub struct WorkspaceEdit {
 
 impl_fromstr_serde_json!(WorkspaceEdit);
 
+impl WorkspaceEdit {
+    /// URIs of the documents created, renamed, deleted or edited
+    pub fn affected_uris(&self) -> Vec<String> {
+        let mut uris: Vec<String> = self
+            .changes
+            .iter()
+            .flat_map(|changes| changes.keys().cloned())
+            .collect();
+        for change in self.document_changes.iter().flatten() {
+            let uri = change
+                .pointer("/textDocument/uri")
+                .or_else(|| change.get("uri"))
+                .or_else(|| change.get("newUri"));
+            if let Some(uri) = uri.and_then(|uri| uri.as_str()) {
+                uris.push(uri.to_string());
+            }
+            if let Some(uri) = change.get("oldUri").and_then(|uri| uri.as_str()) {
+                uris.push(uri.to_string());
+            }
+        }
+        uris.sort();
+        uris.dedup();
+        uris
+    }
+}
+
 /// Formatting options for LSP document formatting
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -788,12 +1539,6 @@ pub enum MarkupKind {
     Markdown,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
-pub struct CodeActionResult {
-    #[serde(default)]
-    pub result: Vec<CodeAction>,
-}
-
 /// A symbol kind.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(into = "u8", from = "u8")]
@@ -892,7 +1637,7 @@ impl From<u8> for SymbolTag {
 }
 
 /// Represents a location inside a resource, such as a line inside a text file.
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct Location {
     pub uri: String,
     pub range: Range,
@@ -1018,6 +1763,107 @@ pub enum DocumentSymbolResult {
     Information(Vec<SymbolInformation>),
 }
 
+/// Where the symbols of a document outline came from
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlineSource {
+    Lsp,
+    Treesitter,
+}
+
+/// Symbols of a document from its LSP client, or built from tree-sitter queries
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct DocumentOutline {
+    pub source: OutlineSource,
+    /// LSP client name, or the tree-sitter query used (`tags` or `locals`)
+    pub provider: String,
+    pub symbols: DocumentSymbolResult,
+}
+
+/// Tree-sitter outline as returned by `treesitter_outline.lua`
+#[derive(Debug, serde::Deserialize)]
+struct TreesitterOutline {
+    provider: String,
+    #[serde(default)]
+    symbols: Vec<DocumentSymbol>,
+}
+
+impl From<TreesitterOutline> for DocumentOutline {
+    fn from(outline: TreesitterOutline) -> Self {
+        Self {
+            source: OutlineSource::Treesitter,
+            provider: outline.provider,
+            symbols: DocumentSymbolResult::Symbols(outline.symbols),
+        }
+    }
+}
+
+/// Innermost symbol containing a position
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EnclosingSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+    pub range: Range,
+    /// Names of the symbols containing this one, outermost first
+    pub containers: Vec<String>,
+}
+
+impl Range {
+    /// Whether `position` lies within the range, including both ends
+    pub fn contains(&self, position: &Position) -> bool {
+        let position = (position.line, position.character);
+        (self.start.line, self.start.character) <= position
+            && position <= (self.end.line, self.end.character)
+    }
+}
+
+impl DocumentSymbolResult {
+    /// Innermost symbol whose range contains `position`
+    pub fn enclosing(&self, position: &Position) -> Option<EnclosingSymbol> {
+        match self {
+            Self::Symbols(symbols) => {
+                let mut containers = Vec::new();
+                let mut symbols = symbols.as_slice();
+                let mut innermost = None;
+                while let Some(symbol) = symbols.iter().find(|s| s.range.contains(position)) {
+                    if let Some(parent) = innermost.replace(symbol) {
+                        containers.push(parent.name.clone());
+                    }
+                    symbols = symbol.children.as_deref().unwrap_or_default();
+                }
+                innermost.map(|symbol| EnclosingSymbol {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind.clone(),
+                    detail: symbol.detail.clone(),
+                    range: symbol.range.clone(),
+                    containers,
+                })
+            }
+            // Flat symbols carry no hierarchy, the one with the latest start
+            // and earliest end is the innermost
+            Self::Information(symbols) => symbols
+                .iter()
+                .filter(|s| s.location.range.contains(position))
+                .max_by_key(|s| {
+                    let range = &s.location.range;
+                    (
+                        range.start.line,
+                        range.start.character,
+                        std::cmp::Reverse((range.end.line, range.end.character)),
+                    )
+                })
+                .map(|symbol| EnclosingSymbol {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind.clone(),
+                    detail: None,
+                    range: symbol.location.range.clone(),
+                    containers: symbol.container_name.clone().into_iter().collect(),
+                }),
+        }
+    }
+}
+
 /// Result type for workspace symbols request
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct WorkspaceSymbolResult {
@@ -1055,6 +1901,7 @@ where
     T: AsyncWrite + Send + 'static,
 {
     connection: Option<NeovimConnection<T>>,
+    lsp_config: LspConfig,
 }
 
 #[cfg(unix)]
@@ -1084,40 +1931,105 @@ pub fn make_text_document_identifier_from_path<P: AsRef<Path>>(
     })
 }
 
-/// Nvim execute_lua custom result type
-#[derive(Debug, serde::Deserialize)]
-pub enum NvimExecuteLuaResult<T> {
-    #[serde(rename = "err_msg")]
-    Error(String),
-    #[serde(rename = "result")]
-    Ok(T),
-    #[serde(rename = "err")]
-    LspError { message: String, code: i32 },
+/// Source of response IDs correlating requests with their responses
+static NEXT_RESPONSE_ID: AtomicU64 = AtomicU64::new(1);
+
+tokio::task_local! {
+    /// Timeout of the LSP requests made by the current tool call, see [`with_lsp_timeout`]
+    static LSP_TIMEOUT_MS: u64;
 }
 
-impl<T> From<NvimExecuteLuaResult<T>> for Result<T, NeovimError> {
-    fn from(val: NvimExecuteLuaResult<T>) -> Self {
-        use NvimExecuteLuaResult::*;
-        match val {
-            Ok(result) => Result::Ok(result),
-            Error(msg) => Err(NeovimError::Api(msg)),
-            LspError { message, code } => Err(NeovimError::Lsp { code, message }),
-        }
+/// Run `future` with its LSP requests timing out after `timeout_ms`, overriding
+/// the configured timeouts. Without a timeout the configuration applies.
+pub async fn with_lsp_timeout<F: std::future::Future>(
+    timeout_ms: Option<u64>,
+    future: F,
+) -> F::Output {
+    match timeout_ms {
+        Some(timeout_ms) => LSP_TIMEOUT_MS.scope(timeout_ms, future).await,
+        None => future.await,
+    }
+}
+
+/// Reason of a confirmation denied because no UI is attached to show the prompt
+pub const NO_UI_REASON: &str = "no UI is attached to Neovim";
+
+/// Request waiting for its response, cancelled in Neovim if dropped before it completes
+struct PendingResponse<T>
+where
+    T: AsyncWrite + Send + 'static,
+{
+    response_id: u64,
+    /// Lua script cancelling the request, called with the response ID
+    cancel: &'static str,
+    nvim: Neovim<Compat<WriteHalf<T>>>,
+    responses: PendingResponses,
+    sent: bool,
+    completed: bool,
+}
+
+impl<T> Drop for PendingResponse<T>
+where
+    T: AsyncWrite + Send + 'static,
+{
+    fn drop(&mut self) {
+        self.responses.remove(&self.response_id);
+        if !self.sent || self.completed {
+            return;
+        }
+
+        let nvim = self.nvim.clone();
+        let response_id = self.response_id;
+        let cancel = self.cancel;
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = nvim
+                    .execute_lua(cancel, vec![Value::from(response_id)])
+                    .await
+                {
+                    debug!("Failed to cancel request {response_id}: {e}");
+                }
+            });
+        }
+    }
+}
+
+/// Nvim execute_lua custom result type
+#[derive(Debug, serde::Deserialize)]
+pub enum NvimExecuteLuaResult<T> {
+    #[serde(rename = "err_msg")]
+    Error(String),
+    #[serde(rename = "result")]
+    Ok(T),
+    #[serde(rename = "err")]
+    LspError { message: String, code: i32 },
+}
+
+impl<T> From<NvimExecuteLuaResult<T>> for Result<T, NeovimError> {
+    fn from(val: NvimExecuteLuaResult<T>) -> Self {
+        use NvimExecuteLuaResult::*;
+        match val {
+            Ok(result) => Result::Ok(result),
+            Error(msg) => Err(NeovimError::Api(msg)),
+            LspError { message, code } => Err(NeovimError::Lsp { code, message }),
+        }
     }
 }
 
 impl NeovimClient<Connection> {
     #[instrument(skip(self))]
     pub async fn connect_path(&mut self, path: &str) -> Result<(), NeovimError> {
-        if self.connection.is_some() {
+        if let Some(connection) = &self.connection {
             return Err(NeovimError::Connection(format!(
                 "Already connected to {}. Disconnect first.",
-                self.connection.as_ref().unwrap().target()
+                connection.target()
             )));
         }
 
         debug!("Attempting to connect to Neovim at {}", path);
         let handler = NeovimHandler::new();
+        let responses = handler.responses();
+        let buffer_changes = handler.buffer_changes();
         match create::new_path(path, handler).await {
             Ok((nvim, io_handler)) => {
                 let connection = NeovimConnection::new(
@@ -1128,6 +2040,8 @@ impl NeovimClient<Connection> {
                         rv
                     }),
                     path.to_string(),
+                    responses,
+                    buffer_changes,
                 );
                 self.connection = Some(connection);
                 debug!("Successfully connected to Neovim at {}", path);
@@ -1139,20 +2053,101 @@ impl NeovimClient<Connection> {
             }
         }
     }
+
+    /// Spawn a headless Neovim listening on `path` and connect to it.
+    ///
+    /// The spawned process is owned by the connection and killed on `disconnect`
+    /// or when the client is dropped.
+    #[instrument(skip(self))]
+    pub async fn spawn_path(
+        &mut self,
+        path: &str,
+        cwd: &Path,
+        init_file: Option<&Path>,
+    ) -> Result<u32, NeovimError> {
+        if let Some(connection) = &self.connection {
+            return Err(NeovimError::Connection(format!(
+                "Already connected to {}. Disconnect first.",
+                connection.target()
+            )));
+        }
+
+        let mut cmd = tokio::process::Command::new("nvim");
+        cmd.args(["--headless", "--listen", path])
+            .current_dir(cwd)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true);
+        if let Some(init_file) = init_file {
+            cmd.arg("-u").arg(init_file);
+        }
+
+        debug!("Spawning headless Neovim at {}", path);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| NeovimError::Connection(format!("Failed to spawn Neovim: {e}")))?;
+        let pid = child.id().unwrap_or_default();
+
+        // Wait for Neovim to start listening on the socket
+        let start = std::time::Instant::now();
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(NeovimError::Connection(format!(
+                    "Neovim exited before listening at {path}: {status}"
+                )));
+            }
+
+            #[cfg(unix)]
+            let ready = Connection::connect(path).await.is_ok();
+            #[cfg(windows)]
+            let ready = tokio::net::windows::named_pipe::ClientOptions::new()
+                .open(path)
+                .is_ok();
+            if ready {
+                break;
+            }
+
+            if start.elapsed() >= std::time::Duration::from_secs(10) {
+                let _ = child.kill().await;
+                #[cfg(unix)]
+                let _ = std::fs::remove_file(path);
+                return Err(NeovimError::Connection(format!(
+                    "Neovim failed to start within 10 seconds at {path}"
+                )));
+            }
+        }
+
+        if let Err(e) = self.connect_path(path).await {
+            let _ = child.kill().await;
+            #[cfg(unix)]
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+        if let Some(connection) = self.connection.as_mut() {
+            connection.process = Some(child);
+        }
+        info!("Spawned headless Neovim (pid {pid}) at {path}");
+        Ok(pid)
+    }
 }
 
 impl NeovimClient<TcpStream> {
     #[instrument(skip(self))]
     pub async fn connect_tcp(&mut self, address: &str) -> Result<(), NeovimError> {
-        if self.connection.is_some() {
+        if let Some(connection) = &self.connection {
             return Err(NeovimError::Connection(format!(
                 "Already connected to {}. Disconnect first.",
-                self.connection.as_ref().unwrap().target()
+                connection.target()
             )));
         }
 
         debug!("Attempting to connect to Neovim at {}", address);
         let handler = NeovimHandler::new();
+        let responses = handler.responses();
+        let buffer_changes = handler.buffer_changes();
         match create::new_tcp(address, handler).await {
             Ok((nvim, io_handler)) => {
                 let connection = NeovimConnection::new(
@@ -1163,6 +2158,8 @@ impl NeovimClient<TcpStream> {
                         rv
                     }),
                     address.to_string(),
+                    responses,
+                    buffer_changes,
                 );
                 self.connection = Some(connection);
                 debug!("Successfully connected to Neovim at {}", address);
@@ -1181,7 +2178,99 @@ where
     T: AsyncWrite + Send + 'static,
 {
     pub fn new() -> Self {
-        Self { connection: None }
+        Self {
+            connection: None,
+            lsp_config: LspConfig::default(),
+        }
+    }
+
+    /// Set the LSP settings used for requests, e.g. timeouts
+    pub fn set_lsp_config(&mut self, lsp_config: LspConfig) {
+        self.lsp_config = lsp_config;
+    }
+
+    /// Timeout for an LSP request: the one given to the tool call, the configured one,
+    /// or the request-specific default
+    fn lsp_timeout_ms(&self, client_name: &str, method: &str, default: u64) -> u64 {
+        LSP_TIMEOUT_MS
+            .try_with(|timeout_ms| *timeout_ms)
+            .ok()
+            .or_else(|| self.lsp_config.request_timeout_ms(client_name, method))
+            .unwrap_or(default)
+    }
+
+    /// Send an LSP request through Neovim and wait for the response.
+    ///
+    /// The request is sent with `client:request`, so the editor stays responsive while
+    /// the server works. The response arrives as an `NVIM_MCP_LspResponse` notification
+    /// with the same JSON shape as the other Lua helpers.
+    async fn lsp_request(
+        &self,
+        client_name: &str,
+        method: &str,
+        params: String,
+        bufnr: u64,
+        default_timeout_ms: u64,
+    ) -> Result<Value, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let timeout_ms = self.lsp_timeout_ms(client_name, method, default_timeout_ms);
+        let response_id = NEXT_RESPONSE_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        conn.responses.insert(response_id, sender);
+        let mut pending = PendingResponse {
+            response_id,
+            cancel: include_str!("lua/lsp_cancel_request.lua"),
+            nvim: conn.nvim.clone(),
+            responses: conn.responses.clone(),
+            sent: false,
+            completed: false,
+        };
+
+        let sent = conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/lsp_request.lua"),
+                vec![
+                    Value::from(client_name), // client_name
+                    Value::from(method),      // method
+                    Value::from(params),      // params
+                    Value::from(bufnr),       // bufnr
+                    Value::from(response_id), // response_id
+                ],
+            )
+            .await
+            .map_err(|e| NeovimError::Api(format!("Failed to send LSP request {method}: {e}")))?;
+
+        match serde_json::from_str::<NvimExecuteLuaResult<u64>>(sent.as_str().unwrap_or_default()) {
+            Ok(NvimExecuteLuaResult::Ok(request_id)) => {
+                debug!("Sent LSP request {method} ({request_id}), response {response_id}");
+                pending.sent = true;
+            }
+            // The request could not be sent, pass the error on to the caller
+            Ok(_) => return Ok(sent),
+            Err(e) => {
+                return Err(NeovimError::Api(format!(
+                    "Failed to parse LSP request result: {e}"
+                )));
+            }
+        }
+
+        match tokio::time::timeout(Duration::from_millis(timeout_ms), receiver).await {
+            Ok(Ok(response)) => {
+                pending.completed = true;
+                Ok(Value::from(response))
+            }
+            Ok(Err(_)) => Err(NeovimError::Connection(format!(
+                "Connection closed while waiting for LSP request {method}"
+            ))),
+            Err(_) => Err(NeovimError::Timeout {
+                method: method.to_string(),
+                timeout_ms,
+            }),
+        }
     }
 
     #[instrument(skip(self))]
@@ -1259,9 +2348,40 @@ where
         }
     }
 
-    /// Get project root directory from Neovim
+    /// Run a script attaching to or detaching from a buffer
+    async fn buffer_attachment(&self, script: &str, buffer_id: u64) -> Result<(), NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(script, vec![Value::from(buffer_id)])
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<()>>(result.as_str().unwrap()) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse buffer attachment result: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse buffer attachment result: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to attach to buffer {buffer_id}: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to attach to buffer {buffer_id}: {e}"
+                )))
+            }
+        }
+    }
+
+    /// Get the working directory of Neovim, `project_relative_path` is resolved against it
     #[instrument(skip(self))]
-    async fn get_project_root(&self) -> Result<PathBuf, NeovimError> {
+    async fn project_cwd(&self) -> Result<PathBuf, NeovimError> {
         let conn = self.connection.as_ref().ok_or_else(|| {
             NeovimError::Connection("Not connected to any Neovim instance".to_string())
         })?;
@@ -1296,7 +2416,7 @@ where
             }
             DocumentIdentifier::ProjectRelativePath(rel_path) => {
                 // Get project root from Neovim
-                let project_root = self.get_project_root().await?;
+                let project_root = self.project_cwd().await?;
                 let absolute_path = project_root.join(rel_path);
                 make_text_document_identifier_from_path(absolute_path)
             }
@@ -1324,6 +2444,14 @@ where
         if let Some(connection) = self.connection.take() {
             let target = connection.target().to_string();
             connection.io_handler.abort();
+            if let Some(mut process) = connection.process {
+                if let Err(e) = process.kill().await {
+                    debug!("Failed to kill spawned Neovim process: {}", e);
+                }
+                #[cfg(unix)]
+                let _ = std::fs::remove_file(&target);
+                debug!("Terminated spawned Neovim process at {}", target);
+            }
             debug!("Successfully disconnected from Neovim at {}", target);
             Ok(target)
         } else {
@@ -1385,6 +2513,187 @@ where
         }
     }
 
+    #[instrument(skip(self))]
+    async fn exec_command(&self, command: &str) -> Result<String, NeovimError> {
+        if command.trim().is_empty() {
+            return Err(NeovimError::Api("Command cannot be empty".to_string()));
+        }
+
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .exec2(command, vec![(Value::from("output"), Value::from(true))])
+            .await
+        {
+            Ok(result) => Ok(result
+                .into_iter()
+                .find(|(key, _)| key.as_str() == Some("output"))
+                .and_then(|(_, output)| output.as_str().map(str::to_string))
+                .unwrap_or_default()),
+            Err(e) => {
+                debug!("Command execution failed: {e}");
+                match *e {
+                    CallError::NeovimError(_, message) => {
+                        Err(NeovimError::from_vim_message(&message))
+                    }
+                    e => Err(NeovimError::Api(format!("Command execution failed: {e}"))),
+                }
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn execute_lua_sandboxed(
+        &self,
+        code: &str,
+        allowed_api: Option<&[String]>,
+        denied_names: &[String],
+    ) -> Result<Value, NeovimError> {
+        debug!("Executing sandboxed Lua code: {}", code);
+
+        if code.trim().is_empty() {
+            return Err(NeovimError::Api("Lua code cannot be empty".to_string()));
+        }
+
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let to_array = |names: &[String]| {
+            Value::Array(names.iter().map(|n| Value::from(n.as_str())).collect())
+        };
+        let result = conn
+            .nvim
+            .exec_lua(
+                include_str!("lua/exec_lua_sandboxed.lua"),
+                vec![
+                    Value::from(code),                                       // code
+                    allowed_api.map(to_array).unwrap_or(Value::from(false)), // allowed_api
+                    to_array(denied_names),                                  // denied_names
+                ],
+            )
+            .await
+            .map_err(|e| {
+                debug!("Lua execution failed: {e}");
+                NeovimError::Api(format!("Lua execution failed: {e}"))
+            })?;
+
+        let field = |key: &str| {
+            result.as_map().and_then(|map| {
+                map.iter()
+                    .find(|(k, _)| k.as_str() == Some(key))
+                    .map(|(_, v)| v.clone())
+            })
+        };
+        if let Some(name) = field("violation") {
+            return Err(NeovimError::PolicyViolation {
+                rule: "sandbox".to_string(),
+                detail: format!("`{}` is not allowed", name.as_str().unwrap_or_default()),
+            });
+        }
+        if let Some(message) = field("err_msg") {
+            return Err(NeovimError::Api(format!(
+                "Lua execution failed: {}",
+                message.as_str().unwrap_or_default()
+            )));
+        }
+        Ok(field("result").unwrap_or(Value::Nil))
+    }
+
+    #[instrument(skip(self))]
+    async fn confirm(
+        &self,
+        title: &str,
+        preview: ConfirmPreview,
+        timeout_ms: u64,
+    ) -> Result<(), NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let (kind, payload, client_name) = match preview {
+            ConfirmPreview::WorkspaceEdit {
+                client_name,
+                workspace_edit,
+            } => (
+                "workspace_edit".to_string(),
+                serde_json::to_string(&workspace_edit).map_err(|e| {
+                    NeovimError::Api(format!("Failed to serialize workspace edit: {e}"))
+                })?,
+                Value::from(client_name),
+            ),
+            ConfirmPreview::Code { filetype, code } => (filetype, code, Value::from(false)),
+        };
+
+        let response_id = NEXT_RESPONSE_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        conn.responses.insert(response_id, sender);
+        let mut pending = PendingResponse {
+            response_id,
+            cancel: include_str!("lua/confirm_cancel.lua"),
+            nvim: conn.nvim.clone(),
+            responses: conn.responses.clone(),
+            sent: false,
+            completed: false,
+        };
+
+        let shown = conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/confirm.lua"),
+                vec![
+                    Value::from(response_id), // response_id
+                    Value::from(title),       // title
+                    Value::from(kind),        // kind
+                    Value::from(payload),     // payload
+                    client_name,              // client_name
+                ],
+            )
+            .await
+            .map_err(|e| NeovimError::Api(format!("Failed to ask for confirmation: {e}")))?;
+        let denied = |reason: String| NeovimError::ConfirmationDenied {
+            action: title.to_string(),
+            reason,
+        };
+        match serde_json::from_str::<NvimExecuteLuaResult<bool>>(shown.as_str().unwrap_or_default())
+        {
+            Ok(NvimExecuteLuaResult::Ok(true)) => pending.sent = true,
+            Ok(NvimExecuteLuaResult::Ok(false)) => return Err(denied(NO_UI_REASON.to_string())),
+            Ok(result) => return Result::<bool, NeovimError>::from(result).map(|_| ()),
+            Err(e) => {
+                return Err(NeovimError::Api(format!(
+                    "Failed to parse confirmation prompt result: {e}"
+                )));
+            }
+        }
+
+        let response = match tokio::time::timeout(Duration::from_millis(timeout_ms), receiver).await
+        {
+            Ok(Ok(response)) => {
+                pending.completed = true;
+                response
+            }
+            Ok(Err(_)) => {
+                return Err(NeovimError::Connection(
+                    "Connection closed while waiting for confirmation".to_string(),
+                ));
+            }
+            Err(_) => return Err(denied(format!("no answer within {timeout_ms}ms"))),
+        };
+
+        match serde_json::from_str::<NvimExecuteLuaResult<bool>>(&response) {
+            Ok(NvimExecuteLuaResult::Ok(true)) => Ok(()),
+            Ok(NvimExecuteLuaResult::Ok(false)) => Err(denied("denied by the user".to_string())),
+            Ok(result) => Result::<bool, NeovimError>::from(result).map(|_| ()),
+            Err(e) => Err(NeovimError::Api(format!(
+                "Failed to parse confirmation response: {e}"
+            ))),
+        }
+    }
+
     #[instrument(skip(self))]
     async fn setup_diagnostics_changed_autocmd(&self) -> Result<(), NeovimError> {
         debug!("Setting up diagnostics changed autocmd");
@@ -1422,108 +2731,721 @@ where
     }
 
     #[instrument(skip(self))]
-    async fn lsp_get_clients(&self) -> Result<Vec<LspClient>, NeovimError> {
-        debug!("Getting LSP clients");
-
+    async fn get_filetype(&self, document: &DocumentIdentifier) -> Result<String, NeovimError> {
         let conn = self.connection.as_ref().ok_or_else(|| {
             NeovimError::Connection("Not connected to any Neovim instance".to_string())
         })?;
 
         match conn
             .nvim
-            .execute_lua(include_str!("lua/lsp_get_clients.lua"), vec![])
+            .execute_lua(
+                include_str!("lua/get_filetype.lua"),
+                vec![Value::from(serde_json::to_string(document).map_err(
+                    |e| NeovimError::Api(format!("Failed to serialize document identifier: {e}")),
+                )?)],
+            )
             .await
         {
-            Ok(clients) => {
-                debug!("LSP clients retrieved successfully");
-                let clients: Vec<LspClient> = match serde_json::from_str(clients.as_str().unwrap())
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<String>>(result.as_str().unwrap())
                 {
-                    Ok(d) => d,
+                    Ok(rv) => rv.into(),
                     Err(e) => {
-                        debug!("Failed to parse clients: {}", e);
-                        return Err(NeovimError::Api(format!("Failed to parse clients: {e}")));
+                        debug!("Failed to parse filetype result: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse filetype result: {e}"
+                        )))
                     }
-                };
-                debug!("Found {} clients", clients.len());
-                Ok(clients)
+                }
             }
             Err(e) => {
-                debug!("Failed to get LSP clients: {}", e);
-                Err(NeovimError::Api(format!("Failed to get LSP clients: {e}")))
+                debug!("Failed to get filetype: {}", e);
+                Err(NeovimError::Api(format!("Failed to get filetype: {e}")))
             }
         }
     }
 
     #[instrument(skip(self))]
-    async fn lsp_get_code_actions(
-        &self,
-        client_name: &str,
-        document: DocumentIdentifier,
-        range: Range,
-    ) -> Result<Vec<CodeAction>, NeovimError> {
-        let text_document = self.resolve_text_document_identifier(&document).await?;
+    async fn get_project_root(&self) -> Result<ProjectRoot, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
 
-        let diagnostics = match &document {
-            DocumentIdentifier::BufferId(buffer_id) => self
-                .get_buffer_diagnostics(*buffer_id)
-                .await
-                .map_err(|e| NeovimError::Api(format!("Failed to get diagnostics: {e}")))?,
-            _ => {
-                // For path-based identifiers, diagnostics might not be available
-                Vec::new()
+        match conn
+            .nvim
+            .execute_lua(include_str!("lua/get_project_root.lua"), vec![])
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<ProjectRoot>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse project root result: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse project root result: {e}"
+                        )))
+                    }
+                }
             }
-        };
+            Err(e) => {
+                debug!("Failed to get project root: {}", e);
+                Err(NeovimError::Api(format!("Failed to get project root: {e}")))
+            }
+        }
+    }
 
+    #[instrument(skip(self))]
+    async fn read_document(
+        &self,
+        document: &DocumentIdentifier,
+    ) -> Result<DocumentText, NeovimError> {
         let conn = self.connection.as_ref().ok_or_else(|| {
             NeovimError::Connection("Not connected to any Neovim instance".to_string())
         })?;
 
-        // Get buffer ID for Lua execution (needed for some LSP operations)
-        let buffer_id = match &document {
-            DocumentIdentifier::BufferId(id) => *id,
-            _ => 0, // Use buffer 0 as fallback for path-based operations
+        match conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/read_document.lua"),
+                vec![Value::from(serde_json::to_string(document).map_err(
+                    |e| NeovimError::Api(format!("Failed to serialize document identifier: {e}")),
+                )?)],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<DocumentText>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse document text: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse document text: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to read document: {}", e);
+                Err(NeovimError::Api(format!("Failed to read document: {e}")))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cursor(&self) -> Result<CursorPosition, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(include_str!("lua/get_cursor.lua"), vec![])
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<CursorPosition>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse cursor position: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse cursor position: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to get cursor position: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to get cursor position: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_selection(&self) -> Result<Selection, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(include_str!("lua/get_selection.lua"), vec![])
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<Selection>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse selection: {}", e);
+                        Err(NeovimError::Api(format!("Failed to parse selection: {e}")))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to get selection: {}", e);
+                Err(NeovimError::Api(format!("Failed to get selection: {e}")))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cursor_context(&self, context_lines: u64) -> Result<CursorContext, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/get_cursor_context.lua"),
+                vec![Value::from(context_lines)],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<CursorContext>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse cursor context: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse cursor context: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to get cursor context: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to get cursor context: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_quickfix_list(&self, window_id: Option<u64>) -> Result<QuickfixList, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/get_quickfix_list.lua"),
+                vec![window_id.map_or(Value::Nil, Value::from)],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<QuickfixList>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse quickfix list: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse quickfix list: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to get quickfix list: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to get quickfix list: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self, entries))]
+    async fn set_quickfix_list(
+        &self,
+        window_id: Option<u64>,
+        title: &str,
+        entries: &[QuickfixEntry],
+        action: QuickfixAction,
+        open: bool,
+    ) -> Result<(), NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let entries = serde_json::to_string(entries)
+            .map_err(|e| NeovimError::Api(format!("Failed to serialize quickfix entries: {e}")))?;
+        match conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/set_quickfix_list.lua"),
+                vec![
+                    window_id.map_or(Value::Nil, Value::from),
+                    Value::from(title),
+                    Value::from(entries),
+                    Value::from(action.as_flag()),
+                    Value::from(open),
+                ],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<()>>(result.as_str().unwrap()) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse quickfix list result: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse quickfix list result: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to set quickfix list: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to set quickfix list: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn search_workspace(&self, options: &SearchOptions) -> Result<SearchResult, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let options = serde_json::to_string(options)
+            .map_err(|e| NeovimError::Api(format!("Failed to serialize search options: {e}")))?;
+
+        // ripgrep runs in the background, the result arrives as an
+        // `NVIM_MCP_SearchResponse` notification
+        let response_id = NEXT_RESPONSE_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        conn.responses.insert(response_id, sender);
+        let mut pending = PendingResponse {
+            response_id,
+            cancel: include_str!("lua/search_cancel.lua"),
+            nvim: conn.nvim.clone(),
+            responses: conn.responses.clone(),
+            sent: false,
+            completed: false,
         };
 
+        let started = conn
+            .nvim
+            .execute_lua(
+                include_str!("lua/search_workspace.lua"),
+                vec![Value::from(options), Value::from(response_id)],
+            )
+            .await
+            .map_err(|e| {
+                debug!("Failed to search workspace: {}", e);
+                NeovimError::Api(format!("Failed to search workspace: {e}"))
+            })?;
+        match serde_json::from_str::<NvimExecuteLuaResult<Option<()>>>(
+            started.as_str().unwrap_or_default(),
+        ) {
+            Ok(rv) => {
+                Result::<Option<()>, NeovimError>::from(rv)?;
+                pending.sent = true;
+            }
+            Err(e) => {
+                return Err(NeovimError::Api(format!(
+                    "Failed to parse search start result: {e}"
+                )));
+            }
+        }
+
+        let response = receiver.await.map_err(|_| {
+            NeovimError::Connection(
+                "Connection closed while waiting for search results".to_string(),
+            )
+        })?;
+        pending.completed = true;
+        match serde_json::from_str::<NvimExecuteLuaResult<SearchResult>>(&response) {
+            Ok(rv) => rv.into(),
+            Err(e) => {
+                debug!("Failed to parse search result: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to parse search result: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn find_files(&self, query: &str, max_results: u64) -> Result<FoundFiles, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        // Same root as the other tools resolve project relative paths against
+        let project_root = self.get_project_root().await?;
         match conn
             .nvim
             .execute_lua(
-                include_str!("lua/lsp_client_get_code_actions.lua"),
+                include_str!("lua/find_files.lua"),
                 vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&CodeActionParams {
-                            text_document,
-                            range,
-                            context: CodeActionContext {
-                                diagnostics: diagnostics
-                                    .into_iter()
-                                    .filter_map(|d| d.user_data.map(|u| u.lsp))
-                                    .collect(),
-                                only: None,
-                                trigger_kind: None,
-                            },
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                    Value::from(buffer_id),   // bufnr
+                    Value::from(query),
+                    Value::from(max_results),
+                    Value::from(project_root.cwd.to_string_lossy().as_ref()),
+                    Value::from(project_root.root.to_string_lossy().as_ref()),
+                ],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<FoundFiles>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse found files: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse found files: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to find files: {}", e);
+                Err(NeovimError::Api(format!("Failed to find files: {e}")))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn treesitter_query(
+        &self,
+        document: &DocumentIdentifier,
+        query: &str,
+        start_line: Option<u64>,
+        end_line: Option<u64>,
+        max_results: u64,
+    ) -> Result<TreesitterCaptures, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let document = serde_json::to_string(document).map_err(|e| {
+            NeovimError::Api(format!("Failed to serialize document identifier: {e}"))
+        })?;
+        match conn
+            .nvim
+            .execute_lua(
+                concat!(
+                    include_str!("lua/treesitter_document.lua"),
+                    include_str!("lua/treesitter_query.lua")
+                ),
+                vec![
+                    Value::from(document),
+                    Value::from(query),
+                    start_line.map_or(Value::Nil, Value::from),
+                    end_line.map_or(Value::Nil, Value::from),
+                    Value::from(max_results),
                 ],
             )
             .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<TreesitterCaptures>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse tree-sitter captures: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse tree-sitter captures: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to run tree-sitter query: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to run tree-sitter query: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn treesitter_node_at(
+        &self,
+        document: &DocumentIdentifier,
+        position: &Position,
+    ) -> Result<TreesitterAncestry, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let document = serde_json::to_string(document).map_err(|e| {
+            NeovimError::Api(format!("Failed to serialize document identifier: {e}"))
+        })?;
+        match conn
+            .nvim
+            .execute_lua(
+                concat!(
+                    include_str!("lua/treesitter_document.lua"),
+                    include_str!("lua/treesitter_node_at.lua")
+                ),
+                vec![
+                    Value::from(document),
+                    Value::from(position.line),
+                    Value::from(position.character),
+                ],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<TreesitterAncestry>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => rv.into(),
+                    Err(e) => {
+                        debug!("Failed to parse tree-sitter nodes: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse tree-sitter nodes: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to get tree-sitter node: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to get tree-sitter node: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn treesitter_outline(
+        &self,
+        document: &DocumentIdentifier,
+    ) -> Result<DocumentOutline, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let document = serde_json::to_string(document).map_err(|e| {
+            NeovimError::Api(format!("Failed to serialize document identifier: {e}"))
+        })?;
+        match conn
+            .nvim
+            .execute_lua(
+                concat!(
+                    include_str!("lua/treesitter_document.lua"),
+                    include_str!("lua/treesitter_outline.lua")
+                ),
+                vec![Value::from(document)],
+            )
+            .await
+        {
+            Ok(result) => {
+                match serde_json::from_str::<NvimExecuteLuaResult<TreesitterOutline>>(
+                    result.as_str().unwrap(),
+                ) {
+                    Ok(rv) => Result::from(rv).map(DocumentOutline::from),
+                    Err(e) => {
+                        debug!("Failed to parse tree-sitter outline: {}", e);
+                        Err(NeovimError::Api(format!(
+                            "Failed to parse tree-sitter outline: {e}"
+                        )))
+                    }
+                }
+            }
+            Err(e) => {
+                debug!("Failed to build tree-sitter outline: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to build tree-sitter outline: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_editor_state(&self) -> Result<EditorState, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        let mut response = conn
+            .nvim
+            .call_atomic(EditorState::atomic_calls())
+            .await
+            .map_err(|e| NeovimError::Api(format!("Failed to get editor state: {e}")))?;
+        // The response is `[results, error]`, error is nil when every call succeeded
+        let error = response.pop().unwrap_or(Value::Nil);
+        if !error.is_nil() {
+            return Err(NeovimError::Api(format!(
+                "Failed to get editor state: {}",
+                msgpack_to_json(error)
+            )));
+        }
+        let results = match response.pop() {
+            Some(Value::Array(results)) => results.into_iter().map(msgpack_to_json).collect(),
+            other => {
+                return Err(NeovimError::Api(format!(
+                    "Unexpected editor state response: {other:?}"
+                )));
+            }
+        };
+        EditorState::from_atomic_results(results)
+    }
+
+    fn subscribe_buffer_changes(&self) -> Option<broadcast::Receiver<u64>> {
+        self.connection
+            .as_ref()
+            .map(|conn| conn.buffer_changes.subscribe())
+    }
+
+    #[instrument(skip(self))]
+    async fn attach_buffer(&self, buffer_id: u64) -> Result<(), NeovimError> {
+        self.buffer_attachment(include_str!("lua/buffer_attach.lua"), buffer_id)
+            .await
+    }
+
+    #[instrument(skip(self))]
+    async fn detach_buffer(&self, buffer_id: u64) -> Result<(), NeovimError> {
+        self.buffer_attachment(include_str!("lua/buffer_detach.lua"), buffer_id)
+            .await
+    }
+
+    #[instrument(skip(self))]
+    async fn get_unsaved_changes(&self) -> Result<Vec<UnsavedChange>, NeovimError> {
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(include_str!("lua/get_unsaved_changes.lua"), vec![])
+            .await
+        {
+            Ok(result) => {
+                let changes: Result<Option<Vec<UnsavedChange>>, NeovimError> =
+                    serde_json::from_str::<NvimExecuteLuaResult<Option<Vec<UnsavedChange>>>>(
+                        result.as_str().unwrap(),
+                    )
+                    .map_err(|e| NeovimError::Api(format!("Failed to parse unsaved changes: {e}")))?
+                    .into();
+                Ok(changes?.unwrap_or_default())
+            }
+            Err(e) => {
+                debug!("Failed to get unsaved changes: {}", e);
+                Err(NeovimError::Api(format!(
+                    "Failed to get unsaved changes: {e}"
+                )))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn lsp_get_clients(&self) -> Result<Vec<LspClient>, NeovimError> {
+        debug!("Getting LSP clients");
+
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            NeovimError::Connection("Not connected to any Neovim instance".to_string())
+        })?;
+
+        match conn
+            .nvim
+            .execute_lua(include_str!("lua/lsp_get_clients.lua"), vec![])
+            .await
+        {
+            Ok(clients) => {
+                debug!("LSP clients retrieved successfully");
+                let clients: Vec<LspClient> = match serde_json::from_str(clients.as_str().unwrap())
+                {
+                    Ok(d) => d,
+                    Err(e) => {
+                        debug!("Failed to parse clients: {}", e);
+                        return Err(NeovimError::Api(format!("Failed to parse clients: {e}")));
+                    }
+                };
+                debug!("Found {} clients", clients.len());
+                Ok(clients)
+            }
+            Err(e) => {
+                debug!("Failed to get LSP clients: {}", e);
+                Err(NeovimError::Api(format!("Failed to get LSP clients: {e}")))
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn lsp_get_code_actions(
+        &self,
+        client_name: &str,
+        document: DocumentIdentifier,
+        range: Range,
+    ) -> Result<Vec<CodeAction>, NeovimError> {
+        let text_document = self.resolve_text_document_identifier(&document).await?;
+
+        let diagnostics = match &document {
+            DocumentIdentifier::BufferId(buffer_id) => self
+                .get_buffer_diagnostics(*buffer_id)
+                .await
+                .map_err(|e| NeovimError::Api(format!("Failed to get diagnostics: {e}")))?,
+            _ => {
+                // For path-based identifiers, diagnostics might not be available
+                Vec::new()
+            }
+        };
+
+        // Get buffer ID for Lua execution (needed for some LSP operations)
+        let buffer_id = match &document {
+            DocumentIdentifier::BufferId(id) => *id,
+            _ => 0, // Use buffer 0 as fallback for path-based operations
+        };
+
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/codeAction",
+                serde_json::to_string(&CodeActionParams {
+                    text_document,
+                    range,
+                    context: CodeActionContext {
+                        diagnostics: diagnostics
+                            .into_iter()
+                            .filter_map(|d| d.user_data.map(|u| u.lsp))
+                            .collect(),
+                        only: None,
+                        trigger_kind: None,
+                    },
+                })
+                .unwrap(),
+                buffer_id,
+                1000,
+            )
+            .await
         {
             Ok(actions) => {
-                let actions = serde_json::from_str::<CodeActionResult>(actions.as_str().unwrap())
-                    .map_err(|e| {
-                    NeovimError::Api(format!("Failed to parse code actions: {e}"))
-                })?;
-                debug!("Found {} code actions", actions.result.len());
-                Ok(actions.result)
+                let actions: Result<Option<Vec<CodeAction>>, NeovimError> =
+                    serde_json::from_str::<NvimExecuteLuaResult<Option<Vec<CodeAction>>>>(
+                        actions.as_str().unwrap(),
+                    )
+                    .map_err(|e| NeovimError::Api(format!("Failed to parse code actions: {e}")))?
+                    .into();
+                let actions = actions?.unwrap_or_default();
+                debug!("Found {} code actions", actions.len());
+                Ok(actions)
             }
             Err(e) => {
                 debug!("Failed to get LSP code actions: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get LSP code actions: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1537,32 +3459,23 @@ where
     ) -> Result<HoverResult, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         // Get buffer ID for Lua execution (needed for some LSP operations)
         let buffer_id = match &document {
             DocumentIdentifier::BufferId(id) => *id,
             _ => 0, // Use buffer 0 as fallback for path-based operations
         };
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_hover.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&TextDocumentPositionParams {
-                            text_document,
-                            position,
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                    Value::from(buffer_id),   // bufnr
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/hover",
+                serde_json::to_string(&TextDocumentPositionParams {
+                    text_document,
+                    position,
+                })
+                .unwrap(),
+                buffer_id,
+                1000,
             )
             .await
         {
@@ -1581,7 +3494,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get LSP hover: {}", e);
-                Err(NeovimError::Api(format!("Failed to get LSP hover: {e}")))
+                Err(e)
             }
         }
     }
@@ -1594,28 +3507,19 @@ where
     ) -> Result<Option<DocumentSymbolResult>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         // Get buffer ID for Lua execution (needed for some LSP operations)
         let buffer_id = match &document {
             DocumentIdentifier::BufferId(id) => *id,
             _ => 0, // Use buffer 0 as fallback for path-based operations
         };
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_document_symbols.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&DocumentSymbolParams { text_document }).unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                    Value::from(buffer_id),   // bufnr
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/documentSymbol",
+                serde_json::to_string(&DocumentSymbolParams { text_document }).unwrap(),
+                buffer_id,
+                1000,
             )
             .await
         {
@@ -1634,9 +3538,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get document symbols: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get document symbols: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1647,24 +3549,16 @@ where
         client_name: &str,
         query: &str,
     ) -> Result<WorkspaceSymbolResult, NeovimError> {
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_workspace_symbols.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&WorkspaceSymbolParams {
-                            query: query.to_string(),
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "workspace/symbol",
+                serde_json::to_string(&WorkspaceSymbolParams {
+                    query: query.to_string(),
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -1683,9 +3577,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get workspace symbols: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get workspace symbols: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1700,35 +3592,26 @@ where
     ) -> Result<Vec<Location>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         // Get buffer ID for Lua execution (needed for some LSP operations)
         let buffer_id = match &document {
             DocumentIdentifier::BufferId(id) => *id,
             _ => 0, // Use buffer 0 as fallback for path-based operations
         };
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_references.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&ReferenceParams {
-                            text_document,
-                            position,
-                            context: ReferenceContext {
-                                include_declaration,
-                            },
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                    Value::from(buffer_id),   // bufnr
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/references",
+                serde_json::to_string(&ReferenceParams {
+                    text_document,
+                    position,
+                    context: ReferenceContext {
+                        include_declaration,
+                    },
+                })
+                .unwrap(),
+                buffer_id,
+                1000,
             )
             .await
         {
@@ -1750,9 +3633,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get LSP references: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get LSP references: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1766,25 +3647,17 @@ where
     ) -> Result<Option<LocateResult>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_definition.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&TextDocumentPositionParams {
-                            text_document,
-                            position,
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/definition",
+                serde_json::to_string(&TextDocumentPositionParams {
+                    text_document,
+                    position,
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -1803,9 +3676,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get LSP definition: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get LSP definition: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1819,25 +3690,17 @@ where
     ) -> Result<Option<LocateResult>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_type_definition.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&TextDocumentPositionParams {
-                            text_document,
-                            position,
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/typeDefinition",
+                serde_json::to_string(&TextDocumentPositionParams {
+                    text_document,
+                    position,
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -1856,9 +3719,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get LSP type definition: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get LSP type definition: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1872,25 +3733,17 @@ where
     ) -> Result<Option<LocateResult>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_implementation.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&TextDocumentPositionParams {
-                            text_document,
-                            position,
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/implementation",
+                serde_json::to_string(&TextDocumentPositionParams {
+                    text_document,
+                    position,
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -1909,9 +3762,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get LSP implementation: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get LSP implementation: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1925,25 +3776,17 @@ where
     ) -> Result<Option<LocateResult>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_declaration.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&TextDocumentPositionParams {
-                            text_document,
-                            position,
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/declaration",
+                serde_json::to_string(&TextDocumentPositionParams {
+                    text_document,
+                    position,
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -1962,9 +3805,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to get LSP declaration: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get LSP declaration: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -1975,22 +3816,15 @@ where
         client_name: &str,
         code_action: CodeAction,
     ) -> Result<CodeAction, NeovimError> {
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_resolve_code_action.lua"),
-                vec![
-                    Value::from(client_name),
-                    Value::from(serde_json::to_string(&code_action).map_err(|e| {
-                        NeovimError::Api(format!("Failed to serialize code action: {e}"))
-                    })?),
-                    Value::from(5000), // timeout_ms
-                    Value::from(0),    // bufnr (not needed for this request)
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "codeAction/resolve",
+                serde_json::to_string(&code_action).map_err(|e| {
+                    NeovimError::Api(format!("Failed to serialize code action: {e}"))
+                })?,
+                0,
+                5000,
             )
             .await
         {
@@ -2009,9 +3843,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to resolve LSP code action: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to resolve LSP code action: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -2068,31 +3900,22 @@ where
     ) -> Result<Option<PrepareRenameResult>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         let buffer_id = match &document {
             DocumentIdentifier::BufferId(id) => *id,
             _ => 0,
         };
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_prepare_rename.lua"),
-                vec![
-                    Value::from(client_name),
-                    Value::from(
-                        serde_json::to_string(&TextDocumentPositionParams {
-                            text_document,
-                            position,
-                        })
-                        .unwrap(),
-                    ),
-                    Value::from(1000),
-                    Value::from(buffer_id),
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/prepareRename",
+                serde_json::to_string(&TextDocumentPositionParams {
+                    text_document,
+                    position,
+                })
+                .unwrap(),
+                buffer_id,
+                1000,
             )
             .await
         {
@@ -2111,7 +3934,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to prepare rename: {}", e);
-                Err(NeovimError::Api(format!("Failed to prepare rename: {e}")))
+                Err(e)
             }
         }
     }
@@ -2126,32 +3949,23 @@ where
     ) -> Result<Option<WorkspaceEdit>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         let buffer_id = match &document {
             DocumentIdentifier::BufferId(id) => *id,
             _ => 0,
         };
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_rename.lua"),
-                vec![
-                    Value::from(client_name),
-                    Value::from(
-                        serde_json::to_string(&RenameRequestParams {
-                            text_document,
-                            position,
-                            new_name: new_name.to_string(),
-                        })
-                        .unwrap(),
-                    ),
-                    Value::from(5000), // Longer timeout for rename operations
-                    Value::from(buffer_id),
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/rename",
+                serde_json::to_string(&RenameRequestParams {
+                    text_document,
+                    position,
+                    new_name: new_name.to_string(),
+                })
+                .unwrap(),
+                buffer_id,
+                5000,
             )
             .await
         {
@@ -2170,7 +3984,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to rename: {}", e);
-                Err(NeovimError::Api(format!("Failed to rename: {e}")))
+                Err(e)
             }
         }
     }
@@ -2184,10 +3998,6 @@ where
     ) -> Result<Vec<TextEdit>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         #[derive(serde::Serialize)]
         #[serde(rename_all = "camelCase")]
         struct DocumentFormattingRequest {
@@ -2195,21 +4005,17 @@ where
             options: FormattingOptions,
         }
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_formatting.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&DocumentFormattingRequest {
-                            text_document,
-                            options,
-                        })
-                        .unwrap(),
-                    ),
-                    Value::from(1000), // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/formatting",
+                serde_json::to_string(&DocumentFormattingRequest {
+                    text_document,
+                    options,
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -2231,7 +4037,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to format document: {}", e);
-                Err(NeovimError::Api(format!("Failed to format document: {e}")))
+                Err(e)
             }
         }
     }
@@ -2246,10 +4052,6 @@ where
     ) -> Result<Vec<TextEdit>, NeovimError> {
         let text_document = self.resolve_text_document_identifier(&document).await?;
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         #[derive(serde::Serialize)]
         #[serde(rename_all = "camelCase")]
         struct DocumentRangeFormattingRequest {
@@ -2258,22 +4060,18 @@ where
             options: FormattingOptions,
         }
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_range_formatting.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&DocumentRangeFormattingRequest {
-                            text_document,
-                            range,
-                            options,
-                        })
-                        .unwrap(),
-                    ),
-                    Value::from(1000), // timeout_ms
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/rangeFormatting",
+                serde_json::to_string(&DocumentRangeFormattingRequest {
+                    text_document,
+                    range,
+                    options,
+                })
+                .unwrap(),
+                0,
+                1000,
             )
             .await
         {
@@ -2295,7 +4093,7 @@ where
             }
             Err(e) => {
                 debug!("Failed to format range: {}", e);
-                Err(NeovimError::Api(format!("Failed to format range: {e}")))
+                Err(e)
             }
         }
     }
@@ -2318,49 +4116,41 @@ where
             trigger_kind: None,
         };
 
-        let conn = self.connection.as_ref().ok_or_else(|| {
-            NeovimError::Connection("Not connected to any Neovim instance".to_string())
-        })?;
-
         // Get buffer ID for Lua execution (needed for some LSP operations)
         let buffer_id = match &document {
             DocumentIdentifier::BufferId(id) => *id,
             _ => 0, // Use buffer 0 as fallback for path-based operations
         };
 
-        match conn
-            .nvim
-            .execute_lua(
-                include_str!("lua/lsp_client_get_code_actions.lua"),
-                vec![
-                    Value::from(client_name), // client_name
-                    Value::from(
-                        serde_json::to_string(&CodeActionParams {
-                            text_document,
-                            range,
-                            context,
-                        })
-                        .unwrap(),
-                    ), // params
-                    Value::from(1000),        // timeout_ms
-                    Value::from(buffer_id),   // bufnr
-                ],
+        match self
+            .lsp_request(
+                client_name,
+                "textDocument/codeAction",
+                serde_json::to_string(&CodeActionParams {
+                    text_document,
+                    range,
+                    context,
+                })
+                .unwrap(),
+                buffer_id,
+                1000,
             )
             .await
         {
             Ok(actions) => {
-                let actions = serde_json::from_str::<CodeActionResult>(actions.as_str().unwrap())
-                    .map_err(|e| {
-                    NeovimError::Api(format!("Failed to parse code actions: {e}"))
-                })?;
-                debug!("Found {} organize imports actions", actions.result.len());
-                Ok(actions.result)
+                let actions: Result<Option<Vec<CodeAction>>, NeovimError> =
+                    serde_json::from_str::<NvimExecuteLuaResult<Option<Vec<CodeAction>>>>(
+                        actions.as_str().unwrap(),
+                    )
+                    .map_err(|e| NeovimError::Api(format!("Failed to parse code actions: {e}")))?
+                    .into();
+                let actions = actions?.unwrap_or_default();
+                debug!("Found {} organize imports actions", actions.len());
+                Ok(actions)
             }
             Err(e) => {
                 debug!("Failed to get organize imports actions: {}", e);
-                Err(NeovimError::Api(format!(
-                    "Failed to get organize imports actions: {e}"
-                )))
+                Err(e)
             }
         }
     }
@@ -2458,6 +4248,376 @@ mod tests {
     use super::*;
     use serde_json;
 
+    #[tokio::test]
+    async fn test_lsp_timeout_override() {
+        let mut client = NeovimClient::<TcpStream>::new();
+        client.set_lsp_config(LspConfig {
+            timeout_ms: Some(1000),
+            ..Default::default()
+        });
+        assert_eq!(
+            client.lsp_timeout_ms("lua_ls", "textDocument/hover", 30000),
+            1000
+        );
+
+        // The timeout given to the tool call wins over the configuration
+        let timeout_ms = with_lsp_timeout(Some(50), async {
+            client.lsp_timeout_ms("lua_ls", "textDocument/hover", 30000)
+        })
+        .await;
+        assert_eq!(timeout_ms, 50);
+        let timeout_ms = with_lsp_timeout(None, async {
+            client.lsp_timeout_ms("lua_ls", "textDocument/hover", 30000)
+        })
+        .await;
+        assert_eq!(timeout_ms, 1000);
+    }
+
+    #[test]
+    fn test_document_symbol_enclosing() {
+        let range = |start: u64, end: u64| {
+            serde_json::json!({
+                "start": {"line": start, "character": 0},
+                "end": {"line": end, "character": 1}
+            })
+        };
+        let symbols: DocumentSymbolResult = serde_json::from_value(serde_json::json!([{
+            "name": "Server",
+            "kind": 5,
+            "range": range(0, 20),
+            "selectionRange": range(0, 0),
+            "children": [
+                {"name": "new", "kind": 6, "range": range(2, 5), "selectionRange": range(2, 2)},
+                {"name": "run", "kind": 6, "detail": "fn run(&self)", "range": range(7, 12), "selectionRange": range(7, 7)}
+            ]
+        }]))
+        .unwrap();
+
+        let symbol = symbols
+            .enclosing(&Position {
+                line: 8,
+                character: 4,
+            })
+            .unwrap();
+        assert_eq!(symbol.name, "run");
+        assert_eq!(symbol.detail.as_deref(), Some("fn run(&self)"));
+        assert_eq!(symbol.containers, vec!["Server"]);
+
+        let symbol = symbols
+            .enclosing(&Position {
+                line: 6,
+                character: 0,
+            })
+            .unwrap();
+        assert_eq!(symbol.name, "Server");
+        assert!(symbol.containers.is_empty());
+
+        assert!(
+            symbols
+                .enclosing(&Position {
+                    line: 30,
+                    character: 0,
+                })
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_symbol_information_enclosing() {
+        let symbol = |name: &str, start: u64, end: u64, container: Option<&str>| {
+            serde_json::json!({
+                "name": name,
+                "kind": 12,
+                "location": {
+                    "uri": "file:///tmp/main.lua",
+                    "range": {
+                        "start": {"line": start, "character": 0},
+                        "end": {"line": end, "character": 3}
+                    }
+                },
+                "containerName": container
+            })
+        };
+        let symbols: DocumentSymbolResult = serde_json::from_value(serde_json::json!([
+            symbol("outer", 0, 10, None),
+            symbol("inner", 3, 5, Some("outer")),
+        ]))
+        .unwrap();
+
+        let enclosing = symbols
+            .enclosing(&Position {
+                line: 4,
+                character: 2,
+            })
+            .unwrap();
+        assert_eq!(enclosing.name, "inner");
+        assert_eq!(enclosing.containers, vec!["outer"]);
+    }
+
+    #[test]
+    fn test_vim_error_from_message() {
+        match NeovimError::from_vim_message("Vim:E492: Not an editor command: foo") {
+            NeovimError::Vim { code, message } => {
+                assert_eq!(code.as_deref(), Some("E492"));
+                assert_eq!(message, "Not an editor command: foo");
+            }
+            e => panic!("Expected a Vim error, got {e:?}"),
+        }
+        let error = NeovimError::from_vim_message("Vim(echo):E121: Undefined variable: x");
+        assert_eq!(error.to_string(), "Vim error: E121: Undefined variable: x");
+        match NeovimError::from_vim_message("Vim:Interrupted") {
+            NeovimError::Vim { code, message } => {
+                assert_eq!(code, None);
+                assert_eq!(message, "Interrupted");
+            }
+            e => panic!("Expected a Vim error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_quickfix_entry_deserialization() {
+        let entries: Vec<QuickfixEntry> = serde_json::from_value(serde_json::json!([
+            {
+                "uri": "file:///tmp/main.rs",
+                "range": {
+                    "start": {"line": 1, "character": 4},
+                    "end": {"line": 1, "character": 8}
+                }
+            },
+            {
+                "message": "unused variable",
+                "code": null,
+                "severity": 2,
+                "lnum": 4,
+                "col": 8,
+                "source": "rustc",
+                "bufnr": 1,
+                "end_lnum": 4,
+                "end_col": 9,
+                "namespace": 1,
+                "user_data": null
+            },
+            {
+                "document": {"project_relative_path": "src/lib.rs"},
+                "range": {
+                    "start": {"line": 2, "character": 0},
+                    "end": {"line": 2, "character": 0}
+                },
+                "type": "warning"
+            }
+        ]))
+        .unwrap();
+
+        assert!(matches!(&entries[0], QuickfixEntry::Location(l) if l.range.start.character == 4));
+        assert!(matches!(&entries[1], QuickfixEntry::Diagnostic(d) if d.lnum == 4));
+        match &entries[2] {
+            QuickfixEntry::Position {
+                document,
+                range,
+                text,
+                item_type,
+            } => {
+                assert_eq!(
+                    document,
+                    &DocumentIdentifier::ProjectRelativePath(PathBuf::from("src/lib.rs"))
+                );
+                assert_eq!((range.start.line, range.start.character), (2, 0));
+                assert_eq!(text, "");
+                assert_eq!(item_type, &Some(QuickfixItemType::Warning));
+            }
+            entry => panic!("Expected a position, got {entry:?}"),
+        }
+
+        // Vim's 1-based line and column are not accepted
+        assert!(
+            serde_json::from_value::<QuickfixEntry>(serde_json::json!(
+                {"document": {"buffer_id": 1}, "line": 3, "column": 1}
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_search_result_deserialization() {
+        let result: SearchResult = serde_json::from_value(serde_json::json!({
+            "backend": "ripgrep",
+            "matches": [{
+                "uri": "file:///tmp/main.rs",
+                "range": {
+                    "start": {"line": 2, "character": 4},
+                    "end": {"line": 2, "character": 9}
+                },
+                "text": "    hello();"
+            }],
+            "truncated": false
+        }))
+        .unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].location.range.start.character, 4);
+        assert_eq!(result.matches[0].text, "    hello();");
+
+        // Matches are accepted where LSP locations are
+        let entries: Vec<QuickfixEntry> =
+            serde_json::from_value(serde_json::to_value(&result.matches).unwrap()).unwrap();
+        assert!(
+            matches!(&entries[0], QuickfixEntry::Location(l) if l.uri == "file:///tmp/main.rs")
+        );
+
+        let empty: SearchResult =
+            serde_json::from_value(serde_json::json!({"backend": "vimgrep", "truncated": false}))
+                .unwrap();
+        assert!(empty.matches.is_empty());
+
+        let options: SearchOptions =
+            serde_json::from_value(serde_json::json!({"query": "fn main"})).unwrap();
+        assert_eq!(options.max_results, 200);
+        assert!(options.include.is_empty() && !options.fixed_strings);
+    }
+
+    #[test]
+    fn test_treesitter_captures_deserialization() {
+        let captures: TreesitterCaptures = serde_json::from_value(serde_json::json!({
+            "language": "lua",
+            "captures": [{
+                "capture": "name",
+                "type": "identifier",
+                "named": true,
+                "range": {
+                    "start": {"line": 0, "character": 15},
+                    "end": {"line": 0, "character": 20}
+                },
+                "text": "greet"
+            }],
+            "truncated": false
+        }))
+        .unwrap();
+        let capture = &captures.captures[0];
+        assert_eq!(capture.capture, "name");
+        assert_eq!(capture.node.node_type, "identifier");
+        assert_eq!(capture.node.field, None);
+        assert_eq!(capture.node.range.end.character, 20);
+
+        // Captures serialize flat, without a field they do not fill
+        let value = serde_json::to_value(capture).unwrap();
+        assert_eq!(value["type"], "identifier");
+        assert!(value.get("field").is_none());
+    }
+
+    #[test]
+    fn test_treesitter_outline_conversion() {
+        let outline: TreesitterOutline = serde_json::from_value(serde_json::json!({
+            "provider": "tags",
+            "symbols": [{
+                "name": "helper",
+                "kind": 12,
+                "range": {
+                    "start": {"line": 1, "character": 0},
+                    "end": {"line": 4, "character": 3}
+                },
+                "selectionRange": {
+                    "start": {"line": 1, "character": 15},
+                    "end": {"line": 1, "character": 21}
+                }
+            }]
+        }))
+        .unwrap();
+        let outline = DocumentOutline::from(outline);
+        assert_eq!(outline.source, OutlineSource::Treesitter);
+
+        let value = serde_json::to_value(&outline).unwrap();
+        assert_eq!(value["source"], "treesitter");
+        assert_eq!(value["provider"], "tags");
+        assert_eq!(value["symbols"][0]["name"], "helper");
+        assert_eq!(
+            value["symbols"][0]["selectionRange"]["start"]["character"],
+            15
+        );
+
+        let empty: TreesitterOutline =
+            serde_json::from_value(serde_json::json!({"provider": "locals"})).unwrap();
+        assert!(
+            matches!(DocumentOutline::from(empty).symbols, DocumentSymbolResult::Symbols(s) if s.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_window_layout_from_winlayout() {
+        let layout = serde_json::json!([
+            "row",
+            [["leaf", 1000], ["col", [["leaf", 1001], ["leaf", 1002]]]]
+        ]);
+        assert_eq!(
+            WindowLayout::from_winlayout(&layout),
+            Some(WindowLayout::Row {
+                children: vec![
+                    WindowLayout::Leaf { window_id: 1000 },
+                    WindowLayout::Column {
+                        children: vec![
+                            WindowLayout::Leaf { window_id: 1001 },
+                            WindowLayout::Leaf { window_id: 1002 },
+                        ]
+                    },
+                ]
+            })
+        );
+        assert_eq!(
+            WindowLayout::from_winlayout(&serde_json::json!(["leaf"])),
+            None
+        );
+        assert_eq!(
+            WindowLayout::from_winlayout(&serde_json::json!(["split", []])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_editor_state_from_atomic_results() {
+        let results = vec![
+            serde_json::json!({"mode": "n", "blocking": false}),
+            serde_json::json!("/tmp/project"),
+            serde_json::json!(1),
+            serde_json::json!(1000),
+            serde_json::json!(1),
+            serde_json::json!(-1),
+            serde_json::json!([{
+                "winid": 1000, "bufnr": 1, "tabnr": 1, "winnr": 1,
+                "topline": 1, "botline": 40, "width": 80, "height": 40
+            }]),
+            serde_json::json!(["leaf", 1000]),
+            serde_json::json!([{
+                "window_id": 1000, "name": "/tmp/project/main.rs",
+                "line": 4, "column": 2, "floating": false
+            }]),
+        ];
+        let state = EditorState::from_atomic_results(results).unwrap();
+        assert_eq!(state.mode, "n");
+        assert_eq!(state.alternate_buffer, None);
+        assert_eq!(state.cwd, PathBuf::from("/tmp/project"));
+        assert_eq!(state.windows.len(), 1);
+        assert_eq!(state.windows[0].line, 4);
+        assert_eq!(state.windows[0].first_visible_line, 0);
+        assert_eq!(state.windows[0].last_visible_line, 39);
+        assert_eq!(state.layout, WindowLayout::Leaf { window_id: 1000 });
+
+        assert!(EditorState::from_atomic_results(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_msgpack_to_json() {
+        // Window handles are msgpack extension types holding the ID
+        let mut handle = Vec::new();
+        rmpv::encode::write_value(&mut handle, &Value::from(1000)).unwrap();
+        let value = Value::Map(vec![
+            (Value::from("win"), Value::Ext(1, handle)),
+            (Value::from("lines"), Value::from(vec![Value::from("a")])),
+            (Value::from("none"), Value::Nil),
+        ]);
+        assert_eq!(
+            msgpack_to_json(value),
+            serde_json::json!({"win": 1000, "lines": ["a"], "none": null})
+        );
+    }
+
     #[test]
     fn test_symbol_kind_serialization() {
         assert_eq!(serde_json::to_value(SymbolKind::Function).unwrap(), 12);
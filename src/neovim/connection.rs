@@ -1,7 +1,11 @@
 use nvim_rs::{Neovim, compat::tokio::Compat, error::LoopError};
 use tokio::io::{AsyncWrite, WriteHalf};
+use tokio::process::Child;
+use tokio::sync::broadcast;
 use tokio::task::JoinHandle;
 
+use super::client::PendingResponses;
+
 pub struct NeovimConnection<T>
 where
     T: AsyncWrite + Send + 'static,
@@ -9,6 +13,12 @@ where
     pub nvim: Neovim<Compat<WriteHalf<T>>>,
     pub io_handler: JoinHandle<Result<Result<(), Box<LoopError>>, tokio::task::JoinError>>,
     pub target: String,
+    /// LSP requests waiting for a response from this Neovim instance
+    pub responses: PendingResponses,
+    /// IDs of attached buffers whose text changed
+    pub buffer_changes: broadcast::Sender<u64>,
+    /// Neovim process owned by this connection, if it was spawned by the server
+    pub process: Option<Child>,
 }
 
 impl<T> NeovimConnection<T>
@@ -19,11 +29,16 @@ where
         nvim: Neovim<Compat<WriteHalf<T>>>,
         io_handler: JoinHandle<Result<Result<(), Box<LoopError>>, tokio::task::JoinError>>,
         target: String,
+        responses: PendingResponses,
+        buffer_changes: broadcast::Sender<u64>,
     ) -> Self {
         Self {
             nvim,
             io_handler,
             target,
+            responses,
+            buffer_changes,
+            process: None,
         }
     }
 
@@ -6,6 +6,50 @@ pub enum NeovimError {
     Api(String),
     #[error("LSP error: {code} {message}")]
     Lsp { message: String, code: i32 },
+    #[error("LSP request {method} timed out after {timeout_ms}ms")]
+    Timeout { method: String, timeout_ms: u64 },
+    #[error("Policy violation ({rule}): {detail}")]
+    PolicyViolation { rule: String, detail: String },
+    #[error("Confirmation denied for {action}: {reason}")]
+    ConfirmationDenied { action: String, reason: String },
+    #[error("Path {path} is outside the allowed roots {roots:?}")]
+    PathOutsideRoots { path: String, roots: Vec<String> },
+    /// Error raised by an Ex command, with its E-number like `E492` when it has one
+    #[error("Vim error: {}{message}", code.as_ref().map(|c| format!("{c}: ")).unwrap_or_default())]
+    Vim {
+        code: Option<String>,
+        message: String,
+    },
+}
+
+impl NeovimError {
+    /// Parse an error message like `Vim(echo):E121: Undefined variable: x`
+    pub fn from_vim_message(message: &str) -> Self {
+        let message = message.trim();
+        // Drop the `Vim:` or `Vim(command):` prefix
+        let message = match message.strip_prefix("Vim") {
+            Some(rest) if rest.starts_with([':', '(']) => {
+                rest.split_once(':').map_or(rest, |(_, m)| m).trim_start()
+            }
+            _ => message,
+        };
+        match message.split_once(':') {
+            Some((code, rest))
+                if code.len() > 1
+                    && code.starts_with('E')
+                    && code[1..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                NeovimError::Vim {
+                    code: Some(code.to_string()),
+                    message: rest.trim().to_string(),
+                }
+            }
+            _ => NeovimError::Vim {
+                code: None,
+                message: message.to_string(),
+            },
+        }
+    }
 }
 
 impl From<std::io::Error> for NeovimError {
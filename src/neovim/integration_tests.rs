@@ -1,4 +1,5 @@
 use std::fs;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use tempfile::TempDir;
@@ -6,8 +7,12 @@ use tokio::time::sleep;
 use tracing::info;
 use tracing_test::traced_test;
 
-use crate::neovim::client::{DocumentIdentifier, Position, Range};
-use crate::neovim::{NeovimClient, NeovimClientTrait};
+use crate::neovim::client::{
+    ConfirmPreview, DocumentIdentifier, DocumentSymbolResult, OutlineSource, Position,
+    QuickfixAction, QuickfixEntry, QuickfixItemType, Range, SearchOptions, SelectionMode,
+    SymbolKind, WindowLayout,
+};
+use crate::neovim::{NO_UI_REASON, NeovimClient, NeovimClientTrait, NeovimError, WorkspaceEdit};
 use crate::test_utils::*;
 
 #[tokio::test]
@@ -109,6 +114,934 @@ async fn test_lua_execution() {
     // Guard automatically cleans up when it goes out of scope
 }
 
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_lua_execution_sandboxed() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let allowed_api = vec!["nvim_get_current_buf".to_string()];
+    let denied = vec!["os.execute".to_string(), "vim.fn.system".to_string()];
+
+    // Allowed code runs normally
+    let result = client
+        .execute_lua_sandboxed(
+            "return vim.api.nvim_get_current_buf()",
+            Some(&allowed_api),
+            &denied,
+        )
+        .await;
+    assert!(result.is_ok(), "Failed to execute Lua: {result:?}");
+
+    // Indirect access to denied functions is caught at runtime
+    for code in [
+        "local f = vim.fn['sys' .. 'tem']; return f('true')",
+        "return _G.os['exe' .. 'cute']('true')",
+        "return vim.api['nvim_' .. 'command']('echo 1')",
+        "return getfenv(0)",
+    ] {
+        let result = client
+            .execute_lua_sandboxed(code, Some(&allowed_api), &denied)
+            .await;
+        assert!(
+            matches!(result, Err(NeovimError::PolicyViolation { .. })),
+            "Expected policy violation for {code:?}, got {result:?}"
+        );
+    }
+
+    // Routes to unchecked code are refused, with or without an API allowlist
+    let allowed_escape = vec!["nvim_exec_lua".to_string()];
+    for code in [
+        "vim.cmd('!true')",
+        "return vim.call('system', 'true')",
+        "return vim.fn.call('system', { 'true' })",
+        "return vim.fn['sys' .. 'tem']('true')",
+        "return vim.uv.spawn('true', {})",
+        "return vim.loop.spawn('true', {})",
+        "return vim.api.nvim_exec_lua('return 1', {})",
+        "return vim.api.nvim_command('!true')",
+        "return io.popen('true')",
+    ] {
+        for allowed_api in [None, Some(allowed_escape.as_slice())] {
+            let result = client
+                .execute_lua_sandboxed(code, allowed_api, &denied)
+                .await;
+            assert!(
+                matches!(result, Err(NeovimError::PolicyViolation { .. })),
+                "Expected policy violation for {code:?}, got {result:?}"
+            );
+        }
+    }
+
+    // Writes stay inside of the sandbox
+    let result = client
+        .execute_lua_sandboxed(
+            "vim.fn.getcwd = nil; string.upper = nil; vim.api = {}; _G.answer = 42; return answer",
+            None,
+            &denied,
+        )
+        .await;
+    assert_eq!(result.unwrap(), rmpv::Value::from(42));
+    let result = client
+        .execute_lua(
+            "return type(vim.fn.getcwd) == 'function' and type(string.upper) == 'function' \
+             and type(vim.api.nvim_get_current_buf) == 'function' and answer == nil",
+        )
+        .await;
+    assert_eq!(result.unwrap(), rmpv::Value::from(true));
+
+    // Runtime errors are reported as regular errors
+    let result = client
+        .execute_lua_sandboxed("error('boom')", None, &denied)
+        .await;
+    assert!(
+        matches!(result, Err(NeovimError::Api(_))),
+        "Expected API error, got {result:?}"
+    );
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_confirm() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let preview = || ConfirmPreview::Code {
+        filetype: "lua".to_string(),
+        code: "print('hello')".to_string(),
+    };
+    let answer =
+        |choice: &str| format!("vim.ui.select = function(_, _, on_choice) on_choice({choice}) end");
+
+    // The test instance is headless, nobody could answer the prompt
+    client.execute_lua(&answer("'Approve'")).await.unwrap();
+    let result = client.confirm("Run code", preview(), 5000).await;
+    assert!(
+        matches!(&result, Err(NeovimError::ConfirmationDenied { reason, .. }) if reason == NO_UI_REASON),
+        "Expected headless denial, got {result:?}"
+    );
+    client
+        .execute_lua("vim.api.nvim_list_uis = function() return { {} } end")
+        .await
+        .unwrap();
+
+    client.execute_lua(&answer("'Approve'")).await.unwrap();
+    let result = client.confirm("Run code", preview(), 5000).await;
+    assert!(result.is_ok(), "Expected approval, got {result:?}");
+
+    client.execute_lua(&answer("'Deny'")).await.unwrap();
+    let result = client.confirm("Run code", preview(), 5000).await;
+    assert!(
+        matches!(&result, Err(NeovimError::ConfirmationDenied { reason, .. }) if reason == "denied by the user"),
+        "Expected denial, got {result:?}"
+    );
+
+    // Without an answer the change is denied once the timeout expires
+    client
+        .execute_lua("vim.ui.select = function() end")
+        .await
+        .unwrap();
+    let result = client.confirm("Run code", preview(), 200).await;
+    assert!(
+        matches!(&result, Err(NeovimError::ConfirmationDenied { reason, .. }) if reason.contains("no answer")),
+        "Expected timeout denial, got {result:?}"
+    );
+
+    // The preview float is closed once the request times out
+    sleep(Duration::from_millis(200)).await;
+    let result = client
+        .execute_lua("return #vim.api.nvim_list_wins()")
+        .await
+        .unwrap();
+    assert_eq!(result, rmpv::Value::from(1));
+
+    // Workspace edit previews read files that aren't loaded from disk
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("unloaded.txt");
+    fs::write(&path, "old\n").unwrap();
+    let uri = format!("file://{}", path.display());
+    let workspace_edit: WorkspaceEdit = serde_json::from_value(serde_json::json!({
+        "changes": { uri: [{
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 3 } },
+            "newText": "new",
+        }] },
+    }))
+    .unwrap();
+    client.execute_lua(&answer("'Approve'")).await.unwrap();
+    let preview = ConfirmPreview::WorkspaceEdit {
+        client_name: "none".to_string(),
+        workspace_edit,
+    };
+    let result = client.confirm("Apply edit", preview, 5000).await;
+    assert!(result.is_ok(), "Expected approval, got {result:?}");
+    let loaded = client
+        .execute_lua(&format!("return vim.fn.bufnr('{}')", path.display()))
+        .await
+        .unwrap();
+    assert_eq!(loaded, rmpv::Value::from(-1));
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_get_project_root() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let project = client.get_project_root().await.unwrap();
+    assert!(project.cwd.is_absolute());
+    assert!(
+        project.cwd.starts_with(&project.root),
+        "{project:?} root should contain cwd"
+    );
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_read_document_and_unsaved_changes() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("notes.txt");
+    fs::write(&file_path, "first\nsecond\n").unwrap();
+
+    // Files that aren't loaded are read from disk
+    let document = DocumentIdentifier::from_absolute_path(&file_path);
+    let text = client.read_document(&document).await.unwrap();
+    assert_eq!(text.buffer_id, None);
+    assert_eq!(text.text, "first\nsecond");
+    assert!(client.get_unsaved_changes().await.unwrap().is_empty());
+
+    // Loaded buffers provide their unsaved contents
+    client
+        .execute_lua(&format!(
+            "vim.cmd.edit({:?}); vim.api.nvim_buf_set_lines(0, 1, 2, false, {{'changed'}})",
+            file_path.to_string_lossy()
+        ))
+        .await
+        .unwrap();
+    let text = client.read_document(&document).await.unwrap();
+    assert!(text.buffer_id.is_some());
+    assert!(text.modified);
+    assert_eq!(text.text, "first\nchanged");
+
+    let changes = client.get_unsaved_changes().await.unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].diff.contains("-second"), "{}", changes[0].diff);
+    assert!(changes[0].diff.contains("+changed"), "{}", changes[0].diff);
+
+    let result = client
+        .read_document(&DocumentIdentifier::from_buffer_id(999))
+        .await;
+    assert!(result.is_err(), "Invalid buffer should be rejected");
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_buffer_attach() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let mut changes = client.subscribe_buffer_changes().unwrap();
+    client.attach_buffer(1).await.unwrap();
+    // Attaching twice keeps a single attachment
+    client.attach_buffer(1).await.unwrap();
+
+    client
+        .execute_lua("vim.api.nvim_buf_set_lines(1, 0, -1, false, {'changed'})")
+        .await
+        .unwrap();
+    let changed = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+        .await
+        .expect("buffer change notification")
+        .unwrap();
+    assert_eq!(changed, 1);
+
+    let buffers = client.get_buffers().await.unwrap();
+    let buffer = buffers.iter().find(|b| b.id == 1).unwrap();
+    assert!(buffer.loaded);
+    assert_eq!(buffer.size, "changed\n".len() as u64);
+
+    // No notifications once detached
+    client.detach_buffer(1).await.unwrap();
+    client
+        .execute_lua("vim.api.nvim_buf_set_lines(1, 0, -1, false, {'again'})")
+        .await
+        .unwrap();
+    let result = tokio::time::timeout(Duration::from_millis(500), changes.recv()).await;
+    assert!(result.is_err(), "Unexpected notification {result:?}");
+
+    assert!(client.attach_buffer(999).await.is_err());
+
+    // Listed buffers that aren't loaded report the file size and read from disk
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("listed.txt");
+    fs::write(&path, "on disk\n").unwrap();
+    let bufnr = client
+        .execute_lua(&format!("return vim.fn.bufadd('{}')", path.display()))
+        .await
+        .unwrap()
+        .as_u64()
+        .unwrap();
+    client
+        .execute_lua(&format!("vim.bo[{bufnr}].buflisted = true"))
+        .await
+        .unwrap();
+    let buffers = client.get_buffers().await.unwrap();
+    let buffer = buffers.iter().find(|b| b.id == bufnr).unwrap();
+    assert!(!buffer.loaded);
+    assert!(buffer.listed);
+    assert_eq!(buffer.size, "on disk\n".len() as u64);
+    let document = client
+        .read_document(&DocumentIdentifier::from_buffer_id(bufnr))
+        .await
+        .unwrap();
+    assert_eq!(document.text, "on disk");
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_get_cursor() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    client
+        .execute_lua(
+            "vim.api.nvim_buf_set_lines(0, 0, -1, false, {'local a = 1', 'local résumé = a'}); \
+             vim.api.nvim_win_set_cursor(0, {2, 17})",
+        )
+        .await
+        .unwrap();
+
+    let cursor = client.get_cursor().await.unwrap();
+    assert_eq!(cursor.position.line, 1);
+    // The two 2-byte characters count as one UTF-16 code unit each
+    assert_eq!(cursor.position.character, 15);
+    assert_eq!(cursor.word, "a");
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_exec_command() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let output = client.exec_command("echo 'hello'").await.unwrap();
+    assert_eq!(output, "hello");
+
+    // Multiple lines run as a script
+    let output = client
+        .exec_command("let g:nvim_mcp_test = 42\necho g:nvim_mcp_test")
+        .await
+        .unwrap();
+    assert_eq!(output, "42");
+
+    match client.exec_command("NotACommand").await {
+        Err(NeovimError::Vim { code, message }) => {
+            assert_eq!(code.as_deref(), Some("E492"));
+            assert!(message.contains("NotACommand"), "{message}");
+        }
+        other => panic!("Expected a Vim error, got {other:?}"),
+    }
+
+    assert!(client.exec_command("  ").await.is_err());
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_quickfix_list() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().canonicalize().unwrap().join("todo.txt");
+    fs::write(&file, "first\nsécond\nthird\n").unwrap();
+
+    let list = client.get_quickfix_list(None).await.unwrap();
+    assert!(list.items.is_empty());
+    assert_eq!(list.current, 0);
+
+    let entries: Vec<QuickfixEntry> = serde_json::from_value(serde_json::json!([
+        {
+            "uri": format!("file://{}", file.display()),
+            "range": {
+                "start": {"line": 1, "character": 2},
+                "end": {"line": 1, "character": 4}
+            }
+        },
+        {
+            "document": {"absolute_path": file},
+            "range": {
+                "start": {"line": 2, "character": 0},
+                "end": {"line": 2, "character": 5}
+            },
+            "text": "check",
+            "type": "warning"
+        }
+    ]))
+    .unwrap();
+    client
+        .set_quickfix_list(None, "References", &entries, QuickfixAction::New, false)
+        .await
+        .unwrap();
+
+    let list = client.get_quickfix_list(None).await.unwrap();
+    assert_eq!(list.title, "References");
+    assert_eq!(list.current, 1);
+    assert_eq!(list.items.len(), 2);
+    assert_eq!(list.items[0].path.as_deref(), Some(file.as_path()));
+    // Ranges are 0-based UTF-16 positions both ways, not Vim's byte columns
+    let range = list.items[0].range.as_ref().unwrap();
+    assert_eq!((range.start.line, range.start.character), (1, 2));
+    assert_eq!((range.end.line, range.end.character), (1, 4));
+    assert_eq!(list.items[0].text, "sécond");
+    let range = list.items[1].range.as_ref().unwrap();
+    assert_eq!((range.start.line, range.start.character), (2, 0));
+    assert_eq!((range.end.line, range.end.character), (2, 5));
+    assert_eq!(list.items[1].item_type, Some(QuickfixItemType::Warning));
+
+    client
+        .set_quickfix_list(None, "More", &entries[1..], QuickfixAction::Append, false)
+        .await
+        .unwrap();
+    assert_eq!(client.get_quickfix_list(None).await.unwrap().items.len(), 3);
+
+    // The location list of the current window is separate
+    client
+        .set_quickfix_list(Some(0), "Local", &entries[..1], QuickfixAction::New, true)
+        .await
+        .unwrap();
+    let list = client.get_quickfix_list(Some(0)).await.unwrap();
+    assert_eq!(list.title, "Local");
+    assert_eq!(list.items.len(), 1);
+
+    assert!(client.get_quickfix_list(Some(999)).await.is_err());
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_search_workspace() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(
+        root.join("src/main.rs"),
+        "fn main() {\n    println!(\"héllo\");\n}\n",
+    )
+    .unwrap();
+    fs::write(root.join("notes.txt"), "héllo from notes\n").unwrap();
+    client
+        .execute_lua(&format!(
+            "vim.cmd.cd(vim.fn.fnameescape({:?}))",
+            root.display()
+        ))
+        .await
+        .unwrap();
+
+    let search = |query: &str| SearchOptions {
+        query: query.to_string(),
+        fixed_strings: false,
+        ignore_case: false,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        max_results: 200,
+    };
+
+    for grepprg in ["rg --vimgrep", "internal"] {
+        client
+            .execute_lua(&format!("vim.o.grepprg = {grepprg:?}"))
+            .await
+            .unwrap();
+
+        let result = client.search_workspace(&search("h.llo")).await.unwrap();
+        let backend = if grepprg == "internal" {
+            "vimgrep"
+        } else {
+            "ripgrep"
+        };
+        assert_eq!(result.backend, backend);
+        assert_eq!(result.matches.len(), 2, "{result:?}");
+        assert!(result.matches[0].location.uri.ends_with("/notes.txt"));
+        assert_eq!(result.matches[1].text, "    println!(\"héllo\");");
+        let range = &result.matches[1].location.range;
+        assert_eq!((range.start.line, range.start.character), (1, 14));
+        assert_eq!(range.end.character, 19);
+
+        let mut options = search("héllo");
+        options.include = vec!["*.rs".to_string()];
+        let result = client.search_workspace(&options).await.unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert!(result.matches[0].location.uri.ends_with("/src/main.rs"));
+
+        let mut options = search("HÉLLO");
+        options.ignore_case = true;
+        options.exclude = vec!["*.rs".to_string()];
+        let result = client.search_workspace(&options).await.unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert!(result.matches[0].location.uri.ends_with("/notes.txt"));
+
+        let mut options = search("(");
+        options.fixed_strings = true;
+        options.max_results = 1;
+        let result = client.search_workspace(&options).await.unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert!(result.truncated);
+    }
+
+    // Only the executable is taken from 'grepprg', `-uu` doesn't add hidden files
+    fs::write(root.join(".hidden.txt"), "héllo from a hidden file\n").unwrap();
+    client
+        .execute_lua("vim.o.grepprg = 'rg -uu --vimgrep'")
+        .await
+        .unwrap();
+    let result = client.search_workspace(&search("héllo")).await.unwrap();
+    assert_eq!(result.matches.len(), 2, "{result:?}");
+
+    // Unsaved changes are searched instead of the file on disk
+    client
+        .execute_lua(
+            "vim.cmd.edit('notes.txt') vim.api.nvim_buf_set_lines(0, 0, -1, false, { 'unsaved', 'héllo again' })",
+        )
+        .await
+        .unwrap();
+    let result = client.search_workspace(&search("héllo")).await.unwrap();
+    assert_eq!(result.matches.len(), 2);
+    assert_eq!(result.matches[0].text, "héllo again");
+    assert_eq!(result.matches[0].location.range.start.line, 1);
+    assert!(
+        client
+            .search_workspace(&search("unsaved"))
+            .await
+            .unwrap()
+            .matches[0]
+            .location
+            .uri
+            .ends_with("/notes.txt")
+    );
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_find_files() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src/server")).unwrap();
+    fs::create_dir(root.join(".cache")).unwrap();
+    fs::write(root.join("src/server/tools.rs"), "").unwrap();
+    fs::write(root.join("src/main.rs"), "").unwrap();
+    fs::write(root.join(".cache/tools.rs"), "").unwrap();
+    fs::write(root.join("README.md"), "").unwrap();
+    fs::write(root.join("ignored.rs"), "").unwrap();
+    client
+        .execute_lua(&format!(
+            "vim.cmd.cd(vim.fn.fnameescape({:?}))",
+            root.display()
+        ))
+        .await
+        .unwrap();
+
+    let found = client.find_files("srvtools", 50).await.unwrap();
+    assert_eq!(found.backend, "filesystem");
+    assert_eq!(found.root, root);
+    assert_eq!(found.files, vec![PathBuf::from("src/server/tools.rs")]);
+
+    // Hidden directories are skipped
+    let found = client.find_files("*.rs", 50).await.unwrap();
+    assert_eq!(
+        found.files,
+        vec![
+            PathBuf::from("ignored.rs"),
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("src/server/tools.rs")
+        ]
+    );
+    assert!(!found.truncated);
+
+    let found = client.find_files("*.rs", 1).await.unwrap();
+    assert_eq!(found.files.len(), 1);
+    assert!(found.truncated);
+
+    // Inside a repository ignored files are skipped and paths stay relative
+    // to the working directory below the root
+    fs::write(root.join(".gitignore"), "ignored.rs\n").unwrap();
+    let status = std::process::Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(&root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    client.execute_lua("vim.cmd.cd('src')").await.unwrap();
+
+    let found = client.find_files("**/*.rs", 50).await.unwrap();
+    assert_eq!(found.backend, "git");
+    assert_eq!(found.root, root);
+    assert_eq!(found.cwd, root.join("src"));
+    assert_eq!(
+        found.files,
+        vec![PathBuf::from("main.rs"), PathBuf::from("server/tools.rs")]
+    );
+    let found = client.find_files("README", 50).await.unwrap();
+    assert_eq!(found.files, vec![PathBuf::from("../README.md")]);
+    assert!(
+        client
+            .find_files("nothing*", 50)
+            .await
+            .unwrap()
+            .files
+            .is_empty()
+    );
+
+    // Deleted tracked files are left out, truncation counts the candidates
+    let status = std::process::Command::new("git")
+        .args(["add", "."])
+        .current_dir(&root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    fs::remove_file(root.join("src/main.rs")).unwrap();
+    let found = client.find_files("**/*.rs", 1).await.unwrap();
+    assert!(found.files.is_empty());
+    assert!(found.truncated);
+    let found = client.find_files("**/*.rs", 50).await.unwrap();
+    assert_eq!(found.files, vec![PathBuf::from("server/tools.rs")]);
+    assert!(!found.truncated);
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_treesitter() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().canonicalize().unwrap().join("greet.lua");
+    fs::write(
+        &file,
+        "local function greet(name)\n    return \"héllo \" .. name\nend\n",
+    )
+    .unwrap();
+    let document = DocumentIdentifier::from_absolute_path(&file);
+
+    // The file is parsed from disk when it is not loaded
+    let result = client
+        .treesitter_query(
+            &document,
+            "(function_declaration name: (identifier) @name) (string_content) @string",
+            None,
+            None,
+            200,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.language, "lua");
+    assert!(!result.truncated);
+    assert_eq!(result.captures.len(), 2);
+    assert_eq!(result.captures[0].capture, "name");
+    assert_eq!(result.captures[0].node.node_type, "identifier");
+    assert_eq!(result.captures[0].node.text, "greet");
+    let range = &result.captures[0].node.range;
+    assert_eq!((range.start.line, range.start.character), (0, 15));
+    assert_eq!((range.end.line, range.end.character), (0, 20));
+    // Ranges count UTF-16 code units
+    assert_eq!(result.captures[1].capture, "string");
+    assert_eq!(result.captures[1].node.text, "héllo ");
+    let range = &result.captures[1].node.range;
+    assert_eq!((range.start.character, range.end.character), (12, 18));
+
+    // Loaded buffers are parsed with their unsaved changes
+    client
+        .execute_lua(&format!(
+            "vim.cmd.edit(vim.fn.fnameescape({:?})) vim.api.nvim_buf_set_lines(0, 1, 1, false, {{ '    print(name)' }})",
+            file.display()
+        ))
+        .await
+        .unwrap();
+    let result = client
+        .treesitter_query(&document, "(identifier) @id", Some(1), Some(1), 200)
+        .await
+        .unwrap();
+    let texts: Vec<&str> = result
+        .captures
+        .iter()
+        .map(|c| c.node.text.as_str())
+        .collect();
+    assert_eq!(texts, vec!["print", "name"]);
+    let result = client
+        .treesitter_query(&document, "(identifier) @id", None, None, 1)
+        .await
+        .unwrap();
+    assert_eq!(result.captures.len(), 1);
+    assert!(result.truncated);
+
+    let ancestry = client
+        .treesitter_node_at(
+            &document,
+            &Position {
+                line: 0,
+                character: 16,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(ancestry.nodes[0].node_type, "identifier");
+    assert_eq!(ancestry.nodes[0].field.as_deref(), Some("name"));
+    assert_eq!(ancestry.nodes[1].node_type, "function_declaration");
+    assert_eq!(ancestry.nodes[1].range.end.line, 3);
+    assert_eq!(ancestry.nodes.last().unwrap().node_type, "chunk");
+
+    let err = client
+        .treesitter_query(&document, "(no_such_node) @x", None, None, 200)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid lua query"), "{err}");
+
+    let text_file = file.with_extension("txt");
+    fs::write(&text_file, "plain text\n").unwrap();
+    assert!(
+        client
+            .treesitter_node_at(
+                &DocumentIdentifier::from_absolute_path(&text_file),
+                &Position::default()
+            )
+            .await
+            .is_err()
+    );
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_treesitter_outline() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().canonicalize().unwrap().join("module.lua");
+    fs::write(
+        &file,
+        "local M = {}\nlocal function helper(x)\n    local y = x\n    return y\nend\nfunction M.run()\n    return helper(1)\nend\nreturn M\n",
+    )
+    .unwrap();
+    let document = DocumentIdentifier::from_absolute_path(&file);
+
+    // Locals queries capture names, variables inside functions are left out
+    client
+        .execute_lua(
+            r#"vim.treesitter.query.set("lua", "locals", [[
+                (variable_declaration (assignment_statement (variable_list name: (identifier) @local.definition.var)))
+                (function_declaration name: (identifier) @local.definition.function)
+            ]])"#,
+        )
+        .await
+        .unwrap();
+    let outline = client.treesitter_outline(&document).await.unwrap();
+    assert_eq!(outline.source, OutlineSource::Treesitter);
+    assert_eq!(outline.provider, "locals");
+    let DocumentSymbolResult::Symbols(symbols) = outline.symbols else {
+        panic!("Expected document symbols");
+    };
+    let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["M", "helper"]);
+    assert!(matches!(symbols[1].kind, SymbolKind::Function));
+    assert_eq!(symbols[1].range.start.line, 1);
+    assert_eq!(symbols[1].range.end.line, 4);
+    assert_eq!(symbols[1].selection_range.start.character, 15);
+    assert!(symbols[1].children.is_none());
+
+    // Tags queries are preferred, with the whole definition captured
+    client
+        .execute_lua(
+            r#"vim.treesitter.query.set("lua", "tags", [[
+                (function_declaration name: (_) @name) @definition.function
+            ]])"#,
+        )
+        .await
+        .unwrap();
+    let outline = client.treesitter_outline(&document).await.unwrap();
+    assert_eq!(outline.provider, "tags");
+    let DocumentSymbolResult::Symbols(symbols) = outline.symbols else {
+        panic!("Expected document symbols");
+    };
+    let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["helper", "M.run"]);
+    assert_eq!(symbols[1].range.start.line, 5);
+
+    let text_file = file.with_extension("txt");
+    fs::write(&text_file, "plain text\n").unwrap();
+    assert!(
+        client
+            .treesitter_outline(&DocumentIdentifier::from_absolute_path(&text_file))
+            .await
+            .is_err()
+    );
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_get_selection() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    let result = client.get_selection().await;
+    assert!(result.is_err(), "A new buffer has no selection");
+
+    client
+        .execute_lua(
+            "vim.api.nvim_buf_set_lines(0, 0, -1, false, {'local résumé = 1', 'return résumé', 'end'}); \
+             vim.api.nvim_win_set_cursor(0, {1, 6}); \
+             vim.cmd('normal! vj\\27')",
+        )
+        .await
+        .unwrap();
+
+    // Characterwise from `résumé` on the first line to the space on the second
+    let selection = client.get_selection().await.unwrap();
+    assert_eq!(selection.mode, SelectionMode::Char);
+    assert!(!selection.active);
+    assert_eq!(selection.document, DocumentIdentifier::BufferId(1));
+    assert_eq!(selection.text, "résumé = 1\nreturn ");
+    assert_eq!(selection.range.start.line, 0);
+    assert_eq!(selection.range.start.character, 6);
+    assert_eq!(selection.range.end.line, 1);
+    assert_eq!(selection.range.end.character, 7);
+
+    client
+        .execute_lua("vim.api.nvim_win_set_cursor(0, {1, 0}); vim.cmd('normal! \\22jl\\27')")
+        .await
+        .unwrap();
+
+    let selection = client.get_selection().await.unwrap();
+    assert_eq!(selection.mode, SelectionMode::Block);
+    assert_eq!(selection.text, "lo\nre");
+
+    client
+        .execute_lua("vim.api.nvim_win_set_cursor(0, {2, 3}); vim.cmd('normal! Vj\\27')")
+        .await
+        .unwrap();
+
+    let selection = client.get_selection().await.unwrap();
+    assert_eq!(selection.mode, SelectionMode::Line);
+    assert_eq!(selection.text, "return résumé\nend");
+    assert_eq!(selection.range.start.character, 0);
+    assert_eq!(selection.range.end.line, 2);
+    assert_eq!(selection.range.end.character, 3);
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_get_cursor_context() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    client
+        .execute_lua(
+            "vim.api.nvim_buf_set_lines(0, 0, -1, false, {'a', 'b', 'c', 'd', 'e'}); \
+             vim.api.nvim_win_set_cursor(0, {2, 0})",
+        )
+        .await
+        .unwrap();
+
+    let context = client.get_cursor_context(2).await.unwrap();
+    assert_eq!(context.cursor.position.line, 1);
+    assert_eq!(context.first_line, 0);
+    assert_eq!(context.lines, vec!["a", "b", "c", "d"]);
+    assert!(context.symbol.is_none());
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(any(unix, windows))]
+async fn test_get_editor_state() {
+    let ipc_path = generate_random_ipc_path();
+
+    let (client, _guard) = setup_connected_client_ipc(&ipc_path).await;
+
+    client
+        .execute_lua(
+            "vim.api.nvim_buf_set_lines(0, 0, -1, false, {'a', 'b', 'c'}); \
+             vim.api.nvim_win_set_cursor(0, {3, 0}); \
+             vim.cmd('vsplit'); \
+             vim.cmd('enew')",
+        )
+        .await
+        .unwrap();
+
+    let state = client.get_editor_state().await.unwrap();
+    assert_eq!(state.mode, "n");
+    assert_eq!(state.current_tab, 1);
+    assert_eq!(state.alternate_buffer, Some(1));
+    assert_ne!(state.current_buffer, 1);
+    assert_eq!(state.windows.len(), 2);
+
+    let first = state.windows.iter().find(|w| w.buffer_id == 1).unwrap();
+    assert_eq!(first.line, 2);
+    assert_eq!(first.first_visible_line, 0);
+    assert!(!first.floating);
+    match &state.layout {
+        WindowLayout::Row { children } => assert_eq!(children.len(), 2),
+        layout => panic!("Expected a row layout, got {layout:?}"),
+    }
+
+    // Guard automatically cleans up when it goes out of scope
+}
+
 #[tokio::test]
 #[traced_test]
 #[cfg(any(unix, windows))]
@@ -140,6 +1073,44 @@ async fn test_error_handling() {
     assert!(result.is_err(), "disconnect should fail when not connected");
 }
 
+#[tokio::test]
+#[traced_test]
+#[cfg(unix)]
+async fn test_spawn_lifecycle() {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let socket_path = generate_random_ipc_path();
+    let mut client = NeovimClient::new();
+
+    let result = client
+        .spawn_path(&socket_path, temp_dir.path(), Some("NONE".as_ref()))
+        .await;
+    assert!(result.is_ok(), "Failed to spawn Neovim: {result:?}");
+
+    // Spawned instance should run in the requested working directory
+    let cwd = client
+        .execute_lua("return vim.fn.getcwd()")
+        .await
+        .expect("Failed to get cwd");
+    let expected = temp_dir.path().canonicalize().unwrap();
+    assert_eq!(
+        std::path::PathBuf::from(cwd.as_str().unwrap())
+            .canonicalize()
+            .unwrap(),
+        expected
+    );
+
+    // Spawning again while connected should fail
+    let result = client
+        .spawn_path(&socket_path, temp_dir.path(), Some("NONE".as_ref()))
+        .await;
+    assert!(result.is_err(), "Should not be able to spawn twice");
+
+    // Disconnect kills the process and removes its socket
+    let result = client.disconnect().await;
+    assert!(result.is_ok(), "Failed to disconnect: {result:?}");
+    assert!(!std::path::Path::new(&socket_path).exists());
+}
+
 #[tokio::test]
 #[traced_test]
 #[cfg(any(unix, windows))]
@@ -0,0 +1,58 @@
+local bufnr = unpack({ ... })
+
+if not vim.api.nvim_buf_is_valid(bufnr) then
+    return vim.json.encode({
+        err_msg = string.format("Buffer ID %d is not valid", bufnr),
+    })
+end
+
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, bufnr)
+_G.nvim_mcp_attached_buffers = _G.nvim_mcp_attached_buffers or {}
+if _G.nvim_mcp_attached_buffers[key] then
+    return vim.json.encode({ result = vim.NIL })
+end
+
+-- Each attachment owns a token, callbacks of a replaced or detached attachment detach themselves
+local token = {}
+_G.nvim_mcp_attached_buffers[key] = token
+local function attached()
+    return _G.nvim_mcp_attached_buffers[key] == token
+end
+
+-- Notify at most once per 100ms while the text is being edited
+local pending = false
+local function changed()
+    if not attached() then
+        return true
+    end
+    if pending then
+        return
+    end
+    pending = true
+    vim.defer_fn(function()
+        pending = false
+        if attached() then
+            vim.rpcnotify(channel_id, "NVIM_MCP_BufferChanged", bufnr)
+        end
+    end, 100)
+end
+
+vim.fn.bufload(bufnr)
+local ok = vim.api.nvim_buf_attach(bufnr, false, {
+    on_lines = changed,
+    on_reload = changed,
+    on_detach = function()
+        if attached() then
+            _G.nvim_mcp_attached_buffers[key] = nil
+        end
+    end,
+})
+if not ok then
+    _G.nvim_mcp_attached_buffers[key] = nil
+    return vim.json.encode({
+        err_msg = string.format("Failed to attach to buffer %d", bufnr),
+    })
+end
+
+return vim.json.encode({ result = vim.NIL })
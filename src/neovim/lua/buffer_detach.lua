@@ -0,0 +1,10 @@
+local bufnr = unpack({ ... })
+
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, bufnr)
+-- The attachment detaches itself on the next change
+if _G.nvim_mcp_attached_buffers then
+    _G.nvim_mcp_attached_buffers[key] = nil
+end
+
+return vim.json.encode({ result = vim.NIL })
@@ -0,0 +1,136 @@
+local response_id, title, kind, payload, client_name = unpack({ ... })
+
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, response_id)
+_G.nvim_mcp_confirmations = _G.nvim_mcp_confirmations or {}
+
+-- Nobody can answer a headless Neovim, the server asks elsewhere or denies right away
+if #vim.api.nvim_list_uis() == 0 then
+    return vim.json.encode({ result = false })
+end
+
+-- Lines of a file, from its buffer when loaded so that unsaved changes are included.
+-- Files without a buffer are read from disk instead of being loaded into one.
+local function file_lines(fname)
+    for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
+        if vim.api.nvim_buf_is_loaded(bufnr) and vim.api.nvim_buf_get_name(bufnr) == fname then
+            return vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+        end
+    end
+    if vim.fn.filereadable(fname) == 1 then
+        return vim.fn.readfile(fname)
+    end
+    return {}
+end
+
+-- Unified diff of the files touched by a workspace edit, computed on scratch copies
+local function workspace_edit_preview(workspace_edit)
+    local position_encoding = "utf-16"
+    for _, client in ipairs(vim.lsp.get_clients({ name = client_name or nil })) do
+        position_encoding = client.offset_encoding or position_encoding
+    end
+
+    local edits_by_uri = {}
+    local uris = {}
+    local function add_edits(uri, edits)
+        if not edits_by_uri[uri] then
+            edits_by_uri[uri] = {}
+            table.insert(uris, uri)
+        end
+        vim.list_extend(edits_by_uri[uri], edits)
+    end
+    for uri, edits in pairs(workspace_edit.changes or {}) do
+        add_edits(uri, edits)
+    end
+    local lines = {}
+    for _, change in ipairs(workspace_edit.documentChanges or {}) do
+        if change.textDocument then
+            add_edits(change.textDocument.uri, change.edits)
+        elseif change.kind == "rename" then
+            table.insert(lines, string.format("rename %s -> %s", change.oldUri, change.newUri))
+        else
+            table.insert(lines, string.format("%s %s", change.kind, change.uri))
+        end
+    end
+
+    for _, uri in ipairs(uris) do
+        local fname = vim.uri_to_fname(uri)
+        local old_lines = file_lines(fname)
+        local scratch = vim.api.nvim_create_buf(false, true)
+        vim.api.nvim_buf_set_lines(scratch, 0, -1, false, old_lines)
+        vim.lsp.util.apply_text_edits(edits_by_uri[uri], scratch, position_encoding)
+        local new_lines = vim.api.nvim_buf_get_lines(scratch, 0, -1, false)
+        vim.api.nvim_buf_delete(scratch, { force = true })
+
+        local path = vim.fn.fnamemodify(fname, ":~:.")
+        local diff = vim.diff(table.concat(old_lines, "\n") .. "\n", table.concat(new_lines, "\n") .. "\n")
+        table.insert(lines, "--- a/" .. path)
+        table.insert(lines, "+++ b/" .. path)
+        vim.list_extend(lines, vim.split(diff, "\n", { trimempty = true }))
+    end
+    return lines
+end
+
+local preview, filetype
+if kind == "workspace_edit" then
+    preview = workspace_edit_preview(vim.json.decode(payload))
+    filetype = "diff"
+else
+    preview = vim.split(payload, "\n")
+    filetype = kind
+end
+
+local function respond(approved)
+    local pending = _G.nvim_mcp_confirmations[key]
+    if not pending then
+        return
+    end
+    _G.nvim_mcp_confirmations[key] = nil
+    if vim.api.nvim_win_is_valid(pending.win) then
+        vim.api.nvim_win_close(pending.win, true)
+    end
+    vim.rpcnotify(channel_id, "NVIM_MCP_ConfirmResponse", response_id, vim.json.encode({ result = approved }))
+end
+
+-- Ask once the RPC call has returned, so the server isn't blocked on the prompt
+vim.schedule(function()
+    local buf = vim.api.nvim_create_buf(false, true)
+    vim.api.nvim_buf_set_lines(buf, 0, -1, false, preview)
+    vim.bo[buf].filetype = filetype
+    vim.bo[buf].modifiable = false
+    vim.bo[buf].bufhidden = "wipe"
+
+    local width = math.min(math.max(60, #title + 4), vim.o.columns - 4)
+    local height = math.min(math.max(#preview, 1), vim.o.lines - 8)
+    local win = vim.api.nvim_open_win(buf, false, {
+        relative = "editor",
+        width = width,
+        height = height,
+        row = math.floor((vim.o.lines - height) / 2) - 2,
+        col = math.floor((vim.o.columns - width) / 2),
+        style = "minimal",
+        border = "rounded",
+        title = " " .. title .. " ",
+        title_pos = "center",
+    })
+    _G.nvim_mcp_confirmations[key] = { win = win, prompt_wins = {} }
+
+    local wins_before = {}
+    for _, w in ipairs(vim.api.nvim_list_wins()) do
+        wins_before[w] = true
+    end
+    vim.ui.select({ "Approve", "Deny" }, { prompt = title }, function(choice)
+        respond(choice == "Approve")
+    end)
+    -- Windows of a floating picker implementing `vim.ui.select`, closed on timeout
+    local pending = _G.nvim_mcp_confirmations[key]
+    if pending then
+        for _, w in ipairs(vim.api.nvim_list_wins()) do
+            if not wins_before[w] then
+                table.insert(pending.prompt_wins, w)
+            end
+        end
+    end
+end)
+
+return vim.json.encode({ result = true })
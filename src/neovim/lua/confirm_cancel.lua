@@ -0,0 +1,21 @@
+local response_id = unpack({ ... })
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, response_id)
+
+local pending = (_G.nvim_mcp_confirmations or {})[key]
+if pending then
+    _G.nvim_mcp_confirmations[key] = nil
+    -- The builtin prompt waits for input, dismiss it like the user would
+    if vim.api.nvim_get_mode().blocking then
+        vim.api.nvim_input("<Esc>")
+    end
+    for _, win in ipairs(vim.list_extend({ pending.win }, pending.prompt_wins or {})) do
+        if vim.api.nvim_win_is_valid(win) then
+            vim.api.nvim_win_close(win, true)
+        end
+    end
+    if vim.api.nvim_get_mode().mode:sub(1, 1) == "i" then
+        vim.cmd.stopinsert()
+    end
+end
+return vim.json.encode({ result = vim.NIL })
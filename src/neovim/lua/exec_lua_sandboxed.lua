@@ -0,0 +1,214 @@
+local code, allowed_api, denied_names = unpack({ ... })
+
+-- The environment only holds explicitly copied values, anything else that exists
+-- in the real environment is reported as a violation when the code reaches for it.
+-- Writes land in the copies, so the real modules are never modified.
+
+-- `vim.api` functions running code that is not checked by the sandbox: Ex commands,
+-- Vimscript, Lua outside of the sandbox, keys, terminal input and options holding
+-- expressions. They are refused even when allowlisted.
+local blocked_api = {}
+for _, name in ipairs({
+    "nvim_exec_lua",
+    "nvim_command",
+    "nvim_exec",
+    "nvim_exec2",
+    "nvim_cmd",
+    "nvim_eval",
+    "nvim_call_function",
+    "nvim_call_dict_function",
+    "nvim_call_atomic",
+    "nvim_feedkeys",
+    "nvim_input",
+    "nvim_paste",
+    "nvim_chan_send",
+    "nvim_load_context",
+    "nvim_create_autocmd",
+    "nvim_create_user_command",
+    "nvim_buf_create_user_command",
+    "nvim_set_keymap",
+    "nvim_buf_set_keymap",
+    "nvim_set_option_value",
+    "nvim_set_option",
+    "nvim_buf_set_option",
+    "nvim_win_set_option",
+}) do
+    blocked_api[name] = true
+end
+
+-- `vim.fn` functions without side effects outside of reading editor state.
+-- `expand`, `glob` and `substitute` are left out as they can run shell commands
+-- or expressions.
+local safe_fn = {
+    "bufexists",
+    "buflisted",
+    "bufloaded",
+    "bufname",
+    "bufnr",
+    "col",
+    "empty",
+    "filereadable",
+    "fnamemodify",
+    "getbufline",
+    "getcurpos",
+    "getcwd",
+    "getline",
+    "getpos",
+    "has",
+    "indent",
+    "isdirectory",
+    "json_decode",
+    "json_encode",
+    "len",
+    "line",
+    "localtime",
+    "matchstr",
+    "mode",
+    "strchars",
+    "strftime",
+    "strlen",
+    "tabpagenr",
+    "tolower",
+    "toupper",
+    "trim",
+    "win_getid",
+    "winnr",
+}
+
+local safe_vim = {
+    "deepcopy",
+    "endswith",
+    "gsplit",
+    "inspect",
+    "is_callable",
+    "islist",
+    "list_extend",
+    "list_slice",
+    "notify",
+    "pesc",
+    "print",
+    "split",
+    "startswith",
+    "trim",
+    "validate",
+}
+
+local safe_globals = {
+    "_VERSION",
+    "assert",
+    "error",
+    "ipairs",
+    "next",
+    "pairs",
+    "pcall",
+    "print",
+    "rawequal",
+    "select",
+    "setmetatable",
+    "tonumber",
+    "tostring",
+    "type",
+    "unpack",
+    "xpcall",
+}
+
+local violation
+local function deny(name)
+    violation = name
+    error(string.format("nvim-mcp policy: `%s` is not allowed", name), 3)
+end
+
+local function copy(source, names)
+    local result = {}
+    for _, name in ipairs(names) do
+        result[name] = source[name]
+    end
+    return result
+end
+
+-- Report lookups of names that exist in `real` but were not copied into `tbl`
+local function guard(tbl, real, path)
+    for key, value in pairs(tbl) do
+        if type(value) == "table" and type(real[key]) == "table" then
+            guard(value, real[key], path .. key .. ".")
+        end
+    end
+    return setmetatable(tbl, {
+        __index = function(_, key)
+            local ok, exists = pcall(function()
+                return real[key] ~= nil
+            end)
+            if ok and exists then
+                deny(path .. tostring(key))
+            end
+        end,
+        __metatable = false,
+    })
+end
+
+local api = {}
+for name, fn in pairs(vim.api) do
+    if not blocked_api[name] then
+        api[name] = fn
+    end
+end
+if type(allowed_api) == "table" then
+    local allowed = {}
+    for _, name in ipairs(allowed_api) do
+        allowed[name] = api[name]
+    end
+    api = allowed
+end
+
+local sandbox_vim = copy(vim, safe_vim)
+for name, fn in pairs(vim) do
+    if type(name) == "string" and name:match("^tbl_") and type(fn) == "function" then
+        sandbox_vim[name] = fn
+    end
+end
+sandbox_vim.api = api
+sandbox_vim.fn = copy(vim.fn, safe_fn)
+sandbox_vim.json = copy(vim.json, { "encode", "decode" })
+sandbox_vim.log = { levels = vim.deepcopy(vim.log.levels) }
+
+local string_copy = vim.deepcopy(string)
+string_copy.dump = nil
+
+local env = copy(_G, safe_globals)
+env.vim = sandbox_vim
+env.string = string_copy
+env.table = vim.deepcopy(table)
+env.math = vim.deepcopy(math)
+env.coroutine = vim.deepcopy(coroutine)
+env.bit = bit and vim.deepcopy(bit)
+env.os = copy(os, { "clock", "date", "difftime", "time" })
+
+-- Configured denied names win over the allowlists
+for _, name in ipairs(denied_names) do
+    local tbl = env
+    local parts = vim.split(name, ".", { plain = true })
+    for i = 1, #parts - 1 do
+        tbl = type(tbl) == "table" and tbl[parts[i]] or nil
+    end
+    if type(tbl) == "table" then
+        tbl[parts[#parts]] = nil
+    end
+end
+
+guard(env, _G, "")
+env._G = env
+
+local chunk, err = loadstring(code, "exec_lua")
+if not chunk then
+    return { err_msg = err }
+end
+setfenv(chunk, env)
+
+local ok, result = pcall(chunk)
+if violation then
+    return { violation = violation }
+end
+if not ok then
+    return { err_msg = tostring(result) }
+end
+return { result = result }
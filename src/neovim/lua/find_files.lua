@@ -0,0 +1,78 @@
+-- The working directory and its project root come from get_project_root.lua
+local query, max_results, cwd, root = unpack({ ... })
+cwd = vim.fs.normalize(cwd)
+root = vim.fs.normalize(root)
+local in_repository = vim.uv.fs_stat(root .. "/.git") ~= nil
+
+-- Files relative to the root, honoring .gitignore inside a repository
+local files = {}
+local backend = "filesystem"
+if in_repository and vim.fn.executable("git") == 1 then
+    local output = vim.fn.systemlist({
+        "git",
+        "-c",
+        "core.quotePath=false",
+        "-C",
+        root,
+        "ls-files",
+        "--cached",
+        "--others",
+        "--exclude-standard",
+    })
+    if vim.v.shell_error == 0 then
+        backend = "git"
+        files = output
+    end
+end
+if backend == "filesystem" then
+    for name, type in
+        vim.fs.dir(root, {
+            depth = math.huge,
+            skip = function(dir)
+                return not vim.fs.basename(dir):match("^%.")
+            end,
+        })
+    do
+        if type == "file" then
+            table.insert(files, name)
+        end
+    end
+end
+
+local matches
+if query:find("[*?[{]") then
+    local pattern = vim.glob.to_lpeg(query)
+    matches = vim.tbl_filter(function(file)
+        -- Globs without a slash match the file name anywhere
+        return pattern:match(file) ~= nil or (not query:find("/") and pattern:match(vim.fs.basename(file)) ~= nil)
+    end, files)
+    table.sort(matches)
+else
+    matches = vim.fn.matchfuzzy(files, query, { limit = max_results + 1 })
+end
+
+local truncated = #matches > max_results
+
+-- Tracked files may have been deleted
+matches = vim.tbl_filter(function(file)
+    return vim.uv.fs_stat(root .. "/" .. file) ~= nil
+end, vim.list_slice(matches, 1, max_results))
+
+-- Paths relative to the working directory, which project relative paths are resolved against
+local parents = cwd == root and "" or string.rep("../", #vim.split(vim.fs.relpath(root, cwd), "/"))
+local paths = {}
+for _, file in ipairs(matches) do
+    local path = vim.fs.relpath(cwd, root .. "/" .. file) or parents .. file
+    table.insert(paths, path)
+end
+
+return vim.json.encode({
+    result = {
+        cwd = cwd,
+        root = root,
+        backend = backend,
+        -- Left out when empty, an empty table would be encoded as an object
+        files = #paths > 0 and paths or nil,
+        truncated = truncated,
+    },
+})
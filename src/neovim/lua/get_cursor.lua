@@ -0,0 +1,16 @@
+local win = vim.api.nvim_get_current_win()
+local bufnr = vim.api.nvim_win_get_buf(win)
+local row, col = unpack(vim.api.nvim_win_get_cursor(win))
+
+return vim.json.encode({
+    result = {
+        buffer_id = bufnr,
+        name = vim.api.nvim_buf_get_name(bufnr),
+        filetype = vim.bo[bufnr].filetype,
+        position = {
+            line = row - 1,
+            character = vim.lsp.util.character_offset(bufnr, row - 1, col, "utf-16"),
+        },
+        word = vim.fn.expand("<cword>"),
+    },
+})
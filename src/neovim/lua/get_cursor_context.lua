@@ -0,0 +1,24 @@
+local context_lines = unpack({ ... })
+
+local win = vim.api.nvim_get_current_win()
+local bufnr = vim.api.nvim_win_get_buf(win)
+local row, col = unpack(vim.api.nvim_win_get_cursor(win))
+local first_line = math.max(row - 1 - context_lines, 0)
+
+return vim.json.encode({
+    result = {
+        cursor = {
+            buffer_id = bufnr,
+            name = vim.api.nvim_buf_get_name(bufnr),
+            filetype = vim.bo[bufnr].filetype,
+            position = {
+                line = row - 1,
+                character = vim.lsp.util.character_offset(bufnr, row - 1, col, "utf-16"),
+            },
+            word = vim.fn.expand("<cword>"),
+        },
+        document = { buffer_id = bufnr },
+        first_line = first_line,
+        lines = vim.api.nvim_buf_get_lines(bufnr, first_line, row + context_lines, false),
+    },
+})
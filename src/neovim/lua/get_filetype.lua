@@ -0,0 +1,38 @@
+local document_identifier_json = unpack({ ... })
+local document_identifier = vim.json.decode(document_identifier_json)
+
+if document_identifier.buffer_id then
+    local bufnr = document_identifier.buffer_id
+    if not vim.api.nvim_buf_is_valid(bufnr) then
+        return vim.json.encode({
+            err_msg = string.format("Buffer ID %d is not valid", bufnr),
+        })
+    end
+    return vim.json.encode({
+        result = vim.bo[bufnr].filetype,
+    })
+end
+
+local filepath
+if document_identifier.project_relative_path then
+    filepath = vim.fn.getcwd() .. "/" .. document_identifier.project_relative_path
+elseif document_identifier.absolute_path then
+    filepath = document_identifier.absolute_path
+else
+    return vim.json.encode({
+        err_msg = "Invalid DocumentIdentifier: must have buffer_id, project_relative_path, or absolute_path",
+    })
+end
+
+-- Prefer the filetype of an already loaded buffer for this file
+for _, buf in ipairs(vim.api.nvim_list_bufs()) do
+    if vim.api.nvim_buf_get_name(buf) == filepath and vim.bo[buf].filetype ~= "" then
+        return vim.json.encode({
+            result = vim.bo[buf].filetype,
+        })
+    end
+end
+
+return vim.json.encode({
+    result = vim.filetype.match({ filename = filepath }) or "",
+})
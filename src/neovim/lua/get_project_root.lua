@@ -0,0 +1,7 @@
+local cwd = vim.fn.getcwd()
+return vim.json.encode({
+    result = {
+        cwd = cwd,
+        root = vim.fs.root(cwd, ".git") or cwd,
+    },
+})
@@ -0,0 +1,60 @@
+local window_id = unpack({ ... })
+local what = { title = 0, items = 0, idx = 0 }
+
+local list
+if window_id == vim.NIL then
+    list = vim.fn.getqflist(what)
+else
+    if window_id ~= 0 and not vim.api.nvim_win_is_valid(window_id) then
+        return vim.json.encode({ err_msg = string.format("Invalid window ID: %d", window_id) })
+    end
+    list = vim.fn.getloclist(window_id, what)
+end
+
+local types = { E = "error", W = "warning", I = "info", N = "note" }
+
+-- Quickfix lines and byte columns start from 1, LSP positions count UTF-16 code
+-- units from 0. Unloaded buffers are read from disk without loading them.
+local function position(bufnr, lnum, col)
+    local text
+    if vim.api.nvim_buf_is_loaded(bufnr) then
+        text = vim.api.nvim_buf_get_lines(bufnr, lnum - 1, lnum, false)[1]
+    else
+        text = vim.fn.readfile(vim.api.nvim_buf_get_name(bufnr), "", lnum)[lnum]
+    end
+    local byte = math.max(col - 1, 0)
+    return {
+        line = lnum - 1,
+        character = text and vim.str_utfindex(text, "utf-16", math.min(byte, #text), false) or byte,
+    }
+end
+
+local items = {}
+for _, item in ipairs(list.items or {}) do
+    local name = item.bufnr ~= 0 and vim.api.nvim_buf_get_name(item.bufnr) or ""
+    local range = vim.NIL
+    if item.valid == 1 and name ~= "" and item.lnum > 0 then
+        local start = position(item.bufnr, item.lnum, item.col)
+        range = {
+            start = start,
+            -- The end is unknown for most `:make` and `:grep` items
+            ["end"] = item.end_lnum > 0 and position(item.bufnr, item.end_lnum, item.end_col) or start,
+        }
+    end
+    table.insert(items, {
+        path = range ~= vim.NIL and vim.fn.fnamemodify(name, ":p") or vim.NIL,
+        buffer_id = item.bufnr ~= 0 and item.bufnr or vim.NIL,
+        range = range,
+        text = item.text,
+        type = types[item.type:upper()] or vim.NIL,
+    })
+end
+
+return vim.json.encode({
+    result = {
+        title = list.title or "",
+        current = list.idx or 0,
+        -- Left out when empty, an empty table would be encoded as an object
+        items = #items > 0 and items or nil,
+    },
+})
@@ -0,0 +1,50 @@
+local bufnr = vim.api.nvim_get_current_buf()
+local current_mode = vim.api.nvim_get_mode().mode
+local modes = { v = "char", V = "line", ["\22"] = "block" }
+
+-- While visual mode is active the '< and '> marks still hold the previous selection
+local active = modes[current_mode] ~= nil
+local start_pos, end_pos, mode
+if active then
+    start_pos, end_pos, mode = vim.fn.getpos("v"), vim.fn.getpos("."), current_mode
+else
+    start_pos, end_pos, mode = vim.fn.getpos("'<"), vim.fn.getpos("'>"), vim.fn.visualmode()
+end
+if modes[mode] == nil or start_pos[2] == 0 or end_pos[2] == 0 then
+    return vim.json.encode({ err_msg = "No visual selection in the current buffer" })
+end
+
+local text = vim.fn.getregion(start_pos, end_pos, { type = mode })
+local segments = vim.fn.getregionpos(start_pos, end_pos, { type = mode })
+
+-- Convert the 1-based byte column of a region position to a UTF-16 offset,
+-- for end positions past the last byte of their character. Linewise
+-- selections cover their lines entirely
+local function character(pos, is_end)
+    local row = pos[2] - 1
+    local line = vim.api.nvim_buf_get_lines(bufnr, row, row + 1, true)[1]
+    if mode == "V" then
+        return is_end and vim.lsp.util.character_offset(bufnr, row, #line, "utf-16") or 0
+    end
+    local col = math.min(math.max(pos[3], 1), #line + 1)
+    if is_end and col <= #line then
+        col = col + vim.str_utf_end(line, col) + 1
+    end
+    return vim.lsp.util.character_offset(bufnr, row, col - 1, "utf-16")
+end
+
+local first, last = segments[1][1], segments[#segments][2]
+return vim.json.encode({
+    result = {
+        document = { buffer_id = bufnr },
+        name = vim.api.nvim_buf_get_name(bufnr),
+        filetype = vim.bo[bufnr].filetype,
+        mode = modes[mode],
+        active = active,
+        range = {
+            start = { line = first[2] - 1, character = character(first, false) },
+            ["end"] = { line = last[2] - 1, character = character(last, true) },
+        },
+        text = table.concat(text, "\n"),
+    },
+})
@@ -0,0 +1,22 @@
+local changes = {}
+
+for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
+    local name = vim.api.nvim_buf_get_name(bufnr)
+    if vim.api.nvim_buf_is_loaded(bufnr) and vim.bo[bufnr].modified and vim.bo[bufnr].buftype == "" then
+        local saved = {}
+        if name ~= "" and vim.fn.filereadable(name) == 1 then
+            saved = vim.fn.readfile(name)
+        end
+        local current = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+        local path = name ~= "" and vim.fn.fnamemodify(name, ":~:.") or string.format("[No Name %d]", bufnr)
+        local diff = vim.diff(table.concat(saved, "\n") .. "\n", table.concat(current, "\n") .. "\n")
+        table.insert(changes, {
+            buffer_id = bufnr,
+            name = name,
+            diff = string.format("--- a/%s\n+++ b/%s\n%s", path, path, diff),
+        })
+    end
+end
+
+-- An empty table would be encoded as an object
+return vim.json.encode({ result = #changes > 0 and changes or vim.NIL })
@@ -0,0 +1,12 @@
+local windows = {}
+for _, win in ipairs(vim.api.nvim_list_wins()) do
+    local row, col = unpack(vim.api.nvim_win_get_cursor(win))
+    table.insert(windows, {
+        window_id = win,
+        name = vim.api.nvim_buf_get_name(vim.api.nvim_win_get_buf(win)),
+        line = row - 1,
+        column = col,
+        floating = vim.api.nvim_win_get_config(win).relative ~= "",
+    })
+end
+return windows
@@ -0,0 +1,15 @@
+local response_id = unpack({ ... })
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, response_id)
+local requests = _G.nvim_mcp_lsp_requests or {}
+local pending = requests[key]
+if pending == nil then
+    return vim.json.encode({ result = false })
+end
+
+requests[key] = nil
+local client = vim.lsp.get_client_by_id(pending.client_id)
+if client then
+    client:cancel_request(pending.request_id)
+end
+return vim.json.encode({ result = true })
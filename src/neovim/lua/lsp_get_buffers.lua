@@ -4,10 +4,22 @@ local ans = {}
 for _, id in ipairs(all_bufs) do
     local name = vim.api.nvim_buf_get_name(id)
     local line_count = vim.api.nvim_buf_line_count(id)
+    local loaded = vim.api.nvim_buf_is_loaded(id)
+    local size = 0
+    if loaded then
+        size = vim.api.nvim_buf_get_offset(id, line_count)
+    elseif name ~= "" then
+        local stat = vim.uv.fs_stat(name)
+        size = stat and stat.size or 0
+    end
     table.insert(ans, {
         id = id,
         name = name,
         line_count = line_count,
+        filetype = vim.bo[id].filetype,
+        loaded = loaded,
+        listed = vim.bo[id].buflisted,
+        size = size,
     })
 end
 
@@ -0,0 +1,39 @@
+local clients = vim.lsp.get_clients()
+local client_name, method, params_raw, bufnr, response_id = unpack({ ... })
+local client
+for _, v in ipairs(clients) do
+    if v.name == client_name then
+        client = v
+    end
+end
+if client == nil then
+    return vim.json.encode({
+        err_msg = string.format("LSP client %s not found", vim.json.encode(client_name)),
+    })
+end
+
+-- The response is sent back to the requesting channel once the LSP server answers,
+-- so the editor stays responsive while waiting
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, response_id)
+_G.nvim_mcp_lsp_requests = _G.nvim_mcp_lsp_requests or {}
+
+local params = vim.json.decode(params_raw)
+local ok, request_id = client:request(method, params, function(err, result)
+    _G.nvim_mcp_lsp_requests[key] = nil
+    local response
+    if err then
+        response = { err = err }
+    else
+        response = { result = result == nil and vim.NIL or result }
+    end
+    vim.rpcnotify(channel_id, "NVIM_MCP_LspResponse", response_id, vim.json.encode(response))
+end, bufnr)
+if not ok then
+    return vim.json.encode({
+        err_msg = string.format("LSP client %s failed to send %s request", vim.json.encode(client_name), method),
+    })
+end
+
+_G.nvim_mcp_lsp_requests[key] = { client_id = client.id, request_id = request_id }
+return vim.json.encode({ result = request_id })
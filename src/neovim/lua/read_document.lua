@@ -0,0 +1,77 @@
+local document_identifier_json = unpack({ ... })
+local document_identifier = vim.json.decode(document_identifier_json)
+
+local function buffer_document(bufnr)
+    -- Listed buffers that were never loaded (e.g. from `:badd` or a session) have no
+    -- text in memory yet, read them from disk without loading them
+    local name = vim.api.nvim_buf_get_name(bufnr)
+    if not vim.api.nvim_buf_is_loaded(bufnr) and name ~= "" and vim.fn.filereadable(name) == 1 then
+        local filetype = vim.bo[bufnr].filetype
+        if filetype == "" then
+            filetype = vim.filetype.match({ filename = name }) or ""
+        end
+        return vim.json.encode({
+            result = {
+                buffer_id = bufnr,
+                name = name,
+                filetype = filetype,
+                modified = false,
+                text = table.concat(vim.fn.readfile(name), "\n"),
+            },
+        })
+    end
+    return vim.json.encode({
+        result = {
+            buffer_id = bufnr,
+            name = vim.api.nvim_buf_get_name(bufnr),
+            filetype = vim.bo[bufnr].filetype,
+            modified = vim.bo[bufnr].modified,
+            text = table.concat(vim.api.nvim_buf_get_lines(bufnr, 0, -1, false), "\n"),
+        },
+    })
+end
+
+if document_identifier.buffer_id then
+    local bufnr = document_identifier.buffer_id
+    if not vim.api.nvim_buf_is_valid(bufnr) then
+        return vim.json.encode({
+            err_msg = string.format("Buffer ID %d is not valid", bufnr),
+        })
+    end
+    return buffer_document(bufnr)
+end
+
+local filepath
+if document_identifier.project_relative_path then
+    filepath = vim.fn.getcwd() .. "/" .. document_identifier.project_relative_path
+elseif document_identifier.absolute_path then
+    filepath = document_identifier.absolute_path
+else
+    return vim.json.encode({
+        err_msg = "Invalid DocumentIdentifier: must have buffer_id, project_relative_path, or absolute_path",
+    })
+end
+filepath = vim.fs.normalize(filepath)
+
+-- Prefer the unsaved contents of a loaded buffer for this file
+for _, buf in ipairs(vim.api.nvim_list_bufs()) do
+    if vim.api.nvim_buf_is_loaded(buf) and vim.fs.normalize(vim.api.nvim_buf_get_name(buf)) == filepath then
+        return buffer_document(buf)
+    end
+end
+
+if vim.fn.filereadable(filepath) == 0 then
+    return vim.json.encode({
+        err_msg = string.format("File %s is not readable", filepath),
+    })
+end
+
+return vim.json.encode({
+    result = {
+        buffer_id = vim.NIL,
+        name = filepath,
+        filetype = vim.filetype.match({ filename = filepath }) or "",
+        modified = false,
+        text = table.concat(vim.fn.readfile(filepath), "\n"),
+    },
+})
@@ -0,0 +1,12 @@
+local response_id = unpack({ ... })
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, response_id)
+
+local pending = (_G.nvim_mcp_searches or {})[key]
+if pending then
+    _G.nvim_mcp_searches[key] = nil
+    if pending.process then
+        pending.process:kill("sigterm")
+    end
+end
+return vim.json.encode({ result = vim.NIL })
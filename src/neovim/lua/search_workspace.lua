@@ -0,0 +1,316 @@
+local options_json, response_id = unpack({ ... })
+local options = vim.json.decode(options_json)
+local cwd = vim.fs.normalize(vim.fn.getcwd())
+local max_results = options.max_results
+local matches = {}
+-- Set when the search stopped reading results past `max_results`
+local stopped_early = false
+
+-- The result is sent back to the requesting channel once the search completes,
+-- so the editor stays responsive while ripgrep runs
+local channel_id = vim.api.nvim_get_chan_info(0).id
+local key = string.format("%d:%d", channel_id, response_id)
+_G.nvim_mcp_searches = _G.nvim_mcp_searches or {}
+_G.nvim_mcp_searches[key] = {}
+
+-- Globs without a slash match the file name anywhere, like ripgrep's `--glob`
+local function glob_matches(glob, path)
+    local pattern = vim.glob.to_lpeg(glob)
+    return pattern:match(path) ~= nil or (not glob:find("/") and pattern:match(vim.fs.basename(path)) ~= nil)
+end
+
+local function included(path)
+    local relative = vim.fs.relpath(cwd, path)
+    if relative == nil then
+        return false
+    end
+    for _, glob in ipairs(options.exclude) do
+        if glob_matches(glob, relative) then
+            return false
+        end
+    end
+    if #options.include == 0 then
+        return true
+    end
+    for _, glob in ipairs(options.include) do
+        if glob_matches(glob, relative) then
+            return true
+        end
+    end
+    return false
+end
+
+local function add_match(path, line_number, text, start_byte, end_byte)
+    text = text:gsub("\r?\n$", "")
+    table.insert(matches, {
+        uri = vim.uri_from_fname(path),
+        range = {
+            start = {
+                line = line_number - 1,
+                character = vim.str_utfindex(text, "utf-16", math.min(start_byte, #text), false),
+            },
+            ["end"] = {
+                line = line_number - 1,
+                character = vim.str_utfindex(text, "utf-16", math.min(end_byte, #text), false),
+            },
+        },
+        text = text,
+    })
+end
+
+-- Modified buffers inside the working directory, searched instead of their files
+local modified = {}
+for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
+    local name = vim.api.nvim_buf_get_name(bufnr)
+    if vim.api.nvim_buf_is_loaded(bufnr) and vim.bo[bufnr].modified and name ~= "" then
+        local path = vim.fs.normalize(vim.fn.fnamemodify(name, ":p"))
+        if vim.fs.relpath(cwd, path) ~= nil then
+            modified[path] = bufnr
+        end
+    end
+end
+
+local function ripgrep(args)
+    -- Only the executable comes from 'grepprg', its flags such as `--vimgrep` or `-uu`
+    -- would change the output format or the files searched
+    local executable = vim.split(vim.o.grepprg, "%s+", { trimempty = true })[1]
+    local command = { executable, "--json", "--no-config" }
+    if options.fixed_strings then
+        table.insert(command, "--fixed-strings")
+    end
+    if options.ignore_case then
+        table.insert(command, "--ignore-case")
+    end
+    vim.list_extend(command, args)
+    vim.list_extend(command, { "--regexp", options.query })
+    return command
+end
+
+-- Run ripgrep without blocking the editor. Matches are collected from its JSON output
+-- as it arrives, and ripgrep is stopped once `accept` has taken more than `max_results`.
+-- `on_done` is called on the main loop with the collected matches and an error, if any.
+local function run_ripgrep(args, stdin, accept, on_done)
+    local found = {}
+    local errors = {}
+    local buffered = ""
+    local process
+
+    local function read_line(line)
+        local ok, message = pcall(vim.json.decode, line)
+        if not ok or type(message) ~= "table" or message.type ~= "match" then
+            return
+        end
+        for _, submatch in ipairs(message.data.submatches) do
+            local found_match = {
+                path = message.data.path.text,
+                line_number = message.data.line_number,
+                text = message.data.lines.text or "",
+                start_byte = submatch.start,
+                end_byte = submatch["end"],
+            }
+            if found_match.path and accept(found_match.path) then
+                table.insert(found, found_match)
+            end
+        end
+    end
+
+    local ok, result = pcall(vim.system, ripgrep(args), {
+        stdin = stdin,
+        stdout = function(_, data)
+            if data == nil or stopped_early then
+                return
+            end
+            buffered = buffered .. data
+            for line in buffered:gmatch("([^\n]*)\n") do
+                read_line(line)
+            end
+            buffered = buffered:match("[^\n]*$")
+            if #matches + #found > max_results then
+                stopped_early = true
+                process:kill("sigterm")
+            end
+        end,
+        stderr = function(_, data)
+            if data then
+                table.insert(errors, data)
+            end
+        end,
+    }, function(completed)
+        vim.schedule(function()
+            -- ripgrep exits with 1 when nothing matched
+            local err
+            if not stopped_early and completed.code > 1 then
+                err = vim.trim(table.concat(errors))
+            end
+            on_done(found, err)
+        end)
+    end)
+    if not ok then
+        on_done({}, result)
+        return
+    end
+    process = result
+    local pending = _G.nvim_mcp_searches[key]
+    if pending then
+        pending.process = process
+    end
+end
+
+local function search_ripgrep(done)
+    local args = {}
+    for _, glob in ipairs(options.include) do
+        vim.list_extend(args, { "--glob", glob })
+    end
+    for _, glob in ipairs(options.exclude) do
+        vim.list_extend(args, { "--glob", "!" .. glob })
+    end
+    table.insert(args, "--")
+    table.insert(args, cwd)
+
+    -- Unsaved changes are piped through the same search, one buffer after the other
+    local buffers = {}
+    for path, bufnr in pairs(modified) do
+        if included(path) then
+            table.insert(buffers, { path = path, bufnr = bufnr })
+        end
+    end
+    local function search_buffer(i)
+        local buffer = buffers[i]
+        if buffer == nil or stopped_early or _G.nvim_mcp_searches[key] == nil then
+            return done()
+        end
+        local lines = vim.api.nvim_buf_get_lines(buffer.bufnr, 0, -1, false)
+        run_ripgrep({ "-" }, lines, function()
+            return true
+        end, function(found, err)
+            if err then
+                return done(err)
+            end
+            for _, match in ipairs(found) do
+                add_match(buffer.path, match.line_number, match.text, match.start_byte, match.end_byte)
+            end
+            search_buffer(i + 1)
+        end)
+    end
+
+    run_ripgrep(args, nil, function(path)
+        return not modified[vim.fs.normalize(path)]
+    end, function(found, err)
+        if err then
+            return done(err)
+        end
+        for _, match in ipairs(found) do
+            add_match(vim.fs.normalize(match.path), match.line_number, match.text, match.start_byte, match.end_byte)
+        end
+        search_buffer(1)
+    end)
+end
+
+local function search_vimgrep()
+    local pattern
+    if options.fixed_strings then
+        pattern = "\\V" .. options.query:gsub("\\", "\\\\")
+    else
+        pattern = "\\v" .. options.query
+    end
+    if options.ignore_case then
+        pattern = "\\c" .. pattern
+    end
+    pattern = pattern:gsub("/", "\\/")
+
+    local files = {}
+    for _, glob in ipairs(#options.include > 0 and options.include or { "*" }) do
+        local path = (glob:find("/") and glob or "**/" .. glob):gsub(" ", "\\ ")
+        table.insert(files, path)
+    end
+
+    -- Search in a hidden window so the user's location lists are left alone,
+    -- :vimgrep already reads loaded buffers instead of their files
+    local buf = vim.api.nvim_create_buf(false, true)
+    local win = vim.api.nvim_open_win(buf, false, {
+        relative = "editor",
+        row = 0,
+        col = 0,
+        width = 1,
+        height = 1,
+        hide = true,
+        noautocmd = true,
+    })
+    local wildignore = vim.o.wildignore
+    vim.opt.wildignore:append(options.exclude)
+    local ok, err = pcall(vim.api.nvim_win_call, win, function()
+        vim.cmd(
+            string.format("noautocmd %dlvimgrep /%s/gj %s", max_results + 1, pattern, table.concat(files, " "))
+        )
+    end)
+    vim.o.wildignore = wildignore
+    local items = vim.fn.getloclist(win)
+    vim.api.nvim_win_close(win, true)
+    vim.api.nvim_buf_delete(buf, { force = true })
+    if not ok and not tostring(err):find("E480") then
+        error(err, 0)
+    end
+
+    for _, item in ipairs(items) do
+        local path = vim.fs.normalize(vim.fn.fnamemodify(vim.api.nvim_buf_get_name(item.bufnr), ":p"))
+        local text = vim.fn.getbufline(item.bufnr, item.lnum)[1]
+        if text == nil then
+            text = vim.fn.readfile(path, "", item.lnum)[item.lnum] or ""
+        end
+        local end_col = item.end_lnum == item.lnum and item.end_col > 0 and item.end_col or item.col
+        add_match(path, item.lnum, text, item.col - 1, end_col - 1)
+    end
+end
+
+local backend = vim.split(vim.o.grepprg, "%s+", { trimempty = true })[1] == "rg" and "ripgrep" or "vimgrep"
+
+local function respond(err)
+    -- Cancelled by the server in the meantime
+    if _G.nvim_mcp_searches[key] == nil then
+        return
+    end
+    _G.nvim_mcp_searches[key] = nil
+
+    local response
+    if err then
+        response = { err_msg = string.format("Search with %s failed: %s", backend, err) }
+    else
+        table.sort(matches, function(a, b)
+            if a.uri ~= b.uri then
+                return a.uri < b.uri
+            end
+            if a.range.start.line ~= b.range.start.line then
+                return a.range.start.line < b.range.start.line
+            end
+            return a.range.start.character < b.range.start.character
+        end)
+        local truncated = stopped_early or #matches > max_results
+        for i = #matches, max_results + 1, -1 do
+            matches[i] = nil
+        end
+        response = {
+            result = {
+                backend = backend,
+                -- Left out when empty, an empty table would be encoded as an object
+                matches = #matches > 0 and matches or nil,
+                truncated = truncated,
+            },
+        }
+    end
+    vim.rpcnotify(channel_id, "NVIM_MCP_SearchResponse", response_id, vim.json.encode(response))
+end
+
+if backend == "ripgrep" then
+    local ok, err = pcall(search_ripgrep, respond)
+    if not ok then
+        respond(err)
+    end
+else
+    -- Answer once the RPC call has returned, like ripgrep does
+    vim.schedule(function()
+        local ok, err = pcall(search_vimgrep)
+        respond(not ok and err or nil)
+    end)
+end
+
+return vim.json.encode({ result = vim.NIL })
@@ -0,0 +1,65 @@
+local window_id, title, entries_json, action, open = unpack({ ... })
+local entries = vim.json.decode(entries_json)
+local types = { error = "E", warning = "W", info = "I", note = "N" }
+
+-- Document ranges are resolved like LSP locations, which converts the UTF-16
+-- characters to byte columns
+local function document_item(entry)
+    local document = entry.document
+    local uri
+    if document.buffer_id then
+        uri = vim.uri_from_bufnr(document.buffer_id)
+    elseif document.project_relative_path then
+        uri = vim.uri_from_fname(vim.fs.joinpath(vim.fn.getcwd(), document.project_relative_path))
+    else
+        uri = vim.uri_from_fname(document.absolute_path)
+    end
+    local item = vim.lsp.util.locations_to_items({ { uri = uri, range = entry.range } }, "utf-16")[1]
+    if document.buffer_id then
+        item.filename = nil
+        item.bufnr = document.buffer_id
+    end
+    item.text = entry.text ~= vim.NIL and entry.text or ""
+    item.type = types[entry.type]
+    return item
+end
+
+local items = {}
+for _, entry in ipairs(entries) do
+    if entry.document then
+        table.insert(items, document_item(entry))
+    elseif entry.uri and entry.range then
+        vim.list_extend(items, vim.lsp.util.locations_to_items({ entry }, "utf-16"))
+    elseif entry.bufnr and entry.lnum then
+        vim.list_extend(items, vim.diagnostic.toqflist({ entry }))
+    end
+end
+
+local what = { title = title, items = items }
+local result
+if window_id == vim.NIL then
+    result = vim.fn.setqflist({}, action, what)
+else
+    if window_id ~= 0 and not vim.api.nvim_win_is_valid(window_id) then
+        return vim.json.encode({ err_msg = string.format("Invalid window ID: %d", window_id) })
+    end
+    result = vim.fn.setloclist(window_id, {}, action, what)
+end
+if result ~= 0 then
+    return vim.json.encode({ err_msg = "Failed to set the list" })
+end
+
+if open then
+    -- Opening the list window moves the cursor into it, keep it where the user left it
+    local current_win = vim.api.nvim_get_current_win()
+    if window_id == vim.NIL then
+        vim.cmd("copen")
+    else
+        vim.api.nvim_win_call(window_id, function()
+            vim.cmd("lopen")
+        end)
+    end
+    vim.api.nvim_set_current_win(current_win)
+end
+
+return vim.json.encode({ result = vim.NIL })
@@ -0,0 +1,87 @@
+-- Helpers shared by the tree-sitter scripts, which are appended to this one and
+-- receive their arguments through `...` as usual
+
+local MAX_TEXT_LENGTH = 200
+
+-- Syntax tree of a document, parsed from its buffer when loaded, otherwise from the file on disk
+local function parse_document(document_identifier)
+    local bufnr, filepath
+    if document_identifier.buffer_id then
+        bufnr = document_identifier.buffer_id
+        if not vim.api.nvim_buf_is_valid(bufnr) then
+            return nil, string.format("Buffer ID %d is not valid", bufnr)
+        end
+        vim.fn.bufload(bufnr)
+    else
+        if document_identifier.project_relative_path then
+            filepath = vim.fn.getcwd() .. "/" .. document_identifier.project_relative_path
+        elseif document_identifier.absolute_path then
+            filepath = document_identifier.absolute_path
+        else
+            return nil, "Invalid DocumentIdentifier: must have buffer_id, project_relative_path, or absolute_path"
+        end
+        filepath = vim.fs.normalize(filepath)
+        for _, buf in ipairs(vim.api.nvim_list_bufs()) do
+            if vim.api.nvim_buf_is_loaded(buf) and vim.fs.normalize(vim.api.nvim_buf_get_name(buf)) == filepath then
+                bufnr = buf
+                break
+            end
+        end
+        if not bufnr and vim.fn.filereadable(filepath) == 0 then
+            return nil, string.format("File %s is not readable", filepath)
+        end
+    end
+
+    local document = {}
+    local filetype
+    if bufnr then
+        document.lines = vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+        document.source = bufnr
+        filetype = vim.bo[bufnr].filetype
+    else
+        document.lines = vim.fn.readfile(filepath)
+        document.source = table.concat(document.lines, "\n")
+        filetype = vim.filetype.match({ filename = filepath }) or ""
+    end
+    document.language = vim.treesitter.language.get_lang(filetype) or filetype
+
+    local ok, parser = pcall(function()
+        if bufnr then
+            return vim.treesitter.get_parser(bufnr, document.language, { error = false })
+        end
+        return vim.treesitter.get_string_parser(document.source, document.language)
+    end)
+    if not ok or not parser then
+        return nil, string.format("No tree-sitter parser for filetype '%s'", filetype)
+    end
+    document.root = parser:parse()[1]:root()
+    return document
+end
+
+local function character(document, row, col)
+    local line = document.lines[row + 1] or ""
+    return vim.str_utfindex(line, "utf-16", math.min(col, #line), false)
+end
+
+-- LSP range of a node, with UTF-16 characters
+local function node_range(document, node)
+    local start_row, start_col, end_row, end_col = node:range()
+    return {
+        start = { line = start_row, character = character(document, start_row, start_col) },
+        ["end"] = { line = end_row, character = character(document, end_row, end_col) },
+    }
+end
+
+local function node_info(document, node)
+    local text = vim.treesitter.get_node_text(node, document.source)
+    if #text > MAX_TEXT_LENGTH then
+        -- Cut at a character boundary
+        text = text:sub(1, MAX_TEXT_LENGTH + vim.str_utf_start(text, MAX_TEXT_LENGTH + 1)) .. "…"
+    end
+    return {
+        type = node:type(),
+        named = node:named(),
+        range = node_range(document, node),
+        text = text,
+    }
+end
@@ -0,0 +1,36 @@
+local document_identifier_json, line, character_offset = unpack({ ... })
+local document_identifier = vim.json.decode(document_identifier_json)
+
+-- `parse_document` and `node_info` come from treesitter_document.lua
+local document, err = parse_document(document_identifier)
+if not document then
+    return vim.json.encode({ err_msg = err })
+end
+
+local text = document.lines[line + 1] or ""
+local col = vim.str_byteindex(text, "utf-16", character_offset, false)
+local node = document.root:named_descendant_for_range(line, col, line, col)
+
+-- Innermost node first, each with the field it fills in its parent
+local nodes = {}
+while node do
+    local info = node_info(document, node)
+    local parent = node:parent()
+    if parent then
+        for child, field in parent:iter_children() do
+            if child:equal(node) then
+                info.field = field
+                break
+            end
+        end
+    end
+    table.insert(nodes, info)
+    node = parent
+end
+
+return vim.json.encode({
+    result = {
+        language = document.language,
+        nodes = nodes,
+    },
+})
@@ -0,0 +1,134 @@
+local document_identifier_json = unpack({ ... })
+local document_identifier = vim.json.decode(document_identifier_json)
+
+-- LSP SymbolKind of the definition captures, others such as parameters and imports are left out
+local SYMBOL_KINDS = {
+    module = 2,
+    namespace = 3,
+    package = 4,
+    class = 5,
+    method = 6,
+    field = 8,
+    constructor = 9,
+    enum = 10,
+    interface = 11,
+    ["function"] = 12,
+    macro = 12,
+    var = 13,
+    constant = 14,
+    struct = 23,
+    type = 23,
+}
+local CALLABLE_KINDS = { [6] = true, [9] = true, [12] = true }
+
+-- `parse_document` and `node_range` come from treesitter_document.lua
+local document, err = parse_document(document_identifier)
+if not document then
+    return vim.json.encode({ err_msg = err })
+end
+
+local query_name = "tags"
+local query = vim.treesitter.query.get(document.language, query_name)
+if not query then
+    query_name = "locals"
+    query = vim.treesitter.query.get(document.language, query_name)
+end
+if not query then
+    return vim.json.encode({
+        err_msg = string.format("No tree-sitter tags or locals query for language '%s'", document.language),
+    })
+end
+
+-- Locals queries capture the name only, the definition is the node naming it
+local function definition_node(name_node)
+    local node = name_node
+    for _ = 1, 3 do
+        local parent = node:parent()
+        if not parent then
+            break
+        end
+        local name = parent:field("name")[1]
+        if name and name:equal(node) then
+            return parent
+        end
+        node = parent
+    end
+    return name_node:parent() or name_node
+end
+
+local symbols = {}
+local seen = {}
+for _, match in query:iter_matches(document.root, document.source, nil, nil, { all = true }) do
+    local definition, kind, name_node
+    for id, nodes in pairs(match) do
+        local capture = query.captures[id]
+        local suffix = capture:match("^definition%.(.+)$") or capture:match("^local%.definition%.(.+)$")
+        if suffix and SYMBOL_KINDS[suffix] then
+            definition, kind = nodes[1], SYMBOL_KINDS[suffix]
+        elseif capture == "name" then
+            name_node = nodes[1]
+        end
+    end
+    if definition then
+        if query_name == "locals" then
+            name_node = definition
+            definition = definition_node(definition)
+        end
+        name_node = name_node or definition
+        local key = table.concat({ name_node:range() }, ":")
+        if not seen[key] then
+            seen[key] = true
+            table.insert(symbols, {
+                name = vim.treesitter.get_node_text(name_node, document.source),
+                kind = kind,
+                range = node_range(document, definition),
+                selectionRange = node_range(document, name_node),
+            })
+        end
+    end
+end
+
+-- Nest symbols by range, outer symbols sort before the ones they contain
+local function before(a, b)
+    if a.line ~= b.line then
+        return a.line < b.line
+    end
+    return a.character < b.character
+end
+table.sort(symbols, function(a, b)
+    if before(a.range.start, b.range.start) or before(b.range.start, a.range.start) then
+        return before(a.range.start, b.range.start)
+    end
+    return before(b.range["end"], a.range["end"])
+end)
+
+local outline = {}
+local stack = {}
+for _, symbol in ipairs(symbols) do
+    while #stack > 0 and before(stack[#stack].range["end"], symbol.range["end"]) do
+        table.remove(stack)
+    end
+    local parent = stack[#stack]
+    -- Variables local to functions are not part of the outline
+    local in_callable = false
+    for _, ancestor in ipairs(stack) do
+        in_callable = in_callable or CALLABLE_KINDS[ancestor.kind] ~= nil
+    end
+    if not (symbol.kind == SYMBOL_KINDS.var and in_callable) then
+        if parent then
+            parent.children = parent.children or {}
+            table.insert(parent.children, symbol)
+        else
+            table.insert(outline, symbol)
+        end
+        table.insert(stack, symbol)
+    end
+end
+
+return vim.json.encode({
+    result = {
+        provider = query_name,
+        -- Left out when empty, an empty table would be encoded as an object
+        symbols = #outline > 0 and outline or nil,
+    },
+})
@@ -0,0 +1,35 @@
+local document_identifier_json, query_text, start_line, end_line, max_results = unpack({ ... })
+local document_identifier = vim.json.decode(document_identifier_json)
+
+-- `parse_document` and `node_info` come from treesitter_document.lua
+local document, err = parse_document(document_identifier)
+if not document then
+    return vim.json.encode({ err_msg = err })
+end
+
+local ok, query = pcall(vim.treesitter.query.parse, document.language, query_text)
+if not ok then
+    return vim.json.encode({ err_msg = string.format("Invalid %s query: %s", document.language, query) })
+end
+
+local captures = {}
+local truncated = false
+local end_row = end_line and end_line + 1
+for id, node in query:iter_captures(document.root, document.source, start_line, end_row) do
+    if #captures == max_results then
+        truncated = true
+        break
+    end
+    local capture = node_info(document, node)
+    capture.capture = query.captures[id]
+    table.insert(captures, capture)
+end
+
+return vim.json.encode({
+    result = {
+        language = document.language,
+        -- Left out when empty, an empty table would be encoded as an object
+        captures = #captures > 0 and captures or nil,
+        truncated = truncated,
+    },
+})
@@ -6,8 +6,10 @@ mod error;
 pub mod integration_tests;
 
 pub use client::{
-    CodeAction, DocumentIdentifier, FormattingOptions, NeovimClient, NeovimClientTrait, Position,
-    PrepareRenameResult, Range, WorkspaceEdit, string_or_struct,
+    CodeAction, ConfirmPreview, CursorPosition, DocumentIdentifier, DocumentOutline,
+    FormattingOptions, HoverResult, NO_UI_REASON, NeovimClient, NeovimClientTrait, OutlineSource,
+    Position, PrepareRenameResult, QuickfixAction, QuickfixEntry, Range, SearchOptions,
+    WorkspaceEdit, string_or_struct, with_lsp_timeout,
 };
 
 pub use error::NeovimError;
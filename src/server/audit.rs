@@ -0,0 +1,206 @@
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex, mpsc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rmcp::model::{CallToolResult, JsonObject};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::neovim::{DocumentIdentifier, WorkspaceEdit};
+
+/// Number of entries kept in memory for the `nvim-audit://` resource
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// A mutating tool call recorded in the audit log
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AuditEntry {
+    /// Start of the call in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub tool: String,
+    pub connection_id: Option<String>,
+    /// Socket path or address of the Neovim instance
+    pub target: Option<String>,
+    /// Documents named by the call or touched by the applied edit, buffers as `buffer:<id>`
+    pub files: Vec<String>,
+    /// Tool arguments, including the edit payload or Lua code
+    pub arguments: Option<JsonObject>,
+    pub success: bool,
+    /// Structured content of the result, or its text when there is none
+    pub result: Option<Value>,
+    /// Workspace edit computed and applied by the tool, e.g. by `lsp_rename`
+    pub workspace_edit: Option<Value>,
+    /// Error message of a failed call
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl AuditEntry {
+    /// Start an entry for a tool call, completed with [`AuditEntry::finish`]
+    pub fn new(tool: &str, arguments: Option<&JsonObject>) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            tool: tool.to_string(),
+            connection_id: arguments
+                .and_then(|args| args.get("connection_id"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            target: None,
+            files: arguments.map(affected_files).unwrap_or_default(),
+            arguments: arguments.cloned(),
+            success: false,
+            result: None,
+            workspace_edit: None,
+            error: None,
+            duration_ms: 0,
+        }
+    }
+
+    /// Record the outcome of the call
+    pub fn finish(
+        mut self,
+        result: &Result<CallToolResult, rmcp::ErrorData>,
+        elapsed: Duration,
+    ) -> Self {
+        self.duration_ms = elapsed.as_millis() as u64;
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.error = Some(e.message.to_string());
+                return self;
+            }
+        };
+        self.success = result.is_error != Some(true);
+
+        let mut structured = result.structured_content.clone();
+        // Tools applying an edit they computed themselves report it separately
+        if let Some(edit) = structured
+            .as_mut()
+            .and_then(Value::as_object_mut)
+            .and_then(|content| content.remove("workspace_edit"))
+        {
+            if let Ok(parsed) = serde_json::from_value::<WorkspaceEdit>(edit.clone()) {
+                for uri in parsed.affected_uris() {
+                    if !self.files.contains(&uri) {
+                        self.files.push(uri);
+                    }
+                }
+            }
+            self.workspace_edit = Some(edit);
+        }
+        self.result = structured
+            .filter(|content| content.as_object().is_none_or(|map| !map.is_empty()))
+            .or_else(|| {
+                let text: Vec<&str> = result
+                    .content
+                    .iter()
+                    .filter_map(|content| content.as_text().map(|text| text.text.as_str()))
+                    .collect();
+                (!text.is_empty()).then(|| Value::String(text.join("\n")))
+            });
+        self
+    }
+}
+
+/// Append-only record of the tool calls that modified the editor
+#[derive(Clone, Default)]
+pub struct AuditLog {
+    /// Lines for the thread appending to the audit file, so recording an entry
+    /// never blocks the async runtime on disk I/O
+    writer: Option<mpsc::Sender<String>>,
+    entries: Arc<Mutex<VecDeque<AuditEntry>>>,
+}
+
+impl AuditLog {
+    /// Audit log that also appends every entry as a JSON line to `path`
+    pub fn with_file(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (writer, lines) = mpsc::channel::<String>();
+        // Stops once every clone of the log has been dropped
+        std::thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || {
+                for line in lines {
+                    if let Err(e) = writeln!(file, "{line}").and_then(|_| file.flush()) {
+                        warn!("Failed to write audit log entry: {e}");
+                    }
+                }
+            })?;
+        Ok(Self {
+            writer: Some(writer),
+            entries: Arc::default(),
+        })
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        if let Some(writer) = &self.writer {
+            match serde_json::to_string(&entry) {
+                Ok(line) => {
+                    if writer.send(line).is_err() {
+                        warn!("Failed to write audit log entry: the writer has stopped");
+                    }
+                }
+                Err(e) => warn!("Failed to serialize audit log entry: {e}"),
+            }
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == MAX_AUDIT_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Entries recorded by this server, oldest first
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+}
+
+/// Documents named in the tool arguments, either directly or through a workspace edit
+pub fn affected_files(arguments: &JsonObject) -> Vec<String> {
+    let mut files = Vec::new();
+    if let Some(document) = arguments
+        .get("document")
+        .and_then(parse_argument::<DocumentIdentifier>)
+    {
+        files.push(match document {
+            DocumentIdentifier::BufferId(id) => format!("buffer:{id}"),
+            DocumentIdentifier::ProjectRelativePath(path)
+            | DocumentIdentifier::AbsolutePath(path) => path.display().to_string(),
+        });
+    }
+    if let Some(edit) = arguments
+        .get("workspace_edit")
+        .and_then(parse_argument::<WorkspaceEdit>)
+    {
+        files.extend(edit.affected_uris());
+    }
+    files
+}
+
+/// Parse an argument given either as a JSON value or as a JSON string
+pub fn parse_argument<T>(value: &Value) -> Option<T>
+where
+    T: DeserializeOwned + FromStr,
+{
+    match value {
+        Value::String(s) => s.parse().ok(),
+        value => serde_json::from_value(value.clone()).ok(),
+    }
+}
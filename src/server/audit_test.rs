@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rmcp::model::{CallToolResult, Content, JsonObject};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use crate::server::audit::*;
+
+    fn arguments(value: serde_json::Value) -> JsonObject {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_affected_files() {
+        let args = arguments(json!({
+            "connection_id": "abc1234",
+            "document": {"absolute_path": "/project/src/main.rs"},
+        }));
+        assert_eq!(affected_files(&args), vec!["/project/src/main.rs"]);
+
+        // Arguments given as JSON strings are parsed too
+        let args = arguments(json!({
+            "document": "{\"buffer_id\": 3}",
+            "workspace_edit": {
+                "changes": {"file:///project/a.rs": [], "file:///project/b.rs": []},
+            },
+        }));
+        assert_eq!(
+            affected_files(&args),
+            vec!["buffer:3", "file:///project/a.rs", "file:///project/b.rs"]
+        );
+
+        assert!(affected_files(&arguments(json!({"code": "print(1)"}))).is_empty());
+    }
+
+    #[test]
+    fn test_audit_log_appends_jsonl() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("logs").join("audit.jsonl");
+        let log = AuditLog::with_file(&path).unwrap();
+
+        let args = arguments(json!({"connection_id": "abc1234", "code": "vim.cmd('w')"}));
+        let ok = Ok(CallToolResult::success(vec![Content::text("done")]));
+        log.record(AuditEntry::new("exec_lua", Some(&args)).finish(&ok, Duration::from_millis(5)));
+        let err = Err(rmcp::ErrorData::invalid_request("denied", None));
+        log.record(AuditEntry::new("lsp_rename", None).finish(&err, Duration::ZERO));
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].connection_id.as_deref(), Some("abc1234"));
+        assert!(entries[0].success);
+        assert_eq!(entries[0].result, Some(json!("done")));
+        assert_eq!(entries[0].duration_ms, 5);
+        assert_eq!(entries[1].error.as_deref(), Some("denied"));
+
+        // A second log on the same file appends instead of truncating
+        let log = AuditLog::with_file(&path).unwrap();
+        log.record(AuditEntry::new("navigate_to_file", None).finish(&ok, Duration::ZERO));
+
+        // Lines are written in the background
+        let mut content = String::new();
+        for _ in 0..100 {
+            content = std::fs::read_to_string(&path).unwrap();
+            if content.lines().count() == 3 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let tools: Vec<String> = content
+            .lines()
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+                entry["tool"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(tools, vec!["exec_lua", "lsp_rename", "navigate_to_file"]);
+    }
+
+    #[test]
+    fn test_audit_entry_records_applied_edit() {
+        let args = arguments(json!({
+            "document": {"project_relative_path": "src/lib.rs"},
+            "new_name": "renamed",
+        }));
+        let edit = json!({
+            "changes": {"file:///project/src/lib.rs": [], "file:///project/src/main.rs": []},
+        });
+        let result = Ok(CallToolResult {
+            structured_content: Some(json!({ "workspace_edit": edit })),
+            ..CallToolResult::success(vec![Content::text("Rename completed successfully")])
+        });
+        let entry = AuditEntry::new("lsp_rename", Some(&args)).finish(&result, Duration::ZERO);
+
+        assert!(entry.success);
+        assert_eq!(entry.workspace_edit, Some(edit));
+        assert_eq!(entry.result, Some(json!("Rename completed successfully")));
+        assert_eq!(
+            entry.files,
+            vec![
+                "src/lib.rs",
+                "file:///project/src/lib.rs",
+                "file:///project/src/main.rs"
+            ]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use rmcp::model::{ArgumentInfo, CompletionContext, CompletionInfo, Reference};
+use tracing::debug;
+
+use super::core::NeovimMcpServer;
+use super::prompts::prompt_definitions;
+use super::resources::resource_templates;
+use crate::neovim::{NeovimClientTrait, NeovimError};
+
+/// Live state an argument is completed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionSource {
+    Connections,
+    LspClients,
+    BufferIds,
+    Paths,
+    /// Buffer IDs and paths
+    Documents,
+}
+
+/// Source for an argument of the referenced prompt or resource template, none
+/// when the reference is unknown or doesn't declare the argument
+pub fn completion_source(reference: &Reference, argument: &str) -> Option<CompletionSource> {
+    match reference {
+        Reference::Prompt(prompt) => {
+            let declared = prompt_definitions()
+                .into_iter()
+                .find(|definition| definition.name == prompt.name)?
+                .arguments?
+                .iter()
+                .any(|definition| definition.name == argument);
+            if !declared {
+                return None;
+            }
+            match argument {
+                "connection_id" => Some(CompletionSource::Connections),
+                "lsp_client_name" => Some(CompletionSource::LspClients),
+                "document" => Some(CompletionSource::Documents),
+                _ => None,
+            }
+        }
+        Reference::Resource(resource) => {
+            let variable = format!("{{{argument}}}");
+            let declared = resource_templates().iter().any(|template| {
+                template.raw.uri_template == resource.uri
+                    && template.raw.uri_template.contains(&variable)
+            });
+            if !declared {
+                return None;
+            }
+            match argument {
+                "connection_id" => Some(CompletionSource::Connections),
+                "buffer_id" => Some(CompletionSource::BufferIds),
+                "path" => Some(CompletionSource::Paths),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Completion values for `value`: prefix matches first, then other substring
+/// matches, ignoring case and limited to [`CompletionInfo::MAX_VALUES`]
+pub fn filter_completions(candidates: Vec<String>, value: &str) -> CompletionInfo {
+    let needle = value.to_lowercase();
+    let mut prefixed = Vec::new();
+    let mut contained = Vec::new();
+    for candidate in candidates {
+        if prefixed.contains(&candidate) || contained.contains(&candidate) {
+            continue;
+        }
+        let lowercase = candidate.to_lowercase();
+        if lowercase.starts_with(&needle) {
+            prefixed.push(candidate);
+        } else if lowercase.contains(&needle) {
+            contained.push(candidate);
+        }
+    }
+    prefixed.extend(contained);
+
+    let total = prefixed.len();
+    prefixed.truncate(CompletionInfo::MAX_VALUES);
+    CompletionInfo {
+        values: prefixed,
+        total: Some(total as u32),
+        has_more: Some(total > CompletionInfo::MAX_VALUES),
+    }
+}
+
+/// Buffer IDs and names of the connection, names relative to the working directory when inside it
+async fn buffer_candidates(
+    client: &(dyn NeovimClientTrait + Send),
+    ids: bool,
+    paths: bool,
+) -> Result<Vec<String>, NeovimError> {
+    let buffers = client.get_buffers().await?;
+    let cwd = if paths {
+        Some(client.get_project_root().await?.cwd)
+    } else {
+        None
+    };
+
+    let mut candidates = Vec::new();
+    for buffer in buffers {
+        if ids {
+            candidates.push(buffer.id.to_string());
+        }
+        if let Some(cwd) = &cwd
+            && !buffer.name.is_empty()
+        {
+            let path = Path::new(&buffer.name);
+            let path = path.strip_prefix(cwd).unwrap_or(path);
+            candidates.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(candidates)
+}
+
+impl NeovimMcpServer {
+    /// Connections to complete from: the one named in the context, otherwise all of them
+    fn completion_connections(&self, context: Option<&CompletionContext>) -> Vec<String> {
+        match context.and_then(|c| c.get_argument("connection_id")) {
+            Some(connection_id) => vec![connection_id.clone()],
+            None => self
+                .nvim_clients
+                .iter()
+                .map(|entry| entry.key().clone())
+                .collect(),
+        }
+    }
+
+    async fn completion_candidates(
+        &self,
+        source: CompletionSource,
+        context: Option<&CompletionContext>,
+    ) -> Result<Vec<String>, NeovimError> {
+        if source == CompletionSource::Connections {
+            return Ok(self
+                .nvim_clients
+                .iter()
+                .map(|entry| entry.key().clone())
+                .collect());
+        }
+
+        let mut candidates = Vec::new();
+        for connection_id in self.completion_connections(context) {
+            let Some(client) = self.nvim_clients.get(&connection_id) else {
+                continue;
+            };
+            let client = client.value().as_ref();
+            match source {
+                CompletionSource::LspClients => candidates.extend(
+                    client
+                        .lsp_get_clients()
+                        .await?
+                        .into_iter()
+                        .map(|lsp_client| lsp_client.name),
+                ),
+                CompletionSource::BufferIds => {
+                    candidates.extend(buffer_candidates(client, true, false).await?)
+                }
+                CompletionSource::Paths => {
+                    candidates.extend(buffer_candidates(client, false, true).await?)
+                }
+                CompletionSource::Documents => {
+                    candidates.extend(buffer_candidates(client, true, true).await?)
+                }
+                CompletionSource::Connections => {}
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Complete a prompt or resource template argument from the state of the connections
+    pub async fn complete_argument(
+        &self,
+        reference: &Reference,
+        argument: &ArgumentInfo,
+        context: Option<&CompletionContext>,
+    ) -> CompletionInfo {
+        let Some(source) = completion_source(reference, &argument.name) else {
+            return CompletionInfo::default();
+        };
+        let candidates = self
+            .completion_candidates(source, context)
+            .await
+            .unwrap_or_else(|e| {
+                // Completion is best effort, a failing connection offers no values
+                debug!("Failed to complete {}: {e}", argument.name);
+                Vec::new()
+            });
+        filter_completions(candidates, &argument.value)
+    }
+}
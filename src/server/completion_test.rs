@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use rmcp::model::{CompletionInfo, Reference};
+
+    use crate::server::completion::*;
+    use crate::server::prompts::{EXPLAIN_SYMBOL, FIX_DIAGNOSTICS};
+
+    fn values(candidates: &[&str], value: &str) -> Vec<String> {
+        filter_completions(candidates.iter().map(|c| c.to_string()).collect(), value).values
+    }
+
+    #[test]
+    fn test_filter_completions_prefix_first() {
+        assert_eq!(
+            values(&["src/main.rs", "src/lib.rs", "lib.rs", "README.md"], "lib"),
+            vec!["lib.rs", "src/lib.rs"]
+        );
+        assert_eq!(
+            values(&["rust_analyzer", "lua_ls"], "RUST"),
+            vec!["rust_analyzer"]
+        );
+        assert_eq!(values(&["1", "12", "1"], ""), vec!["1", "12"]);
+        assert!(values(&["lua_ls"], "clangd").is_empty());
+    }
+
+    #[test]
+    fn test_filter_completions_limit() {
+        let candidates = (0..150).map(|i| i.to_string()).collect();
+        let completion = filter_completions(candidates, "");
+        assert_eq!(completion.values.len(), CompletionInfo::MAX_VALUES);
+        assert_eq!(completion.total, Some(150));
+        assert_eq!(completion.has_more, Some(true));
+    }
+
+    #[test]
+    fn test_completion_source_from_reference() {
+        let fix_diagnostics = Reference::for_prompt(FIX_DIAGNOSTICS);
+        assert_eq!(
+            completion_source(&fix_diagnostics, "document"),
+            Some(CompletionSource::Documents)
+        );
+        assert_eq!(
+            completion_source(&fix_diagnostics, "connection_id"),
+            Some(CompletionSource::Connections)
+        );
+        // Arguments are only completed for the references declaring them
+        assert_eq!(completion_source(&fix_diagnostics, "lsp_client_name"), None);
+        assert_eq!(
+            completion_source(&Reference::for_prompt(EXPLAIN_SYMBOL), "lsp_client_name"),
+            Some(CompletionSource::LspClients)
+        );
+        assert_eq!(
+            completion_source(&Reference::for_prompt("unknown"), "connection_id"),
+            None
+        );
+
+        let buffer = Reference::for_resource("nvim-buffer://{connection_id}/{buffer_id}");
+        assert_eq!(
+            completion_source(&buffer, "buffer_id"),
+            Some(CompletionSource::BufferIds)
+        );
+        assert_eq!(completion_source(&buffer, "path"), None);
+        assert_eq!(
+            completion_source(
+                &Reference::for_resource("nvim-symbols://{connection_id}/{path}"),
+                "path"
+            ),
+            Some(CompletionSource::Paths)
+        );
+        assert_eq!(
+            completion_source(
+                &Reference::for_resource("nvim-connections://"),
+                "connection_id"
+            ),
+            None
+        );
+    }
+}
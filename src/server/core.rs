@@ -1,14 +1,29 @@
 use std::{
     path::{Path, PathBuf},
     process::Command,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
 };
 
-use dashmap::DashMap;
-use rmcp::{ErrorData as McpError, handler::server::router::tool::ToolRouter};
+use dashmap::{DashMap, DashSet};
+use rmcp::{
+    ErrorData as McpError,
+    handler::server::router::tool::ToolRouter,
+    transport::{
+        StreamableHttpService, streamable_http_server::session::local::LocalSessionManager,
+    },
+};
+use serde_json::json;
 use tracing::debug;
 
-use crate::neovim::{NeovimClientTrait, NeovimError};
+use super::audit::AuditLog;
+use super::resources::ResourceSubscription;
+use crate::config::{
+    ConfigError, ConfirmConfig, ExecLuaPolicy, LspConfig, PathPolicy, ServerConfig,
+};
+use crate::neovim::{DocumentIdentifier, NeovimClientTrait, NeovimError};
 
 impl From<NeovimError> for McpError {
     fn from(err: NeovimError) -> Self {
@@ -18,16 +33,55 @@ impl From<NeovimError> for McpError {
                 McpError::invalid_request(format!("LSP Error: {code}, {message}"), None)
             }
             NeovimError::Api(msg) => McpError::internal_error(msg, None),
+            NeovimError::Timeout { method, timeout_ms } => McpError::internal_error(
+                format!("LSP request {method} timed out after {timeout_ms}ms"),
+                Some(json!({ "method": method, "timeout_ms": timeout_ms })),
+            ),
+            NeovimError::PolicyViolation { rule, detail } => McpError::invalid_request(
+                format!("Policy violation ({rule}): {detail}"),
+                Some(json!({ "rule": rule, "detail": detail })),
+            ),
+            NeovimError::ConfirmationDenied { action, reason } => McpError::invalid_request(
+                format!("Confirmation denied for {action}: {reason}"),
+                Some(json!({ "policy": "confirmation", "action": action, "reason": reason })),
+            ),
+            NeovimError::PathOutsideRoots { path, roots } => McpError::invalid_params(
+                format!("Path {path} is outside the allowed roots {roots:?}"),
+                Some(json!({ "policy": "path_confinement", "path": path, "roots": roots })),
+            ),
+            NeovimError::Vim { code, message } => McpError::invalid_request(
+                match &code {
+                    Some(code) => format!("Vim error: {code}: {message}"),
+                    None => format!("Vim error: {message}"),
+                },
+                Some(json!({ "code": code, "message": message })),
+            ),
         }
     }
 }
 
+#[derive(Clone)]
 pub struct NeovimMcpServer {
     pub nvim_clients: Arc<DashMap<String, Box<dyn NeovimClientTrait + Send>>>,
     pub tool_router: ToolRouter<Self>,
     pub socket_path: PathBuf,
+    pub lsp_config: LspConfig,
+    pub exec_lua_policy: ExecLuaPolicy,
+    pub confirm: ConfirmConfig,
+    pub path_policy: PathPolicy,
+    pub read_only: bool,
+    /// Connections on which mutating tools are rejected
+    pub read_only_connections: Arc<DashSet<String>>,
+    pub audit_log: AuditLog,
+    /// Resource subscriptions of MCP clients, keyed by URI
+    pub resource_subscriptions: Arc<DashMap<String, ResourceSubscription>>,
+    /// MCP session served by this instance, see [`NeovimMcpServer::new_session`]
+    pub session_id: u64,
 }
 
+/// Source of [`NeovimMcpServer::session_id`]
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
 impl NeovimMcpServer {
     pub fn new(socket_path: PathBuf) -> Self {
         debug!(
@@ -38,13 +92,73 @@ impl NeovimMcpServer {
             nvim_clients: Arc::new(DashMap::new()),
             tool_router: crate::server::tools::build_tool_router(),
             socket_path,
+            lsp_config: LspConfig::default(),
+            exec_lua_policy: ExecLuaPolicy::default(),
+            confirm: ConfirmConfig::default(),
+            path_policy: PathPolicy::default(),
+            read_only: false,
+            read_only_connections: Arc::new(DashSet::new()),
+            audit_log: AuditLog::default(),
+            resource_subscriptions: Arc::new(DashMap::new()),
+            session_id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Server for another MCP session, sharing the connections and subscriptions
+    /// of this one under its own session ID
+    pub fn new_session(&self) -> Self {
+        Self {
+            session_id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+            ..self.clone()
         }
     }
 
+    /// Create a server from a loaded configuration
+    pub fn with_config(config: &ServerConfig) -> Result<Self, ConfigError> {
+        debug!(
+            "Creating new NeovimMcpServer instance with config: {:?}",
+            config
+        );
+        let audit_log = match &config.audit_log {
+            Some(path) => AuditLog::with_file(path).map_err(|e| {
+                ConfigError::Filesystem(format!("Cannot open audit log {}: {}", path.display(), e))
+            })?,
+            None => AuditLog::default(),
+        };
+        Ok(Self {
+            nvim_clients: Arc::new(DashMap::new()),
+            tool_router: crate::server::tools::build_tool_router_with_config(
+                &config.tools,
+                &config.safety,
+            )?,
+            socket_path: config.socket_path.clone(),
+            lsp_config: config.lsp.clone(),
+            exec_lua_policy: config.safety.exec_lua.clone(),
+            confirm: config.safety.confirm.clone(),
+            path_policy: config.safety.paths.clone(),
+            read_only: config.safety.is_read_only(),
+            read_only_connections: Arc::new(DashSet::new()),
+            audit_log,
+            resource_subscriptions: Arc::new(DashMap::new()),
+            session_id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+
     pub fn router(&self) -> &ToolRouter<Self> {
         &self.tool_router
     }
 
+    /// Router serving the streamable HTTP transport at `/mcp`. Every HTTP session
+    /// shares this server and its connection map.
+    pub fn into_http_router(self) -> axum::Router {
+        let service = StreamableHttpService::new(
+            move || Ok(self.new_session()),
+            LocalSessionManager::default().into(),
+            Default::default(),
+        );
+        axum::Router::new().nest_service("/mcp", service)
+    }
+
     /// Generate shorter connection ID with collision detection
     pub fn generate_shorter_connection_id(&self, target: &str) -> String {
         let full_hash = b3sum(target);
@@ -87,6 +201,33 @@ impl NeovimMcpServer {
             )
         })
     }
+
+    /// Resolve the LSP client name for a document, falling back to the
+    /// configured default client for the document's filetype
+    pub async fn resolve_lsp_client_name(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        document: &DocumentIdentifier,
+        lsp_client_name: Option<String>,
+    ) -> Result<String, McpError> {
+        if let Some(name) = lsp_client_name {
+            return Ok(name);
+        }
+
+        let filetype = client.get_filetype(document).await?;
+        self.lsp_config
+            .default_clients
+            .get(&filetype)
+            .cloned()
+            .ok_or_else(|| {
+                McpError::invalid_params(
+                    format!(
+                        "lsp_client_name is required, no default LSP client configured for filetype {filetype:?}"
+                    ),
+                    None,
+                )
+            })
+    }
 }
 
 /// Generate BLAKE3 hash from input string
@@ -102,8 +243,7 @@ fn escape_path(path: &str) -> String {
 }
 
 /// Get git root directory
-#[allow(dead_code)]
-fn get_git_root() -> Option<String> {
+pub fn get_git_root() -> Option<String> {
     let output = Command::new("git")
         .args(["rev-parse", "--show-toplevel"])
         .output()
@@ -117,6 +257,23 @@ fn get_git_root() -> Option<String> {
     }
 }
 
+/// Generate a private socket path for a Neovim instance spawned by this server.
+/// The name deliberately doesn't match the plugin pattern used by `get_targets`.
+pub fn generate_spawn_target(socket_path: &Path) -> String {
+    static SPAWN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let pid = std::process::id();
+    let seq = SPAWN_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    if cfg!(windows) {
+        format!("\\\\.\\pipe\\nvim-mcp-spawn-{pid}-{seq}")
+    } else {
+        socket_path
+            .join(format!("nvim-mcp-spawn.{pid}.{seq}.sock"))
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
 /// Find all existing nvim-mcp socket targets in the filesystem
 /// Returns a vector of socket paths that match the pattern generated by the Lua plugin
 pub fn find_get_all_targets(socket_path: &Path) -> Vec<String> {
@@ -1,7 +1,11 @@
 use std::time::Duration;
 
 use rmcp::{
-    model::{CallToolRequestParam, ReadResourceRequestParam},
+    model::{
+        ArgumentInfo, CallToolRequestParam, CompleteRequestParam, CompletionContext,
+        GetPromptRequestParam, ReadResourceRequestParam, Reference, SubscribeRequestParam,
+        UnsubscribeRequestParam,
+    },
     serde_json::{Map, Value},
     service::ServiceExt,
     transport::{ConfigureCommandExt, TokioChildProcess},
@@ -10,13 +14,14 @@ use tokio::{process::Command, time};
 use tracing::{error, info};
 use tracing_test::traced_test;
 
+use crate::server::NeovimMcpServer;
 use crate::test_utils::*;
 
 // Helper function to extract connection_id from connect response
 fn extract_connection_id(
     result: &rmcp::model::CallToolResult,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    if let Some(content) = result.content.as_ref().and_then(|c| c.first()) {
+    if let Some(content) = result.content.first() {
         // The content should be JSON
         let json_str = match &content.raw {
             rmcp::model::RawContent::Text(text_content) => &text_content.text,
@@ -116,6 +121,120 @@ async fn test_list_tools() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// POST a JSON-RPC message to the streamable HTTP endpoint, returning the status,
+/// the session ID header and the messages of the event stream
+async fn post_mcp(
+    address: std::net::SocketAddr,
+    session_id: Option<&str>,
+    message: Value,
+) -> Result<(hyper::StatusCode, Option<String>, Vec<Value>), Box<dyn std::error::Error>> {
+    use http_body_util::{BodyExt, Full};
+    use hyper::{body::Bytes, header};
+
+    let stream = tokio::net::TcpStream::connect(address).await?;
+    let (mut sender, connection) =
+        hyper::client::conn::http1::handshake(hyper_util::rt::TokioIo::new(stream)).await?;
+    tokio::spawn(connection);
+
+    let mut request = hyper::Request::post(format!("http://{address}/mcp"))
+        .header(header::HOST, address.to_string())
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::ACCEPT, "application/json, text/event-stream");
+    if let Some(session_id) = session_id {
+        request = request.header("Mcp-Session-Id", session_id);
+    }
+    let response = sender
+        .send_request(request.body(Full::new(Bytes::from(message.to_string())))?)
+        .await?;
+
+    let status = response.status();
+    let session_id = response
+        .headers()
+        .get("Mcp-Session-Id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = time::timeout(Duration::from_secs(10), response.into_body().collect())
+        .await??
+        .to_bytes();
+    let messages = String::from_utf8_lossy(&body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str(data.trim()).ok())
+        .collect();
+    Ok((status, session_id, messages))
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_streamable_http_list_tools() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    let router = NeovimMcpServer::new(std::env::temp_dir()).into_http_router();
+    let server = tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let (status, session_id, messages) = post_mcp(
+        address,
+        None,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": {"name": "test", "version": "0.0.0"}
+            }
+        }),
+    )
+    .await?;
+    assert!(status.is_success(), "initialize failed: {status}");
+    let session_id = session_id.expect("initialize should start a session");
+    assert_eq!(messages.len(), 1, "{messages:?}");
+    assert!(
+        messages[0]["result"]["capabilities"]["tools"].is_object(),
+        "{messages:?}"
+    );
+
+    let (status, _, _) = post_mcp(
+        address,
+        Some(&session_id),
+        serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+    )
+    .await?;
+    assert_eq!(status, hyper::StatusCode::ACCEPTED);
+
+    let (status, _, messages) = post_mcp(
+        address,
+        Some(&session_id),
+        serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+    )
+    .await?;
+    assert!(status.is_success(), "tools/list failed: {status}");
+    let tools = messages
+        .iter()
+        .find(|message| message["id"] == 2)
+        .and_then(|message| message["result"]["tools"].as_array())
+        .ok_or_else(|| format!("No tools/list response in {messages:?}"))?;
+    let names: Vec<&str> = tools
+        .iter()
+        .filter_map(|tool| tool["name"].as_str())
+        .collect();
+    assert!(names.contains(&"connect"), "{names:?}");
+    assert!(names.contains(&"lsp_references"), "{names:?}");
+
+    // Requests without a session are rejected
+    let (status, _, _) = post_mcp(
+        address,
+        None,
+        serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"}),
+    )
+    .await?;
+    assert!(status.is_client_error(), "{status}");
+
+    server.abort();
+    Ok(())
+}
+
 #[tokio::test]
 #[traced_test]
 async fn test_connect_nvim_tcp_tool() -> Result<(), Box<dyn std::error::Error>> {
@@ -150,10 +269,10 @@ async fn test_connect_nvim_tcp_tool() -> Result<(), Box<dyn std::error::Error>>
         .await?;
 
     info!("Connect result: {:#?}", result);
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
 
     // Verify the response contains success message
-    if let Some(content) = result.content.as_ref().and_then(|c| c.first()) {
+    if let Some(content) = result.content.first() {
         if let Some(text) = content.as_text() {
             assert!(text.text.contains("Connected to Neovim"));
             assert!(text.text.contains(&ipc_path));
@@ -256,10 +375,10 @@ async fn test_disconnect_nvim_tcp_tool() -> Result<(), Box<dyn std::error::Error
         .await?;
 
     info!("Disconnect result: {:#?}", result);
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
 
     // Verify the response contains success message
-    if let Some(content) = result.content.as_ref().and_then(|c| c.first()) {
+    if let Some(content) = result.content.first() {
         if let Some(text) = content.as_text() {
             assert!(text.text.contains("Disconnected from Neovim"));
             assert!(text.text.contains(&ipc_path));
@@ -293,6 +412,144 @@ async fn test_disconnect_nvim_tcp_tool() -> Result<(), Box<dyn std::error::Error
     Ok(())
 }
 
+#[tokio::test]
+#[traced_test]
+#[cfg(unix)]
+async fn test_spawn_nvim_tool() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Starting MCP client to test nvim-mcp server");
+
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp"]);
+            },
+        ))?)
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to server: {}", e);
+            e
+        })?;
+
+    let temp_dir = tempfile::TempDir::new()?;
+
+    // Spawning with a missing working directory should fail
+    let mut spawn_args = Map::new();
+    spawn_args.insert(
+        "cwd".to_string(),
+        Value::String("/nonexistent/nvim-mcp/cwd".to_string()),
+    );
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "spawn_nvim".into(),
+            arguments: Some(spawn_args),
+        })
+        .await;
+    assert!(result.is_err(), "spawn_nvim should fail for a missing cwd");
+
+    let mut spawn_args = Map::new();
+    spawn_args.insert(
+        "cwd".to_string(),
+        Value::String(temp_dir.path().to_string_lossy().to_string()),
+    );
+    spawn_args.insert("init_file".to_string(), Value::String("NONE".to_string()));
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "spawn_nvim".into(),
+            arguments: Some(spawn_args),
+        })
+        .await?;
+    info!("Spawn result: {:#?}", result);
+    let connection_id = extract_connection_id(&result)?;
+
+    // The spawned instance is usable like any other connection
+    let mut lua_args = Map::new();
+    lua_args.insert(
+        "connection_id".to_string(),
+        Value::String(connection_id.clone()),
+    );
+    lua_args.insert(
+        "code".to_string(),
+        Value::String("return vim.fn.getcwd()".to_string()),
+    );
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "exec_lua".into(),
+            arguments: Some(lua_args),
+        })
+        .await?;
+    let text = result
+        .content
+        .first()
+        .and_then(|c| c.as_text())
+        .map(|t| t.text.clone())
+        .unwrap_or_default();
+    let dir_name = temp_dir.path().file_name().unwrap().to_string_lossy();
+    assert!(text.contains(dir_name.as_ref()));
+
+    // Disconnect terminates the spawned instance
+    let mut disconnect_args = Map::new();
+    disconnect_args.insert("connection_id".to_string(), Value::String(connection_id));
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "disconnect".into(),
+            arguments: Some(disconnect_args),
+        })
+        .await;
+    assert!(result.is_ok(), "Failed to disconnect spawned instance");
+
+    service.cancel().await?;
+    info!("Spawn nvim tool test completed successfully");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[traced_test]
+#[cfg(unix)]
+async fn test_spawn_nvim_setup_failure_removes_socket() -> Result<(), Box<dyn std::error::Error>> {
+    let socket_dir = tempfile::TempDir::new()?;
+    let socket_path = socket_dir.path().to_string_lossy().to_string();
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp", "--", "--socket-path"])
+                    .arg(&socket_path);
+            },
+        ))?)
+        .await?;
+
+    // An init file breaking the diagnostics autocmd fails the setup after connecting
+    let temp_dir = tempfile::TempDir::new()?;
+    let init_file = temp_dir.path().join("init.lua");
+    std::fs::write(
+        &init_file,
+        "vim.api.nvim_create_augroup = function() error('broken') end\n",
+    )?;
+
+    let mut spawn_args = Map::new();
+    spawn_args.insert(
+        "cwd".to_string(),
+        Value::String(temp_dir.path().to_string_lossy().to_string()),
+    );
+    spawn_args.insert(
+        "init_file".to_string(),
+        Value::String(init_file.to_string_lossy().to_string()),
+    );
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "spawn_nvim".into(),
+            arguments: Some(spawn_args),
+        })
+        .await;
+    assert!(result.is_err(), "spawn_nvim should fail: {result:?}");
+
+    let leftover: Vec<_> = std::fs::read_dir(socket_dir.path())?.collect();
+    assert!(leftover.is_empty(), "Socket left behind: {leftover:?}");
+
+    service.cancel().await?;
+    Ok(())
+}
+
 #[tokio::test]
 #[traced_test]
 async fn test_list_buffers_tool() -> Result<(), Box<dyn std::error::Error>> {
@@ -358,10 +615,10 @@ async fn test_list_buffers_tool() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
 
     info!("List buffers result: {:#?}", result);
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
 
     // Verify the response contains buffer information
-    if let Some(content) = result.content.as_ref().and_then(|c| c.first()) {
+    if let Some(content) = result.content.first() {
         if let Some(text) = content.as_text() {
             // The response should be JSON with buffer info
             assert!(text.text.contains("\"id\""));
@@ -416,7 +673,7 @@ async fn test_complete_workflow() -> Result<(), Box<dyn std::error::Error>> {
         })
         .await?;
 
-    assert!(!connect_result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!connect_result.content.is_empty());
     let connection_id = extract_connection_id(&connect_result)?;
     info!(
         "✓ Connected successfully with connection_id: {}",
@@ -438,7 +695,7 @@ async fn test_complete_workflow() -> Result<(), Box<dyn std::error::Error>> {
         })
         .await?;
 
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
     info!("✓ Listed buffers successfully");
 
     // Step 3: Get LSP clients
@@ -456,7 +713,7 @@ async fn test_complete_workflow() -> Result<(), Box<dyn std::error::Error>> {
         })
         .await?;
 
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
     info!("✓ Got LSP clients successfully");
 
     // Step 4: Disconnect
@@ -474,7 +731,7 @@ async fn test_complete_workflow() -> Result<(), Box<dyn std::error::Error>> {
         })
         .await?;
 
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
     info!("✓ Disconnected successfully");
 
     // Step 5: Verify we can't list buffers after disconnect
@@ -564,6 +821,454 @@ async fn test_error_handling() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+#[traced_test]
+async fn test_read_only_mode() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Starting MCP client to test nvim-mcp server in read-only mode");
+
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp", "--", "--read-only"]);
+            },
+        ))?)
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to server: {}", e);
+            e
+        })?;
+
+    // Mutating tools are not exposed
+    let tools = service.list_tools(Default::default()).await?;
+    let tool_names: Vec<&str> = tools.tools.iter().map(|t| t.name.as_ref()).collect();
+    for name in [
+        "exec_command",
+        "exec_lua",
+        "lsp_apply_edit",
+        "lsp_rename",
+        "navigate_to_file",
+        "set_quickfix_list",
+    ] {
+        assert!(!tool_names.contains(&name), "{name} should be hidden");
+    }
+    assert!(tool_names.contains(&"lsp_formatting"));
+    assert!(tool_names.contains(&"lsp_references"));
+
+    // Apply mode is rejected before reaching Neovim
+    let mut args = Map::new();
+    args.insert(
+        "connection_id".to_string(),
+        Value::String("missing".to_string()),
+    );
+    args.insert("document".to_string(), serde_json::json!({"buffer_id": 0}));
+    args.insert(
+        "options".to_string(),
+        serde_json::json!({"tabSize": 4, "insertSpaces": true}),
+    );
+    args.insert("apply_edits".to_string(), Value::Bool(true));
+
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "lsp_formatting".into(),
+            arguments: Some(args.clone()),
+        })
+        .await;
+    let err = result.expect_err("Apply mode should be rejected in read-only mode");
+    assert!(
+        err.to_string().contains("read-only"),
+        "unexpected error: {err}"
+    );
+
+    // Inspect mode passes the policy and fails on the unknown connection instead
+    args.insert("apply_edits".to_string(), Value::Bool(false));
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "lsp_formatting".into(),
+            arguments: Some(args),
+        })
+        .await;
+    let err = result.expect_err("Unknown connection should be rejected");
+    assert!(
+        !err.to_string().contains("read-only"),
+        "unexpected error: {err}"
+    );
+
+    service.cancel().await?;
+    info!("Read-only mode test completed successfully");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_audit_log() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Starting MCP client to test nvim-mcp server audit log");
+
+    let temp_dir = tempfile::TempDir::new()?;
+    let audit_path = temp_dir.path().join("audit.jsonl");
+    let audit_arg = audit_path.to_string_lossy().to_string();
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp", "--", "--audit-log", &audit_arg]);
+            },
+        ))?)
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to server: {}", e);
+            e
+        })?;
+
+    // Failed mutating calls are recorded too
+    let mut args = Map::new();
+    args.insert(
+        "connection_id".to_string(),
+        Value::String("missing".to_string()),
+    );
+    args.insert(
+        "code".to_string(),
+        Value::String("vim.cmd('write')".to_string()),
+    );
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "exec_lua".into(),
+            arguments: Some(args),
+        })
+        .await;
+    assert!(result.is_err(), "Unknown connection should be rejected");
+
+    // Read-only tools are not recorded
+    let mut args = Map::new();
+    args.insert(
+        "connection_id".to_string(),
+        Value::String("missing".to_string()),
+    );
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "lsp_clients".into(),
+            arguments: Some(args),
+        })
+        .await;
+    assert!(result.is_err(), "Unknown connection should be rejected");
+
+    let result = service
+        .read_resource(ReadResourceRequestParam {
+            uri: "nvim-audit://".to_string(),
+        })
+        .await?;
+    let text = match &result.contents[0] {
+        rmcp::model::ResourceContents::TextResourceContents { text, .. } => text.clone(),
+        _ => panic!("Expected text content"),
+    };
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&text)?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["tool"], "exec_lua");
+    assert_eq!(entries[0]["connection_id"], "missing");
+    assert_eq!(entries[0]["arguments"]["code"], "vim.cmd('write')");
+    assert_eq!(entries[0]["success"], false);
+
+    service.cancel().await?;
+
+    let lines: Vec<serde_json::Value> = std::fs::read_to_string(&audit_path)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(lines, entries);
+    info!("Audit log test completed successfully");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_prompts() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Starting MCP client to test nvim-mcp server prompts");
+
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp"]);
+            },
+        ))?)
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to server: {}", e);
+            e
+        })?;
+
+    let server_info = service.peer_info().expect("server info");
+    assert!(server_info.capabilities.prompts.is_some());
+
+    let prompts = service.list_all_prompts().await?;
+    let names: Vec<&str> = prompts.iter().map(|p| p.name.as_str()).collect();
+    assert!(names.contains(&"fix_diagnostics"));
+    assert!(names.contains(&"explain_symbol_at_cursor"));
+    assert!(names.contains(&"review_unsaved_changes"));
+    assert!(names.contains(&"refactor_with_rename"));
+
+    // Prompts are filled from a connection, which must exist
+    let mut args = Map::new();
+    args.insert(
+        "connection_id".to_string(),
+        Value::String("missing".to_string()),
+    );
+    let result = service
+        .get_prompt(GetPromptRequestParam {
+            name: "review_unsaved_changes".to_string(),
+            arguments: Some(args),
+        })
+        .await;
+    assert!(result.is_err(), "Unknown connection should be rejected");
+
+    let result = service
+        .get_prompt(GetPromptRequestParam {
+            name: "unknown_prompt".to_string(),
+            arguments: None,
+        })
+        .await;
+    assert!(result.is_err(), "Unknown prompt should be rejected");
+
+    // Without connections there is nothing to complete
+    let completion = service
+        .complete(CompleteRequestParam {
+            r#ref: Reference::for_prompt("fix_diagnostics"),
+            argument: ArgumentInfo {
+                name: "connection_id".to_string(),
+                value: String::new(),
+            },
+            context: None,
+        })
+        .await?;
+    assert!(completion.completion.values.is_empty());
+
+    service.cancel().await?;
+    info!("Prompts test completed successfully");
+
+    Ok(())
+}
+
+/// Start the server with path confinement to a temporary project and spawn a
+/// Neovim instance working in it
+async fn serve_confined() -> Result<
+    (
+        rmcp::service::RunningService<rmcp::RoleClient, ()>,
+        tempfile::TempDir,
+        String,
+    ),
+    Box<dyn std::error::Error>,
+> {
+    let temp_dir = tempfile::TempDir::new()?;
+    let project = temp_dir.path().canonicalize()?.join("project");
+    std::fs::create_dir(&project)?;
+    std::fs::write(project.join("main.lua"), "print(1)\n")?;
+    std::fs::write(temp_dir.path().join("secret.txt"), "secret\n")?;
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[safety.paths]\nconfine = true\nallowed_roots = [{:?}]\n",
+            project.display().to_string()
+        ),
+    )?;
+    let config_arg = config_path.to_string_lossy().to_string();
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp", "--", "--config", &config_arg]);
+            },
+        ))?)
+        .await?;
+
+    let mut spawn_args = Map::new();
+    spawn_args.insert(
+        "cwd".to_string(),
+        Value::String(project.to_string_lossy().to_string()),
+    );
+    spawn_args.insert("init_file".to_string(), Value::String("NONE".to_string()));
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "spawn_nvim".into(),
+            arguments: Some(spawn_args),
+        })
+        .await?;
+    let connection_id = extract_connection_id(&result)?;
+    Ok((service, temp_dir, connection_id))
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_prompt_document_confined() -> Result<(), Box<dyn std::error::Error>> {
+    let (service, temp_dir, connection_id) = serve_confined().await?;
+
+    let prompt_args = |document: String| {
+        let mut args = Map::new();
+        args.insert(
+            "connection_id".to_string(),
+            Value::String(connection_id.clone()),
+        );
+        args.insert("document".to_string(), Value::String(document));
+        GetPromptRequestParam {
+            name: "fix_diagnostics".to_string(),
+            arguments: Some(args),
+        }
+    };
+
+    let result = service
+        .get_prompt(prompt_args("main.lua".to_string()))
+        .await?;
+    assert!(!result.messages.is_empty());
+
+    // Files outside of the allowed roots are not sent to the client
+    let secret = temp_dir.path().join("secret.txt");
+    for document in [
+        secret.to_string_lossy().to_string(),
+        "../secret.txt".to_string(),
+    ] {
+        let result = service.get_prompt(prompt_args(document.clone())).await;
+        assert!(result.is_err(), "{document} should be rejected");
+    }
+
+    service.cancel().await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_symbols_resource_confined() -> Result<(), Box<dyn std::error::Error>> {
+    let (service, temp_dir, connection_id) = serve_confined().await?;
+
+    // Both absolute and relative paths escaping the root are rejected
+    let secret = temp_dir.path().join("secret.txt");
+    for path in [
+        secret.to_string_lossy().to_string(),
+        "../secret.txt".to_string(),
+    ] {
+        let uri = format!("nvim-symbols://{connection_id}/{path}");
+        let result = service
+            .read_resource(ReadResourceRequestParam { uri: uri.clone() })
+            .await;
+        assert!(result.is_err(), "{uri} should be rejected");
+    }
+
+    service.cancel().await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_quickfix_items_confined() -> Result<(), Box<dyn std::error::Error>> {
+    let (service, temp_dir, connection_id) = serve_confined().await?;
+
+    let quickfix_args = |item: Value| {
+        let mut args = Map::new();
+        args.insert(
+            "connection_id".to_string(),
+            Value::String(connection_id.clone()),
+        );
+        args.insert("title".to_string(), Value::String("confined".to_string()));
+        args.insert("items".to_string(), Value::Array(vec![item]));
+        CallToolRequestParam {
+            name: "set_quickfix_list".into(),
+            arguments: Some(args),
+        }
+    };
+    let range = serde_json::json!({
+        "start": { "line": 0, "character": 0 },
+        "end": { "line": 0, "character": 1 }
+    });
+
+    let result = service
+        .call_tool(quickfix_args(serde_json::json!({
+            "document": { "project_relative_path": "main.lua" },
+            "range": range,
+        })))
+        .await?;
+    assert_ne!(result.is_error, Some(true));
+
+    // Neither documents nor location URIs may point outside of the allowed roots
+    let secret = temp_dir.path().join("secret.txt");
+    for item in [
+        serde_json::json!({
+            "document": { "absolute_path": secret.to_string_lossy() },
+            "range": range,
+        }),
+        serde_json::json!({
+            "document": { "project_relative_path": "../secret.txt" },
+            "range": range,
+        }),
+        serde_json::json!({
+            "uri": format!("file://{}", secret.display()),
+            "range": range,
+        }),
+    ] {
+        let result = service.call_tool(quickfix_args(item.clone())).await;
+        assert!(result.is_err(), "{item} should be rejected");
+    }
+
+    service.cancel().await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[traced_test]
+async fn test_argument_completion() -> Result<(), Box<dyn std::error::Error>> {
+    info!("Starting MCP client to test nvim-mcp server argument completion");
+
+    let service = ()
+        .serve(TokioChildProcess::new(Command::new("cargo").configure(
+            |cmd| {
+                cmd.args(["run", "--bin", "nvim-mcp"]);
+            },
+        ))?)
+        .await
+        .map_err(|e| {
+            error!("Failed to connect to server: {}", e);
+            e
+        })?;
+
+    // Start a test Neovim instance
+    let ipc_path = generate_random_ipc_path();
+    let _guard = setup_test_neovim_instance(&ipc_path).await?;
+
+    let mut arguments = Map::new();
+    arguments.insert("target".to_string(), Value::String(ipc_path.clone()));
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "connect".into(),
+            arguments: Some(arguments),
+        })
+        .await?;
+    let connection_id = extract_connection_id(&result)?;
+
+    let complete = |name: &str, value: &str| CompleteRequestParam {
+        r#ref: Reference::for_prompt("fix_diagnostics"),
+        argument: ArgumentInfo {
+            name: name.to_string(),
+            value: value.to_string(),
+        },
+        context: Some(CompletionContext::with_arguments(
+            [("connection_id".to_string(), connection_id.clone())].into(),
+        )),
+    };
+
+    let completion = service
+        .complete(complete("connection_id", &connection_id[..2]))
+        .await?;
+    assert_eq!(completion.completion.values, vec![connection_id.clone()]);
+
+    // The test instance has the empty initial buffer open
+    let completion = service.complete(complete("document", "")).await?;
+    assert!(
+        completion.completion.values.contains(&"1".to_string()),
+        "{completion:?}"
+    );
+
+    service.cancel().await?;
+    info!("Argument completion test completed successfully");
+
+    Ok(())
+}
+
 #[tokio::test]
 #[traced_test]
 async fn test_exec_lua_tool() -> Result<(), Box<dyn std::error::Error>> {
@@ -634,10 +1339,10 @@ async fn test_exec_lua_tool() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
 
     info!("Exec Lua result: {:#?}", result);
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
 
     // Verify the response contains Lua result
-    if let Some(content) = result.content.as_ref().and_then(|c| c.first()) {
+    if let Some(content) = result.content.first() {
         if let Some(text) = content.as_text() {
             assert!(text.text.contains("42"));
         } else {
@@ -665,7 +1370,7 @@ async fn test_exec_lua_tool() -> Result<(), Box<dyn std::error::Error>> {
         })
         .await?;
 
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
 
     // Test error handling for invalid Lua
     let mut invalid_lua_args = Map::new();
@@ -789,10 +1494,10 @@ async fn test_lsp_clients_tool() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
 
     info!("LSP clients result: {:#?}", result);
-    assert!(!result.content.as_ref().is_none_or(|c| c.is_empty()));
+    assert!(!result.content.is_empty());
 
     // Verify the response contains content
-    if let Some(_content) = result.content.as_ref().and_then(|c| c.first()) {
+    if let Some(_content) = result.content.first() {
         // Content received successfully - the JSON parsing is handled by the MCP framework
         info!("LSP clients content received successfully");
     } else {
@@ -846,12 +1551,106 @@ async fn test_list_diagnostic_resources() -> Result<(), Box<dyn std::error::Erro
         assert_eq!(resource.raw.mime_type, Some("application/json".to_string()));
     }
 
+    // Connection-scoped resources are discoverable through templates
+    let templates = service.list_all_resource_templates().await?;
+    assert!(
+        templates
+            .iter()
+            .any(|t| t.raw.uri_template == "nvim-buffer://{connection_id}/{buffer_id}")
+    );
+    assert!(
+        templates
+            .iter()
+            .any(|t| t.raw.uri_template == "nvim-diagnostics://{connection_id}/buffer/{buffer_id}")
+    );
+
+    // Only buffers can be subscribed to
+    let result = service
+        .subscribe(SubscribeRequestParam {
+            uri: "nvim-connections://".to_string(),
+        })
+        .await;
+    assert!(result.is_err(), "Subscribing to connections should fail");
+
     service.cancel().await?;
     info!("List diagnostic resources test completed successfully");
 
     Ok(())
 }
 
+#[tokio::test]
+#[traced_test]
+async fn test_resource_subscriptions_per_peer() -> Result<(), Box<dyn std::error::Error>> {
+    // Two MCP sessions served by the same server, as with the HTTP transport
+    let server = NeovimMcpServer::new(std::env::temp_dir());
+    let mut clients = Vec::new();
+    for _ in 0..2 {
+        let (server_transport, client_transport) = tokio::io::duplex(4096);
+        let server = server.new_session();
+        tokio::spawn(async move {
+            if let Ok(running) = server.serve(server_transport).await {
+                let _ = running.waiting().await;
+            }
+        });
+        clients.push(().serve(client_transport).await?);
+    }
+
+    let ipc_path = generate_random_ipc_path();
+    let _guard = setup_test_neovim_instance(&ipc_path).await?;
+
+    let mut connect_args = Map::new();
+    connect_args.insert("target".to_string(), Value::String(ipc_path.clone()));
+    let connect_result = clients[0]
+        .call_tool(CallToolRequestParam {
+            name: "connect".into(),
+            arguments: Some(connect_args),
+        })
+        .await?;
+    let connection_id = extract_connection_id(&connect_result)?;
+
+    let uri = format!("nvim-buffer://{connection_id}/1");
+    let peer_count = || {
+        server
+            .resource_subscriptions
+            .get(&uri)
+            .map(|subscription| subscription.peer_count())
+    };
+    for client in &clients {
+        // Subscribing twice from the same session counts once
+        for _ in 0..2 {
+            client
+                .subscribe(SubscribeRequestParam { uri: uri.clone() })
+                .await?;
+        }
+    }
+    assert_eq!(peer_count(), Some(2));
+
+    // Unsubscribing only removes the calling session
+    clients[0]
+        .unsubscribe(UnsubscribeRequestParam { uri: uri.clone() })
+        .await?;
+    assert_eq!(peer_count(), Some(1));
+
+    // Disconnecting drops the subscriptions of the connection
+    let mut disconnect_args = Map::new();
+    disconnect_args.insert(
+        "connection_id".to_string(),
+        Value::String(connection_id.clone()),
+    );
+    clients[1]
+        .call_tool(CallToolRequestParam {
+            name: "disconnect".into(),
+            arguments: Some(disconnect_args),
+        })
+        .await?;
+    assert_eq!(peer_count(), None);
+
+    for client in clients {
+        client.cancel().await?;
+    }
+    Ok(())
+}
+
 #[tokio::test]
 #[traced_test]
 async fn test_read_workspace_diagnostics() -> Result<(), Box<dyn std::error::Error>> {
@@ -933,6 +1732,34 @@ async fn test_read_workspace_diagnostics() -> Result<(), Box<dyn std::error::Err
 
     assert!(result.is_err(), "Should fail for invalid buffer ID");
 
+    // Test reading buffer text through the buffer template
+    let result = service
+        .read_resource(ReadResourceRequestParam {
+            uri: format!("nvim-buffer://{connection_id}/1"),
+        })
+        .await?;
+    match &result.contents[0] {
+        rmcp::model::ResourceContents::TextResourceContents { text, .. } => {
+            assert_eq!(text, "", "The initial buffer is empty")
+        }
+        _ => panic!("Expected text content"),
+    }
+
+    // Test reading the editor state
+    let result = service
+        .read_resource(ReadResourceRequestParam {
+            uri: format!("nvim-state://{connection_id}"),
+        })
+        .await?;
+    match &result.contents[0] {
+        rmcp::model::ResourceContents::TextResourceContents { text, .. } => {
+            let state: serde_json::Value = serde_json::from_str(text)?;
+            assert_eq!(state["mode"], "n");
+            assert_eq!(state["layout"]["type"], "leaf");
+        }
+        _ => panic!("Expected text content"),
+    }
+
     // Cleanup happens automatically via guard
     service.cancel().await?;
     info!("Read workspace diagnostics test completed successfully");
@@ -1091,7 +1918,7 @@ async fn test_lsp_organize_imports_with_lsp() -> Result<(), Box<dyn std::error::
     let r = result.unwrap();
     info!("Organize imports with LSP succeeded: {:?}", r);
     // The result should contain either success message or actions
-    assert!(r.content.is_some());
+    assert!(!r.content.is_empty());
     assert!(
         serde_json::to_string(&r)
             .unwrap()
@@ -1179,7 +2006,7 @@ async fn test_lsp_organize_imports_inspect_mode() -> Result<(), Box<dyn std::err
     let r = result.unwrap();
     info!("Organize imports inspection succeeded: {:?}", r);
     // The result should contain either code actions or a message about no actions
-    assert!(r.content.is_some());
+    assert!(!r.content.is_empty());
     assert!(
         serde_json::to_string(&r)
             .unwrap()
@@ -1,8 +1,30 @@
+pub(crate) mod audit;
+pub(crate) mod completion;
 pub(crate) mod core;
-mod resources;
+pub(crate) mod policy;
+pub(crate) mod prompts;
+pub(crate) mod resources;
 pub(crate) mod tools;
 
+#[cfg(test)]
+mod audit_test;
+
+#[cfg(test)]
+mod completion_test;
+
 #[cfg(test)]
 mod integration_tests;
 
+#[cfg(test)]
+mod policy_test;
+
+#[cfg(test)]
+mod prompts_test;
+
+#[cfg(test)]
+mod resources_test;
+
+#[cfg(test)]
+mod tools_test;
+
 pub use core::NeovimMcpServer;
@@ -0,0 +1,768 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use regex::Regex;
+use rmcp::{
+    ErrorData as McpError, Peer, RoleServer,
+    model::{CallToolRequestParam, CreateElicitationRequestParam, ElicitationAction},
+    service::ServiceError,
+};
+use serde_json::json;
+use tracing::debug;
+
+use super::audit::parse_argument;
+use super::core::{NeovimMcpServer, get_git_root};
+use crate::config::{ConfirmConfig, ConfirmMode, ExecLuaPolicy};
+use crate::neovim::{
+    ConfirmPreview, DocumentIdentifier, NO_UI_REASON, NeovimClientTrait, NeovimError,
+    QuickfixEntry, WorkspaceEdit,
+};
+
+/// How a tool call affects the editor state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolAccess {
+    /// Only inspects the editor
+    ReadOnly,
+    /// Always modifies the editor or files on disk
+    Mutating,
+    /// Modifies the editor when the boolean `argument` is true
+    MutatingWhen {
+        argument: &'static str,
+        default: bool,
+    },
+}
+
+/// Tools that always modify the editor state or files on disk
+pub const MUTATING_TOOLS: &[&str] = &[
+    "exec_command",
+    "exec_lua",
+    "lsp_apply_edit",
+    "lsp_rename",
+    "navigate_to_file",
+    "set_quickfix_list",
+];
+
+/// Tools that can ask the user to approve their change, see `[safety.confirm]`
+pub const CONFIRMABLE_TOOLS: &[&str] = &[
+    "exec_command",
+    "exec_lua",
+    "lsp_apply_edit",
+    "lsp_rename",
+    "lsp_organize_imports",
+];
+
+/// Tools that modify the editor state only in apply mode
+const APPLY_MODE_TOOLS: &[(&str, bool)] = &[
+    ("lsp_formatting", false),
+    ("lsp_range_formatting", false),
+    ("lsp_organize_imports", true),
+];
+
+/// Classify a tool by its effect on the editor state
+pub fn tool_access(name: &str) -> ToolAccess {
+    if MUTATING_TOOLS.contains(&name) {
+        return ToolAccess::Mutating;
+    }
+    match APPLY_MODE_TOOLS.iter().find(|(tool, _)| *tool == name) {
+        Some((_, default)) => ToolAccess::MutatingWhen {
+            argument: "apply_edits",
+            default: *default,
+        },
+        None => ToolAccess::ReadOnly,
+    }
+}
+
+/// Whether a tool call modifies the editor state, given its arguments
+pub fn is_mutating_call(request: &CallToolRequestParam) -> bool {
+    match tool_access(&request.name) {
+        ToolAccess::ReadOnly => false,
+        ToolAccess::Mutating => true,
+        ToolAccess::MutatingWhen { argument, default } => request
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get(argument))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(default),
+    }
+}
+
+impl NeovimMcpServer {
+    /// Mark a connection as read-only when requested or configured. The mark only
+    /// ever tightens: connection IDs are deterministic, so it is kept across
+    /// reconnects and can't be lifted by connecting again without `read_only`.
+    pub fn mark_connection_read_only(&self, connection_id: &str, read_only: bool) {
+        if read_only || self.read_only {
+            self.read_only_connections.insert(connection_id.to_string());
+        }
+    }
+
+    /// Ask the user to approve a change made by `tool` when confirmation is configured for it
+    pub async fn confirm_change(
+        &self,
+        tool: &str,
+        client: &(dyn NeovimClientTrait + Send),
+        peer: &Peer<RoleServer>,
+        title: &str,
+        preview: ConfirmPreview,
+    ) -> Result<(), McpError> {
+        let mode = self.confirm.mode(tool);
+        if mode == ConfirmMode::Off || !needs_confirmation(&self.confirm, &preview) {
+            return Ok(());
+        }
+
+        let timeout_ms = self.confirm.timeout_ms();
+        if mode == ConfirmMode::Client {
+            if peer.supports_elicitation() {
+                return elicit_confirmation(peer, title, &preview, timeout_ms).await;
+            }
+            debug!("MCP client doesn't support elicitation, asking in Neovim instead");
+        }
+        match client.confirm(title, preview.clone(), timeout_ms).await {
+            // A headless Neovim can't ask, the MCP client user may still answer
+            Err(NeovimError::ConfirmationDenied { reason, .. })
+                if reason == NO_UI_REASON && peer.supports_elicitation() =>
+            {
+                debug!("Neovim has no UI, asking the MCP client instead");
+                elicit_confirmation(peer, title, &preview, timeout_ms).await
+            }
+            result => Ok(result?),
+        }
+    }
+
+    /// Reject documents and workspace edits in the tool arguments that are outside
+    /// of the allowed roots
+    pub async fn check_argument_paths(
+        &self,
+        request: &CallToolRequestParam,
+    ) -> Result<(), McpError> {
+        let Some(arguments) = &request.arguments else {
+            return Ok(());
+        };
+        if !self.path_policy.is_confined() {
+            return Ok(());
+        }
+        // Unknown connections are reported by the tool itself
+        let Some(client) = arguments
+            .get("connection_id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| self.nvim_clients.get(id))
+        else {
+            return Ok(());
+        };
+
+        if let Some(document) = arguments
+            .get("document")
+            .and_then(parse_argument::<DocumentIdentifier>)
+        {
+            self.check_document_confined(client.value().as_ref(), &document)
+                .await?;
+        }
+        if let Some(edit) = arguments
+            .get("workspace_edit")
+            .and_then(parse_argument::<WorkspaceEdit>)
+        {
+            self.check_workspace_edit_confined(client.value().as_ref(), &edit)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Reject a document outside of the allowed roots. Buffers are not checked,
+    /// they have been opened by the user.
+    pub async fn check_document_confined(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        document: &DocumentIdentifier,
+    ) -> Result<(), McpError> {
+        if !self.path_policy.is_confined() {
+            return Ok(());
+        }
+        let path = match document {
+            DocumentIdentifier::BufferId(_) => return Ok(()),
+            DocumentIdentifier::ProjectRelativePath(path) => {
+                client.get_project_root().await?.cwd.join(path)
+            }
+            DocumentIdentifier::AbsolutePath(path) => path.clone(),
+        };
+        let roots = self.allowed_roots(client).await?;
+        Ok(check_path_confined(&path, &roots)?)
+    }
+
+    /// Reject a workspace edit touching files outside of the allowed roots
+    pub async fn check_workspace_edit_confined(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        edit: &WorkspaceEdit,
+    ) -> Result<(), McpError> {
+        if !self.path_policy.is_confined() {
+            return Ok(());
+        }
+        let roots = self.allowed_roots(client).await?;
+        for uri in edit.affected_uris() {
+            // Non-file URIs don't refer to files on disk
+            if let Some(path) = file_uri_to_path(&uri) {
+                check_path_confined(&path, &roots)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject quickfix items pointing at files outside of the allowed roots.
+    /// Diagnostics refer to buffers and are not checked.
+    pub async fn check_quickfix_items_confined(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        items: &[QuickfixEntry],
+    ) -> Result<(), McpError> {
+        if !self.path_policy.is_confined() {
+            return Ok(());
+        }
+        for item in items {
+            match item {
+                QuickfixEntry::Position { document, .. } => {
+                    self.check_document_confined(client, document).await?
+                }
+                QuickfixEntry::Location(location) => {
+                    // Non-file URIs don't refer to files on disk
+                    if let Some(path) = file_uri_to_path(&location.uri) {
+                        let roots = self.allowed_roots(client).await?;
+                        check_path_confined(&path, &roots)?;
+                    }
+                }
+                QuickfixEntry::Diagnostic(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the arguments of `spawn_nvim`. An init file runs arbitrary code, so
+    /// only `NONE` is accepted when the server is read-only or checks `exec_lua`.
+    /// The working directory and init file must be inside the allowed roots.
+    pub fn check_spawn_arguments(
+        &self,
+        cwd: &Path,
+        init_file: Option<&Path>,
+    ) -> Result<(), McpError> {
+        let init_file = init_file.filter(|file| *file != Path::new("NONE"));
+        if let Some(init_file) = init_file {
+            if self.read_only {
+                return Err(McpError::invalid_request(
+                    format!(
+                        "init_file {} is not allowed: the server is in read-only mode",
+                        init_file.display()
+                    ),
+                    Some(json!({ "tool": "spawn_nvim", "policy": "read_only" })),
+                ));
+            }
+            if self.exec_lua_policy.is_enabled() {
+                return Err(NeovimError::PolicyViolation {
+                    rule: "init_file".to_string(),
+                    detail: "only `NONE` is allowed while the exec_lua policy is enabled"
+                        .to_string(),
+                }
+                .into());
+            }
+        }
+
+        if !self.path_policy.is_confined() {
+            return Ok(());
+        }
+        // There is no Neovim instance yet, the project root is the server's
+        let roots = match &self.path_policy.allowed_roots {
+            Some(roots) => roots.clone(),
+            None => get_git_root()
+                .map(PathBuf::from)
+                .or_else(|| std::env::current_dir().ok())
+                .into_iter()
+                .collect(),
+        };
+        check_path_confined(cwd, &roots)?;
+        if let Some(init_file) = init_file {
+            check_path_confined(&cwd.join(init_file), &roots)?;
+        }
+        Ok(())
+    }
+
+    /// Configured roots, or the project root of the Neovim instance
+    async fn allowed_roots(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+    ) -> Result<Vec<PathBuf>, McpError> {
+        match &self.path_policy.allowed_roots {
+            Some(roots) => Ok(roots.clone()),
+            None => Ok(vec![client.get_project_root().await?.root]),
+        }
+    }
+
+    /// Reject tool calls that would modify a read-only server or connection
+    pub fn check_tool_policy(&self, request: &CallToolRequestParam) -> Result<(), McpError> {
+        if !is_mutating_call(request) {
+            return Ok(());
+        }
+        let arguments = request.arguments.as_ref();
+
+        if self.read_only {
+            return Err(McpError::invalid_request(
+                format!(
+                    "Tool `{}` is not allowed: the server is in read-only mode",
+                    request.name
+                ),
+                Some(json!({ "tool": request.name, "policy": "read_only" })),
+            ));
+        }
+
+        let connection_id = arguments
+            .and_then(|args| args.get("connection_id"))
+            .and_then(|value| value.as_str());
+        if let Some(connection_id) = connection_id
+            && self.read_only_connections.contains(connection_id)
+        {
+            return Err(McpError::invalid_request(
+                format!(
+                    "Tool `{}` is not allowed: connection {connection_id} is read-only",
+                    request.name
+                ),
+                Some(json!({
+                    "tool": request.name,
+                    "connection_id": connection_id,
+                    "policy": "read_only",
+                })),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Lua code patterns that may modify buffers, editor state or files
+const LUA_WRITE_PATTERNS: &[&str] = &[
+    "nvim_buf_set_lines",
+    "nvim_buf_set_text",
+    "nvim_buf_delete",
+    "nvim_put",
+    "nvim_command",
+    "nvim_exec",
+    "nvim_cmd",
+    "vim.cmd",
+    "vim.lsp.util.apply",
+    "vim.fn.writefile",
+    "vim.fn.delete",
+    "vim.fn.rename",
+    "vim.fn.mkdir",
+    "io.open",
+    "os.remove",
+    "os.rename",
+    "fs_write",
+    "fs_unlink",
+    "fs_rename",
+];
+
+/// Whether Lua code may modify buffers or files, erring on the side of caution
+pub fn lua_may_write(code: &str) -> bool {
+    let compact: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    LUA_WRITE_PATTERNS
+        .iter()
+        .any(|pattern| compact.contains(pattern))
+}
+
+/// Whether a change is risky enough to ask the user according to `[safety.confirm]`
+pub fn needs_confirmation(config: &ConfirmConfig, preview: &ConfirmPreview) -> bool {
+    match preview {
+        ConfirmPreview::WorkspaceEdit { workspace_edit, .. } => {
+            workspace_edit.affected_uris().len() > config.min_files()
+        }
+        ConfirmPreview::Code { filetype, code } => {
+            !config.is_lua_writes_only() || filetype != "lua" || lua_may_write(code)
+        }
+    }
+}
+
+/// Ask the MCP client user to approve a change through `elicitation/create`
+async fn elicit_confirmation(
+    peer: &Peer<RoleServer>,
+    title: &str,
+    preview: &ConfirmPreview,
+    timeout_ms: u64,
+) -> Result<(), McpError> {
+    let denied = |reason: &str| {
+        McpError::from(NeovimError::ConfirmationDenied {
+            action: title.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+    let request = CreateElicitationRequestParam {
+        message: format!("{title}?\n\n{}", preview.describe()),
+        // Nothing to fill in, accepting the request approves the change
+        requested_schema: json!({ "type": "object", "properties": {} })
+            .as_object()
+            .cloned()
+            .unwrap_or_default(),
+    };
+    match peer
+        .create_elicitation_with_timeout(request, Some(Duration::from_millis(timeout_ms)))
+        .await
+    {
+        Ok(result) => match result.action {
+            ElicitationAction::Accept => Ok(()),
+            ElicitationAction::Decline => Err(denied("declined by the MCP client user")),
+            ElicitationAction::Cancel => Err(denied("cancelled by the MCP client user")),
+        },
+        Err(ServiceError::Timeout { .. }) => {
+            Err(denied(&format!("no answer within {timeout_ms}ms")))
+        }
+        Err(e) => Err(McpError::internal_error(
+            format!("Failed to ask the MCP client for confirmation: {e}"),
+            None,
+        )),
+    }
+}
+
+/// Resolve `.`, `..` and symlinks in a path that doesn't need to exist
+pub fn resolve_path(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => {
+                resolved.push(component);
+                // Resolve symlinks as soon as they appear, so a following `..`
+                // leaves the link target rather than the link
+                if let Ok(real) = resolved.canonicalize() {
+                    resolved = real;
+                }
+            }
+        }
+    }
+    resolved
+}
+
+/// Reject a path that doesn't resolve to a location inside one of `roots`
+pub fn check_path_confined(path: &Path, roots: &[PathBuf]) -> Result<(), NeovimError> {
+    let resolved = resolve_path(path);
+    if roots
+        .iter()
+        .any(|root| resolved.starts_with(resolve_path(root)))
+    {
+        return Ok(());
+    }
+    Err(NeovimError::PathOutsideRoots {
+        path: path.display().to_string(),
+        roots: roots.iter().map(|r| r.display().to_string()).collect(),
+    })
+}
+
+/// Decode the `%XX` escapes of a URI component, `None` when the result isn't UTF-8
+pub fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Convert a `file://` URI to a path, `None` for other schemes
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = percent_decode(uri.strip_prefix("file://")?)?;
+    // `file:///C:/...` on Windows
+    if cfg!(windows) && path.as_bytes().get(2) == Some(&b':') {
+        return Some(PathBuf::from(&path[1..]));
+    }
+    Some(PathBuf::from(path))
+}
+
+/// Reject code containing one of the denied patterns of the policy, ignoring whitespace
+fn check_denied_patterns(policy: &ExecLuaPolicy, code: &str) -> Result<(), NeovimError> {
+    let compact: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    for pattern in policy.denied_patterns() {
+        let compact_pattern: String = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+        if compact.contains(&compact_pattern) {
+            return Err(NeovimError::PolicyViolation {
+                rule: "denied_pattern".to_string(),
+                detail: format!("`{pattern}` is not allowed"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check Lua code against the `exec_lua` policy before sending it to Neovim.
+///
+/// This is a static check of the source text, the sandbox enforces the same rules
+/// at runtime for code that reaches functions indirectly.
+pub fn check_exec_lua(policy: &ExecLuaPolicy, code: &str) -> Result<(), NeovimError> {
+    check_denied_patterns(policy, code)?;
+
+    if let Some(allowed_api) = &policy.allowed_api {
+        let api_call = Regex::new(
+            r#"^\s*(?:\.\s*([A-Za-z_][A-Za-z0-9_]*)|\[\s*["']([A-Za-z_][A-Za-z0-9_]*)["']\s*\])"#,
+        )
+        .expect("valid regex");
+        let api_ref = Regex::new(r"\bvim\s*\.\s*api\b").expect("valid regex");
+        for found in api_ref.find_iter(code) {
+            let function = api_call
+                .captures(&code[found.end()..])
+                .and_then(|c| c.get(1).or_else(|| c.get(2)))
+                .map(|m| m.as_str());
+            match function {
+                Some(function) if allowed_api.iter().any(|f| f == function) => {}
+                Some(function) => {
+                    return Err(NeovimError::PolicyViolation {
+                        rule: "api_not_allowed".to_string(),
+                        detail: format!("`vim.api.{function}` is not in the allowlist"),
+                    });
+                }
+                None => {
+                    return Err(NeovimError::PolicyViolation {
+                        rule: "api_not_allowed".to_string(),
+                        detail: "`vim.api` may only be used to call allowlisted functions"
+                            .to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ex commands rejected by `exec_command` under the `exec_lua` policy, with the
+/// length of their shortest abbreviation: shell commands, Lua and other script
+/// code that would bypass the Lua checks, and commands running other commands
+/// built at runtime
+const DENIED_EX_COMMANDS: &[(&str, usize)] = &[
+    ("execute", 3),
+    ("grep", 2),
+    ("grepadd", 5),
+    ("lgrep", 3),
+    ("lgrepadd", 6),
+    ("lmake", 4),
+    ("lua", 3),
+    ("luado", 4),
+    ("luafile", 4),
+    ("make", 3),
+    ("normal", 4),
+    ("perl", 2),
+    ("py3", 3),
+    ("pyfile", 3),
+    ("python", 2),
+    ("python3", 7),
+    ("pythonx", 7),
+    ("pyx", 3),
+    ("ruby", 3),
+    ("rubyfile", 5),
+    ("runtime", 2),
+    ("shell", 2),
+    ("source", 2),
+    ("terminal", 3),
+];
+
+/// Command modifiers and commands running the command that follows them
+const EX_COMMAND_PREFIXES: &[(&str, usize)] = &[
+    ("aboveleft", 3),
+    ("argdo", 5),
+    ("belowright", 3),
+    ("botright", 2),
+    ("browse", 3),
+    ("bufdo", 5),
+    ("cdo", 3),
+    ("cfdo", 4),
+    ("confirm", 4),
+    ("folddoclosed", 7),
+    ("folddoopen", 5),
+    ("hide", 3),
+    ("horizontal", 3),
+    ("keepalt", 5),
+    ("keepjumps", 5),
+    ("keepmarks", 3),
+    ("keeppatterns", 5),
+    ("ldo", 3),
+    ("leftabove", 5),
+    ("legacy", 3),
+    ("lfdo", 4),
+    ("lockmarks", 3),
+    ("noautocmd", 3),
+    ("noswapfile", 3),
+    ("rightbelow", 6),
+    ("sandbox", 3),
+    ("silent", 3),
+    ("tab", 3),
+    ("tabdo", 4),
+    ("topleft", 2),
+    ("unsilent", 3),
+    ("verbose", 4),
+    ("vertical", 4),
+    ("vim9cmd", 4),
+    ("windo", 5),
+];
+
+/// Commands taking a pattern followed by the command to run
+const EX_PATTERN_PREFIXES: &[(&str, usize)] = &[("filter", 4), ("global", 1), ("vglobal", 1)];
+
+/// Vimscript functions running shell commands or Lua code
+const DENIED_EX_FUNCTIONS: &[&str] = &[
+    "execute",
+    "jobstart",
+    "libcall",
+    "libcallnr",
+    "luaeval",
+    "system",
+    "systemlist",
+    "termopen",
+];
+
+/// Full name of the Ex command `name` abbreviates, if it is one of `commands`
+fn ex_command_name(name: &str, commands: &[(&'static str, usize)]) -> Option<&'static str> {
+    commands
+        .iter()
+        .find(|(command, min)| name.len() >= *min && command.starts_with(name))
+        .map(|(command, _)| *command)
+}
+
+/// Skip the `/pattern/` argument of `:global` and `:filter`
+fn skip_ex_pattern(rest: &str) -> &str {
+    let rest = rest.trim_start();
+    match rest.chars().next() {
+        Some(delimiter) if !delimiter.is_alphanumeric() && delimiter != '"' => {
+            let pattern = &rest[delimiter.len_utf8()..];
+            match pattern.find(delimiter) {
+                Some(end) => &pattern[end + delimiter.len_utf8()..],
+                None => "",
+            }
+        }
+        // `:filter pattern command`
+        _ => rest.split_once(char::is_whitespace).map_or("", |(_, r)| r),
+    }
+}
+
+/// Skip leading colons, whitespace and the range of an Ex command, like `%`,
+/// `'<,'>`, `1,$` or `/pattern/+1`
+fn skip_ex_range(command: &str) -> &str {
+    let mut rest = command;
+    loop {
+        let mut chars = rest.chars();
+        rest = match chars.next() {
+            Some(c) if c == ':' || c.is_whitespace() || c.is_ascii_digit() => chars.as_str(),
+            Some(c) if ",;.$%+-".contains(c) => chars.as_str(),
+            // Marks
+            Some('\'') => {
+                chars.next();
+                chars.as_str()
+            }
+            // `\/`, `\?` and `\&` refer to the last search pattern
+            Some('\\') => {
+                chars.next();
+                chars.as_str()
+            }
+            Some(delimiter @ ('/' | '?')) => {
+                let pattern = chars.as_str();
+                match pattern.find(delimiter) {
+                    Some(end) => &pattern[end + 1..],
+                    None => "",
+                }
+            }
+            _ => return rest,
+        };
+    }
+}
+
+/// Check one Ex command, following modifiers to the command they run
+fn check_ex_command(command: &str) -> Result<(), NeovimError> {
+    let denied = |command: &str| {
+        Err(NeovimError::PolicyViolation {
+            rule: "denied_command".to_string(),
+            detail: format!("`:{command}` is not allowed by the exec_lua policy"),
+        })
+    };
+
+    let mut rest = command;
+    loop {
+        rest = skip_ex_range(rest);
+        if rest.starts_with('!') {
+            return denied("!");
+        }
+        // `:=expr` evaluates Lua
+        if rest.starts_with('=') {
+            return denied("=");
+        }
+
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let (name, args) = rest.split_at(name_len);
+        let bang = args.starts_with('!');
+        let args = args.strip_prefix('!').unwrap_or(args);
+
+        if let Some(command) = ex_command_name(name, DENIED_EX_COMMANDS) {
+            return denied(command);
+        }
+        // `:r !cmd` reads and `:w !cmd` writes through a shell command
+        if matches!(name, "r" | "re" | "rea" | "read")
+            && (bang || args.trim_start().starts_with('!'))
+        {
+            return denied("read !");
+        }
+        if ex_command_name(name, &[("write", 1)]).is_some()
+            && !bang
+            && args.trim_start().starts_with('!')
+        {
+            return denied("write !");
+        }
+
+        rest = if ex_command_name(name, EX_COMMAND_PREFIXES).is_some() {
+            args
+        } else if ex_command_name(name, EX_PATTERN_PREFIXES).is_some() {
+            skip_ex_pattern(args)
+        } else {
+            return Ok(());
+        };
+    }
+}
+
+/// Check Ex commands against the `exec_lua` policy before running them with `exec_command`.
+///
+/// Besides the denied patterns, this rejects shell commands, script code and
+/// commands running commands built at runtime, so the Lua policy can't be
+/// bypassed. Like [`check_exec_lua`] this is a best effort static check, user
+/// defined commands and mappings run whatever they are defined to run.
+pub fn check_exec_command(policy: &ExecLuaPolicy, command: &str) -> Result<(), NeovimError> {
+    check_denied_patterns(policy, command)?;
+
+    let function_call = Regex::new(r"\b([a-z]+)\s*\(").expect("valid regex");
+    for call in function_call.captures_iter(command) {
+        let function = &call[1];
+        if DENIED_EX_FUNCTIONS.contains(&function) {
+            return Err(NeovimError::PolicyViolation {
+                rule: "denied_function".to_string(),
+                detail: format!("`{function}()` is not allowed"),
+            });
+        }
+    }
+    if command.contains("v:lua") {
+        return Err(NeovimError::PolicyViolation {
+            rule: "denied_function".to_string(),
+            detail: "`v:lua` is not allowed by the exec_lua policy".to_string(),
+        });
+    }
+
+    // `|` separates commands, split on it everywhere to err on the side of caution
+    command.split(['|', '\n']).try_for_each(check_ex_command)
+}
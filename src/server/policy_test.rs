@@ -0,0 +1,406 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::{ConfirmConfig, ExecLuaPolicy};
+    use crate::neovim::{ConfirmPreview, NeovimError, WorkspaceEdit};
+    use crate::server::policy::*;
+    use tempfile::TempDir;
+
+    fn violation_rule(result: Result<(), NeovimError>) -> String {
+        match result {
+            Err(NeovimError::PolicyViolation { rule, .. }) => rule,
+            other => panic!("Expected policy violation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_tool_access() {
+        assert_eq!(tool_access("exec_lua"), ToolAccess::Mutating);
+        assert_eq!(tool_access("exec_command"), ToolAccess::Mutating);
+        assert_eq!(tool_access("lsp_references"), ToolAccess::ReadOnly);
+        assert_eq!(
+            tool_access("lsp_organize_imports"),
+            ToolAccess::MutatingWhen {
+                argument: "apply_edits",
+                default: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_tool_annotations_match_access() {
+        let router = crate::server::tools::build_tool_router();
+        for tool in router.list_all() {
+            let annotations = tool
+                .annotations
+                .as_ref()
+                .unwrap_or_else(|| panic!("{} has no annotations", tool.name));
+            let read_only = tool_access(&tool.name) == ToolAccess::ReadOnly
+                && !matches!(
+                    tool.name.as_ref(),
+                    "connect" | "connect_tcp" | "spawn_nvim" | "disconnect"
+                );
+            assert_eq!(
+                annotations.read_only_hint,
+                Some(read_only),
+                "{} read_only_hint",
+                tool.name
+            );
+            assert!(annotations.open_world_hint.is_some(), "{}", tool.name);
+            assert!(annotations.idempotent_hint.is_some(), "{}", tool.name);
+            if !read_only {
+                // Formatting only rewrites layout, so it is not destructive
+                let destructive = matches!(
+                    tool.name.as_ref(),
+                    "disconnect" | "exec_lua" | "exec_command" | "lsp_apply_edit" | "lsp_rename"
+                );
+                assert_eq!(
+                    annotations.destructive_hint,
+                    Some(destructive),
+                    "{} destructive_hint",
+                    tool.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_connection_read_only_only_tightens() {
+        use crate::server::NeovimMcpServer;
+        use rmcp::model::CallToolRequestParam;
+
+        let server = NeovimMcpServer::new(std::env::temp_dir());
+        let request = CallToolRequestParam {
+            name: "exec_lua".into(),
+            arguments: serde_json::json!({ "connection_id": "abc1234", "code": "" })
+                .as_object()
+                .cloned(),
+        };
+
+        server.mark_connection_read_only("abc1234", false);
+        assert!(server.check_tool_policy(&request).is_ok());
+
+        // Reconnecting without `read_only` keeps the mark
+        server.mark_connection_read_only("abc1234", true);
+        server.mark_connection_read_only("abc1234", false);
+        assert!(server.check_tool_policy(&request).is_err());
+
+        // The configured mode applies to every connection
+        let mut server = NeovimMcpServer::new(std::env::temp_dir());
+        server.read_only = true;
+        server.mark_connection_read_only("abc1234", false);
+        assert!(server.read_only_connections.contains("abc1234"));
+    }
+
+    #[test]
+    fn test_spawn_arguments_policy() {
+        use crate::config::PathPolicy;
+        use crate::server::NeovimMcpServer;
+        use std::path::Path;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let init_file = Path::new("init.lua");
+
+        let server = NeovimMcpServer::new(std::env::temp_dir());
+        assert!(server.check_spawn_arguments(&root, Some(init_file)).is_ok());
+
+        // An init file runs arbitrary code
+        let mut server = NeovimMcpServer::new(std::env::temp_dir());
+        server.read_only = true;
+        assert!(
+            server
+                .check_spawn_arguments(&root, Some(init_file))
+                .is_err()
+        );
+        assert!(
+            server
+                .check_spawn_arguments(&root, Some(Path::new("NONE")))
+                .is_ok()
+        );
+        assert!(server.check_spawn_arguments(&root, None).is_ok());
+
+        let mut server = NeovimMcpServer::new(std::env::temp_dir());
+        server.exec_lua_policy.enabled = Some(true);
+        assert!(
+            server
+                .check_spawn_arguments(&root, Some(init_file))
+                .is_err()
+        );
+
+        let mut server = NeovimMcpServer::new(std::env::temp_dir());
+        server.path_policy = PathPolicy {
+            confine: Some(true),
+            allowed_roots: Some(vec![root.join("project")]),
+        };
+        let project = root.join("project");
+        assert!(
+            server
+                .check_spawn_arguments(&project, Some(init_file))
+                .is_ok()
+        );
+        assert!(server.check_spawn_arguments(&root, None).is_err());
+        assert!(
+            server
+                .check_spawn_arguments(&project, Some(Path::new("../init.lua")))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_exec_lua_default_denied_patterns() {
+        let policy = ExecLuaPolicy::default();
+
+        assert!(check_exec_lua(&policy, "return vim.api.nvim_get_current_buf()").is_ok());
+        for code in [
+            "os.execute('rm -rf /')",
+            "local h = io.popen('ls')",
+            "return vim.fn.system({'ls'})",
+            "vim . fn . systemlist('ls')",
+        ] {
+            assert_eq!(
+                violation_rule(check_exec_lua(&policy, code)),
+                "denied_pattern"
+            );
+        }
+    }
+
+    #[test]
+    fn test_exec_lua_custom_denied_patterns() {
+        let policy = ExecLuaPolicy {
+            denied_patterns: Some(vec!["vim.cmd".to_string()]),
+            ..Default::default()
+        };
+
+        assert!(check_exec_lua(&policy, "os.execute('true')").is_ok());
+        assert_eq!(
+            violation_rule(check_exec_lua(&policy, "vim.cmd('write')")),
+            "denied_pattern"
+        );
+    }
+
+    #[test]
+    fn test_exec_lua_api_allowlist() {
+        let policy = ExecLuaPolicy {
+            allowed_api: Some(vec![
+                "nvim_get_current_buf".to_string(),
+                "nvim_buf_get_lines".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        assert!(
+            check_exec_lua(
+                &policy,
+                "local buf = vim.api.nvim_get_current_buf()\n\
+                 return vim.api[\"nvim_buf_get_lines\"](buf, 0, -1, false)"
+            )
+            .is_ok()
+        );
+        assert_eq!(
+            violation_rule(check_exec_lua(
+                &policy,
+                "vim.api.nvim_buf_set_lines(0, 0, -1, false, {})"
+            )),
+            "api_not_allowed"
+        );
+        assert_eq!(
+            violation_rule(check_exec_lua(&policy, "local api = vim.api")),
+            "api_not_allowed"
+        );
+    }
+
+    #[test]
+    fn test_exec_command_policy() {
+        let policy = ExecLuaPolicy::default();
+
+        for command in [
+            "messages",
+            "checkhealth",
+            "Git status",
+            "silent! write",
+            "'<,'>sort",
+            "g/TODO/print",
+            "w! /tmp/out.txt",
+            "echo expand('%')",
+        ] {
+            assert!(
+                check_exec_command(&policy, command).is_ok(),
+                "{command} should be allowed"
+            );
+        }
+
+        for command in [
+            "!ls",
+            ":%!sort",
+            "'a,'b!sort",
+            "/foo/!ls",
+            "r !ls",
+            "r!ls",
+            "w !sh",
+            "lua print(1)",
+            "=vim.version()",
+            "silent! luafile /tmp/x.lua",
+            "vertical botright terminal",
+            "g/x/norm dd",
+            "echo 1 | so /tmp/x.vim",
+            "exe 'lua print(1)'",
+            "py3 print(1)",
+            "make",
+        ] {
+            assert_eq!(
+                violation_rule(check_exec_command(&policy, command)),
+                "denied_command",
+                "{command} should be rejected"
+            );
+        }
+
+        for command in [
+            "echo system('ls')",
+            "call jobstart(['ls'])",
+            "echo v:lua.vim.fn.getcwd()",
+        ] {
+            assert_eq!(
+                violation_rule(check_exec_command(&policy, command)),
+                "denied_function",
+                "{command} should be rejected"
+            );
+        }
+
+        let policy = ExecLuaPolicy {
+            denied_patterns: Some(vec!["bdelete".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            violation_rule(check_exec_command(&policy, "bdelete 3")),
+            "denied_pattern"
+        );
+    }
+
+    #[test]
+    fn test_lua_may_write() {
+        assert!(lua_may_write(
+            "vim.api.nvim_buf_set_lines(0, 0, -1, false, {})"
+        ));
+        assert!(lua_may_write("vim . cmd('write')"));
+        assert!(lua_may_write("local f = io.open('out.txt', 'w')"));
+        assert!(!lua_may_write(
+            "return vim.api.nvim_buf_get_lines(0, 0, -1, false)"
+        ));
+    }
+
+    #[test]
+    fn test_needs_confirmation() {
+        let workspace_edit: WorkspaceEdit = serde_json::from_value(serde_json::json!({
+            "changes": {
+                "file:///project/a.rs": [],
+                "file:///project/b.rs": [],
+            },
+            "documentChanges": [
+                { "kind": "rename", "oldUri": "file:///project/c.rs", "newUri": "file:///project/d.rs" },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(workspace_edit.affected_uris().len(), 4);
+        let edit = ConfirmPreview::WorkspaceEdit {
+            client_name: "rust_analyzer".to_string(),
+            workspace_edit,
+        };
+        let read = ConfirmPreview::Code {
+            filetype: "lua".to_string(),
+            code: "return vim.api.nvim_get_current_buf()".to_string(),
+        };
+
+        let config = ConfirmConfig::default();
+        assert!(needs_confirmation(&config, &edit));
+        assert!(needs_confirmation(&config, &read));
+
+        // The edit must touch more than `min_files` files
+        let config = ConfirmConfig {
+            min_files: Some(3),
+            ..Default::default()
+        };
+        assert!(needs_confirmation(&config, &edit));
+
+        let config = ConfirmConfig {
+            min_files: Some(4),
+            lua_writes_only: Some(true),
+            ..Default::default()
+        };
+        assert!(!needs_confirmation(&config, &edit));
+        assert!(!needs_confirmation(&config, &read));
+
+        // Only Lua code is checked for writes, Ex commands are always confirmed
+        let command = ConfirmPreview::Code {
+            filetype: "vim".to_string(),
+            code: "messages".to_string(),
+        };
+        assert!(needs_confirmation(&config, &command));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("project/src")).unwrap();
+
+        assert_eq!(
+            resolve_path(&root.join("project/./src/../new/file.rs")),
+            root.join("project/new/file.rs")
+        );
+
+        #[cfg(unix)]
+        {
+            // `..` after a symlink leaves the link target
+            std::fs::create_dir_all(root.join("outside/nested")).unwrap();
+            std::os::unix::fs::symlink(root.join("outside/nested"), root.join("project/link"))
+                .unwrap();
+            assert_eq!(
+                resolve_path(&root.join("project/link/../secret")),
+                root.join("outside/secret")
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_path_confined() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("project");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let roots = vec![root.clone()];
+
+        assert!(check_path_confined(&root.join("src/main.rs"), &roots).is_ok());
+        assert!(check_path_confined(&root.join("new/file.rs"), &roots).is_ok());
+        for path in [
+            root.join("../other/file.rs"),
+            temp_dir.path().join("project-other/file.rs"),
+        ] {
+            assert!(
+                matches!(
+                    check_path_confined(&path, &roots),
+                    Err(NeovimError::PathOutsideRoots { .. })
+                ),
+                "{} should be rejected",
+                path.display()
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path(), root.join("escape")).unwrap();
+            assert!(check_path_confined(&root.join("escape/file.rs"), &roots).is_err());
+        }
+    }
+
+    #[test]
+    fn test_file_uri_to_path() {
+        assert_eq!(
+            file_uri_to_path("file:///project/my%20file.rs"),
+            Some(std::path::PathBuf::from("/project/my file.rs"))
+        );
+        assert_eq!(
+            file_uri_to_path("file:///project/100%"),
+            Some(std::path::PathBuf::from("/project/100%"))
+        );
+        assert_eq!(file_uri_to_path("jdt://contents/Foo.class"), None);
+    }
+}
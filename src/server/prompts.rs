@@ -0,0 +1,418 @@
+use std::path::{Path, PathBuf};
+
+use rmcp::{
+    ErrorData as McpError,
+    model::{
+        GetPromptResult, JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole,
+    },
+};
+use serde_json::{Value, json};
+use tracing::debug;
+
+use super::core::NeovimMcpServer;
+use crate::neovim::{
+    CursorPosition, DocumentIdentifier, HoverResult, NeovimClientTrait,
+    client::{Diagnostic, HoverContents, MarkedString},
+};
+
+pub const FIX_DIAGNOSTICS: &str = "fix_diagnostics";
+pub const EXPLAIN_SYMBOL: &str = "explain_symbol_at_cursor";
+pub const REVIEW_UNSAVED_CHANGES: &str = "review_unsaved_changes";
+pub const REFACTOR_WITH_RENAME: &str = "refactor_with_rename";
+
+/// Lines of context shown around the cursor
+const CURSOR_CONTEXT_LINES: u64 = 10;
+
+fn argument(name: &str, description: &str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name: name.to_string(),
+        title: None,
+        description: Some(description.to_string()),
+        required: Some(required),
+    }
+}
+
+fn connection_argument() -> PromptArgument {
+    argument("connection_id", "Neovim connection ID", true)
+}
+
+fn lsp_client_argument() -> PromptArgument {
+    argument(
+        "lsp_client_name",
+        "LSP client used for hover, defaults to the configured client for the filetype",
+        false,
+    )
+}
+
+fn prompt(name: &str, title: &str, description: &str, arguments: Vec<PromptArgument>) -> Prompt {
+    Prompt {
+        name: name.to_string(),
+        title: Some(title.to_string()),
+        description: Some(description.to_string()),
+        arguments: Some(arguments),
+        icons: None,
+    }
+}
+
+/// Prompts offered to MCP clients, filled from the editor by [`NeovimMcpServer::get_prompt_result`]
+pub fn prompt_definitions() -> Vec<Prompt> {
+    vec![
+        prompt(
+            FIX_DIAGNOSTICS,
+            "Fix diagnostics in buffer",
+            "Fix the diagnostics reported for a document, given its text and diagnostics",
+            vec![
+                connection_argument(),
+                argument(
+                    "document",
+                    "Buffer ID, project-relative or absolute path, or DocumentIdentifier JSON",
+                    true,
+                ),
+            ],
+        ),
+        prompt(
+            EXPLAIN_SYMBOL,
+            "Explain symbol at cursor",
+            "Explain the symbol under the cursor, given the surrounding code and its hover information",
+            vec![connection_argument(), lsp_client_argument()],
+        ),
+        prompt(
+            REVIEW_UNSAVED_CHANGES,
+            "Review unsaved changes",
+            "Review the changes of modified buffers against the files on disk",
+            vec![connection_argument()],
+        ),
+        prompt(
+            REFACTOR_WITH_RENAME,
+            "Refactor with rename",
+            "Rename the symbol under the cursor across the workspace with lsp_rename",
+            vec![
+                connection_argument(),
+                argument("new_name", "New name of the symbol", true),
+                lsp_client_argument(),
+            ],
+        ),
+    ]
+}
+
+/// Parse a document argument given as a buffer ID, a path or DocumentIdentifier JSON
+pub fn parse_prompt_document(value: &str) -> Result<DocumentIdentifier, McpError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(McpError::invalid_params("document cannot be empty", None));
+    }
+    if value.starts_with('{') {
+        return value.parse().map_err(|e| {
+            McpError::invalid_params(
+                format!("Invalid document identifier: {e}"),
+                Some(json!({"document": value})),
+            )
+        });
+    }
+    if let Ok(buffer_id) = value.parse::<u64>() {
+        return Ok(DocumentIdentifier::from_buffer_id(buffer_id));
+    }
+    if Path::new(value).is_absolute() {
+        Ok(DocumentIdentifier::from_absolute_path(value))
+    } else {
+        Ok(DocumentIdentifier::from_project_path(PathBuf::from(value)))
+    }
+}
+
+fn required_argument<'a>(
+    arguments: Option<&'a JsonObject>,
+    name: &str,
+) -> Result<&'a str, McpError> {
+    optional_argument(arguments, name)
+        .ok_or_else(|| McpError::invalid_params(format!("Missing required argument: {name}"), None))
+}
+
+fn optional_argument<'a>(arguments: Option<&'a JsonObject>, name: &str) -> Option<&'a str> {
+    arguments
+        .and_then(|args| args.get(name))
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+/// Lines of `text` prefixed with their 1-based line numbers, limited to `lines` (0-based, inclusive)
+pub fn numbered_lines(text: &str, lines: Option<(u64, u64)>, marker: Option<u64>) -> String {
+    let (first, last) = lines.unwrap_or((0, u64::MAX));
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i as u64, line))
+        .filter(|(i, _)| (first..=last).contains(i))
+        .map(|(i, line)| {
+            let prefix = if marker == Some(i) { ">" } else { " " };
+            format!("{prefix}{:>5} | {line}", i + 1)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One diagnostic per line with its 1-based position, severity and source
+pub fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    if diagnostics.is_empty() {
+        return "No diagnostics reported.".to_string();
+    }
+    diagnostics
+        .iter()
+        .map(|d| {
+            let severity = match d.severity {
+                1 => "ERROR",
+                2 => "WARN",
+                3 => "INFO",
+                _ => "HINT",
+            };
+            let code = d
+                .code
+                .as_ref()
+                .map(|code| match code {
+                    Value::String(s) => format!(" [{s}]"),
+                    code => format!(" [{code}]"),
+                })
+                .unwrap_or_default();
+            format!(
+                "- {}:{} {severity} ({}){code}: {}",
+                d.lnum + 1,
+                d.col + 1,
+                d.source,
+                d.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Hover contents rendered as markdown
+pub fn hover_markdown(hover: &HoverResult) -> String {
+    fn marked(s: &MarkedString) -> String {
+        match s {
+            MarkedString::String(s) => s.clone(),
+            MarkedString::Markup { lang, value } => format!("```{lang}\n{value}\n```"),
+        }
+    }
+
+    match &hover.contents {
+        HoverContents::String(s) => marked(s),
+        HoverContents::Strings(strings) => {
+            strings.iter().map(marked).collect::<Vec<_>>().join("\n\n")
+        }
+        HoverContents::Content(content) => content.value.clone(),
+    }
+}
+
+fn prompt_result(description: String, text: String) -> GetPromptResult {
+    GetPromptResult {
+        description: Some(description),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    }
+}
+
+fn unknown_prompt(name: &str) -> McpError {
+    McpError::invalid_params(
+        format!("Unknown prompt: {name}"),
+        Some(json!({"name": name})),
+    )
+}
+
+fn display_name(name: &str) -> &str {
+    if name.is_empty() { "[No Name]" } else { name }
+}
+
+impl NeovimMcpServer {
+    /// Fill a prompt with the current state of the connection named in its arguments
+    pub async fn get_prompt_result(
+        &self,
+        name: &str,
+        arguments: Option<&JsonObject>,
+    ) -> Result<GetPromptResult, McpError> {
+        debug!("Building prompt {name}");
+        if !prompt_definitions()
+            .iter()
+            .any(|prompt| prompt.name == name)
+        {
+            return Err(unknown_prompt(name));
+        }
+        let connection_id = required_argument(arguments, "connection_id")?;
+        let client = self.get_connection(connection_id)?;
+        let client = client.value().as_ref();
+
+        match name {
+            FIX_DIAGNOSTICS => {
+                let document = parse_prompt_document(required_argument(arguments, "document")?)?;
+                self.fix_diagnostics_prompt(client, connection_id, document)
+                    .await
+            }
+            EXPLAIN_SYMBOL => {
+                let lsp_client_name = optional_argument(arguments, "lsp_client_name");
+                self.explain_symbol_prompt(client, connection_id, lsp_client_name)
+                    .await
+            }
+            REVIEW_UNSAVED_CHANGES => {
+                let changes = client.get_unsaved_changes().await?;
+                let text = if changes.is_empty() {
+                    "There are no unsaved changes in Neovim.".to_string()
+                } else {
+                    let diffs = changes
+                        .iter()
+                        .map(|change| format!("```diff\n{}\n```", change.diff.trim_end()))
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    format!(
+                        "Review the unsaved changes in Neovim (connection `{connection_id}`). \
+                         Point out bugs, risky edits and style problems, and suggest improvements \
+                         before they are written to disk.\n\n{diffs}"
+                    )
+                };
+                Ok(prompt_result(
+                    format!("Unsaved changes in {} buffers", changes.len()),
+                    text,
+                ))
+            }
+            REFACTOR_WITH_RENAME => {
+                let new_name = required_argument(arguments, "new_name")?;
+                let lsp_client_name = optional_argument(arguments, "lsp_client_name");
+                self.rename_prompt(client, connection_id, new_name, lsp_client_name)
+                    .await
+            }
+            _ => Err(unknown_prompt(name)),
+        }
+    }
+
+    async fn fix_diagnostics_prompt(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        connection_id: &str,
+        document: DocumentIdentifier,
+    ) -> Result<GetPromptResult, McpError> {
+        // The document text is sent to the client, so it must be inside the allowed roots
+        self.check_document_confined(client, &document).await?;
+        let text = client.read_document(&document).await?;
+        let diagnostics = match text.buffer_id {
+            Some(buffer_id) => client.get_buffer_diagnostics(buffer_id).await?,
+            None => Vec::new(),
+        };
+        let name = display_name(&text.name);
+        let document_json = match text.buffer_id {
+            Some(buffer_id) => json!({"buffer_id": buffer_id}),
+            None => serde_json::to_value(&document).unwrap_or_default(),
+        };
+
+        Ok(prompt_result(
+            format!("{} diagnostics in {name}", diagnostics.len()),
+            format!(
+                "Fix the diagnostics reported in `{name}` (connection `{connection_id}`, document \
+                 `{document_json}`). Prefer the LSP code actions from `lsp_code_actions` when they \
+                 fix a diagnostic, and explain any change that alters behavior.\n\n\
+                 Diagnostics:\n{}\n\n```{}\n{}\n```",
+                format_diagnostics(&diagnostics),
+                text.filetype,
+                numbered_lines(&text.text, None, None),
+            ),
+        ))
+    }
+
+    /// Hover information at the cursor, or a note explaining why there is none
+    async fn hover_at_cursor(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        cursor: &CursorPosition,
+        lsp_client_name: Option<&str>,
+    ) -> String {
+        let document = DocumentIdentifier::from_buffer_id(cursor.buffer_id);
+        let lsp_client_name = match self
+            .resolve_lsp_client_name(client, &document, lsp_client_name.map(str::to_string))
+            .await
+        {
+            Ok(name) => name,
+            Err(e) => return format!("No hover information: {}", e.message),
+        };
+        match client
+            .lsp_hover(&lsp_client_name, document, cursor.position.clone())
+            .await
+        {
+            Ok(hover) => hover_markdown(&hover),
+            Err(e) => format!("No hover information from {lsp_client_name}: {e}"),
+        }
+    }
+
+    /// Code around the cursor, with the cursor line marked
+    async fn cursor_context(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        cursor: &CursorPosition,
+    ) -> Result<String, McpError> {
+        let text = client
+            .read_document(&DocumentIdentifier::from_buffer_id(cursor.buffer_id))
+            .await?;
+        let line = cursor.position.line;
+        Ok(format!(
+            "```{}\n{}\n```",
+            cursor.filetype,
+            numbered_lines(
+                &text.text,
+                Some((
+                    line.saturating_sub(CURSOR_CONTEXT_LINES),
+                    line + CURSOR_CONTEXT_LINES,
+                )),
+                Some(line),
+            )
+        ))
+    }
+
+    async fn explain_symbol_prompt(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        connection_id: &str,
+        lsp_client_name: Option<&str>,
+    ) -> Result<GetPromptResult, McpError> {
+        let cursor = client.get_cursor().await?;
+        let context = self.cursor_context(client, &cursor).await?;
+        let hover = self.hover_at_cursor(client, &cursor, lsp_client_name).await;
+        let name = display_name(&cursor.name);
+
+        Ok(prompt_result(
+            format!("Explain `{}` in {name}", cursor.word),
+            format!(
+                "Explain the symbol `{}` under the cursor in `{name}` (connection \
+                 `{connection_id}`, buffer {}, line {}, character {}): what it is, how it is \
+                 used here and anything surprising about it. Use the LSP tools to look up \
+                 its definition and references if needed.\n\n\
+                 Hover information:\n{hover}\n\nCode around the cursor (marked with `>`):\n{context}",
+                cursor.word, cursor.buffer_id, cursor.position.line, cursor.position.character,
+            ),
+        ))
+    }
+
+    async fn rename_prompt(
+        &self,
+        client: &(dyn NeovimClientTrait + Send),
+        connection_id: &str,
+        new_name: &str,
+        lsp_client_name: Option<&str>,
+    ) -> Result<GetPromptResult, McpError> {
+        let cursor = client.get_cursor().await?;
+        let context = self.cursor_context(client, &cursor).await?;
+        let hover = self.hover_at_cursor(client, &cursor, lsp_client_name).await;
+        let name = display_name(&cursor.name);
+        let arguments = json!({
+            "connection_id": connection_id,
+            "document": {"buffer_id": cursor.buffer_id},
+            "line": cursor.position.line,
+            "character": cursor.position.character,
+            "new_name": new_name,
+        });
+
+        Ok(prompt_result(
+            format!("Rename `{}` to `{new_name}`", cursor.word),
+            format!(
+                "Rename the symbol `{}` under the cursor in `{name}` to `{new_name}` across the \
+                 workspace by calling `lsp_rename` with these arguments:\n\n```json\n{arguments:#}\n```\n\n\
+                 Afterwards, update comments, strings and documentation that still mention \
+                 the old name, and report the files that changed.\n\n\
+                 Hover information:\n{hover}\n\nCode around the cursor (marked with `>`):\n{context}",
+                cursor.word,
+            ),
+        ))
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use crate::neovim::{DocumentIdentifier, HoverResult, client::Diagnostic};
+    use crate::server::prompts::*;
+
+    #[test]
+    fn test_prompt_definitions() {
+        let prompts = prompt_definitions();
+        let names: Vec<&str> = prompts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                FIX_DIAGNOSTICS,
+                EXPLAIN_SYMBOL,
+                REVIEW_UNSAVED_CHANGES,
+                REFACTOR_WITH_RENAME
+            ]
+        );
+
+        for prompt in &prompts {
+            let arguments = prompt.arguments.as_ref().unwrap();
+            assert_eq!(arguments[0].name, "connection_id");
+            assert_eq!(arguments[0].required, Some(true));
+        }
+    }
+
+    #[test]
+    fn test_parse_prompt_document() {
+        assert_eq!(
+            parse_prompt_document("12").unwrap(),
+            DocumentIdentifier::BufferId(12)
+        );
+        assert_eq!(
+            parse_prompt_document(" src/lib.rs ").unwrap(),
+            DocumentIdentifier::ProjectRelativePath(PathBuf::from("src/lib.rs"))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            parse_prompt_document("/tmp/main.rs").unwrap(),
+            DocumentIdentifier::AbsolutePath(PathBuf::from("/tmp/main.rs"))
+        );
+        assert_eq!(
+            parse_prompt_document(r#"{"buffer_id": 3}"#).unwrap(),
+            DocumentIdentifier::BufferId(3)
+        );
+        assert!(parse_prompt_document("").is_err());
+        assert!(parse_prompt_document(r#"{"buffer": 3}"#).is_err());
+    }
+
+    #[test]
+    fn test_numbered_lines() {
+        let text = "fn main() {\n    println!();\n}";
+        assert_eq!(
+            numbered_lines(text, None, None),
+            "     1 | fn main() {\n     2 |     println!();\n     3 | }"
+        );
+        assert_eq!(
+            numbered_lines(text, Some((1, 5)), Some(1)),
+            ">    2 |     println!();\n     3 | }"
+        );
+    }
+
+    #[test]
+    fn test_format_diagnostics() {
+        assert_eq!(format_diagnostics(&[]), "No diagnostics reported.");
+
+        let diagnostics: Vec<Diagnostic> = serde_json::from_value(json!([{
+            "message": "unused variable: `x`",
+            "code": "unused_variables",
+            "severity": 2,
+            "lnum": 4,
+            "col": 8,
+            "source": "rustc",
+            "bufnr": 1,
+            "end_lnum": 4,
+            "end_col": 9,
+            "namespace": 1,
+            "user_data": null
+        }]))
+        .unwrap();
+        assert_eq!(
+            format_diagnostics(&diagnostics),
+            "- 5:9 WARN (rustc) [unused_variables]: unused variable: `x`"
+        );
+    }
+
+    #[test]
+    fn test_hover_markdown() {
+        let hover: HoverResult = serde_json::from_value(json!({
+            "contents": {"kind": "markdown", "value": "```rust\nfn main()\n```"}
+        }))
+        .unwrap();
+        assert_eq!(hover_markdown(&hover), "```rust\nfn main()\n```");
+
+        let hover: HoverResult = serde_json::from_value(json!({
+            "contents": {"Markup": {"lang": "lua", "value": "function f() end"}}
+        }))
+        .unwrap();
+        assert_eq!(hover_markdown(&hover), "```lua\nfunction f() end\n```");
+    }
+}
@@ -1,17 +1,412 @@
-use regex::Regex;
+use std::{collections::HashMap, path::Path, time::Instant};
+
+use dashmap::Entry;
+
 use rmcp::{
     ErrorData as McpError, ServerHandler,
+    handler::server::tool::ToolCallContext,
     model::*,
-    service::{RequestContext, RoleServer},
-    tool_handler,
+    service::{Peer, RequestContext, RoleServer},
 };
+use serde::Serialize;
 use serde_json::json;
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
 use tracing::{debug, instrument};
 
+use super::audit::AuditEntry;
 use super::core::NeovimMcpServer;
+use super::policy::{is_mutating_call, percent_decode};
+use super::prompts::prompt_definitions;
+use crate::neovim::DocumentIdentifier;
+
+/// MCP clients notified when a subscribed buffer changes
+pub struct ResourceSubscription {
+    /// Subscribed peers, keyed by [`NeovimMcpServer::session_id`]
+    peers: HashMap<u64, Peer<RoleServer>>,
+    /// Task forwarding the buffer changes of the connection to the peers
+    forwarder: JoinHandle<()>,
+}
+
+impl ResourceSubscription {
+    /// Number of MCP sessions notified of changes
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+}
+
+impl Drop for ResourceSubscription {
+    fn drop(&mut self) {
+        self.forwarder.abort();
+    }
+}
+
+/// MIME type of the text of a buffer with the given filetype
+pub fn filetype_mime_type(filetype: &str) -> &'static str {
+    match filetype {
+        "c" => "text/x-c",
+        "cpp" => "text/x-c++",
+        "css" => "text/css",
+        "go" => "text/x-go",
+        "html" => "text/html",
+        "java" => "text/x-java",
+        "javascript" | "javascriptreact" => "text/javascript",
+        "json" | "jsonc" => "application/json",
+        "lua" => "text/x-lua",
+        "markdown" => "text/markdown",
+        "python" => "text/x-python",
+        "rust" => "text/x-rust",
+        "sh" | "bash" | "zsh" => "text/x-shellscript",
+        "toml" => "application/toml",
+        "typescript" | "typescriptreact" => "text/typescript",
+        "xml" => "application/xml",
+        "yaml" => "application/yaml",
+        _ => "text/plain",
+    }
+}
+
+/// Resource addressed by a URI of one of the server's schemes
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceUri {
+    Connections,
+    Audit,
+    WorkspaceDiagnostics {
+        connection_id: String,
+    },
+    BufferDiagnostics {
+        connection_id: String,
+        buffer_id: u64,
+    },
+    Buffer {
+        connection_id: String,
+        buffer_id: u64,
+    },
+    Symbols {
+        connection_id: String,
+        document: DocumentIdentifier,
+    },
+    State {
+        connection_id: String,
+    },
+}
+
+fn resource_not_found(uri: &str) -> McpError {
+    McpError::resource_not_found("resource_not_found", Some(json!({"uri": uri})))
+}
+
+fn parse_buffer_id(s: &str) -> Result<u64, McpError> {
+    s.parse()
+        .map_err(|_| McpError::invalid_params("Invalid buffer ID", None))
+}
+
+/// Parse a resource URI, including the ones expanded from [`resource_templates`]
+pub fn parse_resource_uri(uri: &str) -> Result<ResourceUri, McpError> {
+    match uri {
+        "nvim-connections://" => return Ok(ResourceUri::Connections),
+        "nvim-audit://" => return Ok(ResourceUri::Audit),
+        _ => {}
+    }
+
+    let (scheme, rest) = uri
+        .split_once("://")
+        .ok_or_else(|| resource_not_found(uri))?;
+    if scheme == "nvim-state" {
+        if rest.is_empty() || rest.contains('/') {
+            return Err(resource_not_found(uri));
+        }
+        return Ok(ResourceUri::State {
+            connection_id: rest.to_string(),
+        });
+    }
+    let (connection_id, path) = rest
+        .split_once('/')
+        .filter(|(connection_id, path)| !connection_id.is_empty() && !path.is_empty())
+        .ok_or_else(|| resource_not_found(uri))?;
+    let connection_id = connection_id.to_string();
+
+    match (scheme, path) {
+        ("nvim-diagnostics", "workspace") => {
+            Ok(ResourceUri::WorkspaceDiagnostics { connection_id })
+        }
+        ("nvim-diagnostics", path) if path.starts_with("buffer/") => {
+            Ok(ResourceUri::BufferDiagnostics {
+                connection_id,
+                buffer_id: parse_buffer_id(&path["buffer/".len()..])?,
+            })
+        }
+        ("nvim-buffer", buffer_id) => Ok(ResourceUri::Buffer {
+            connection_id,
+            buffer_id: parse_buffer_id(buffer_id)?,
+        }),
+        ("nvim-symbols", path) => {
+            let path = percent_decode(path)
+                .ok_or_else(|| McpError::invalid_params("Invalid path encoding", None))?;
+            let document = if Path::new(&path).is_absolute() {
+                DocumentIdentifier::from_absolute_path(path)
+            } else {
+                DocumentIdentifier::from_project_path(path)
+            };
+            Ok(ResourceUri::Symbols {
+                connection_id,
+                document,
+            })
+        }
+        _ => Err(resource_not_found(uri)),
+    }
+}
+
+fn resource_template(
+    uri_template: &str,
+    name: &str,
+    description: &str,
+    mime_type: &str,
+) -> ResourceTemplate {
+    ResourceTemplate {
+        raw: RawResourceTemplate {
+            uri_template: uri_template.to_string(),
+            name: name.to_string(),
+            title: None,
+            description: Some(description.to_string()),
+            mime_type: Some(mime_type.to_string()),
+        },
+        annotations: None,
+    }
+}
+
+impl NeovimMcpServer {
+    /// Loaded and listed buffers of every connection as `nvim-buffer://` resources
+    async fn buffer_resources(&self) -> Vec<Resource> {
+        let mut resources = Vec::new();
+        let connection_ids: Vec<String> = self
+            .nvim_clients
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        for connection_id in connection_ids {
+            let Some(client) = self.nvim_clients.get(&connection_id) else {
+                continue;
+            };
+            let buffers = match client.get_buffers().await {
+                Ok(buffers) => buffers,
+                Err(e) => {
+                    debug!("Failed to list buffers of {connection_id}: {e}");
+                    continue;
+                }
+            };
+            for buffer in buffers.into_iter().filter(|b| b.loaded || b.listed) {
+                let name = if buffer.name.is_empty() {
+                    "[No Name]"
+                } else {
+                    &buffer.name
+                };
+                resources.push(Resource {
+                    raw: RawResource {
+                        uri: format!("nvim-buffer://{connection_id}/{}", buffer.id),
+                        name: format!("{name} ({connection_id})"),
+                        title: None,
+                        description: Some(format!(
+                            "Text of buffer {} for connection {connection_id}",
+                            buffer.id
+                        )),
+                        mime_type: Some(filetype_mime_type(&buffer.filetype).to_string()),
+                        size: u32::try_from(buffer.size).ok(),
+                        icons: None,
+                    },
+                    annotations: None,
+                });
+            }
+        }
+        resources
+    }
+
+    /// Notify `peer` when the buffer of an `nvim-buffer://` URI changes
+    async fn subscribe_resource(
+        &self,
+        uri: String,
+        peer: Peer<RoleServer>,
+    ) -> Result<(), McpError> {
+        let ResourceUri::Buffer {
+            connection_id,
+            buffer_id,
+        } = parse_resource_uri(&uri)?
+        else {
+            return Err(McpError::invalid_params(
+                "Only nvim-buffer:// resources support subscriptions",
+                Some(json!({"uri": uri})),
+            ));
+        };
+
+        if let Some(mut subscription) = self.resource_subscriptions.get_mut(&uri) {
+            subscription.peers.insert(self.session_id, peer);
+            return Ok(());
+        }
+
+        let client = self.get_connection(&connection_id)?;
+        let mut changes = client.subscribe_buffer_changes().ok_or_else(|| {
+            McpError::invalid_request(format!("Connection {connection_id} is closed"), None)
+        })?;
+        client.attach_buffer(buffer_id).await?;
+        drop(client);
+
+        let subscriptions = self.resource_subscriptions.clone();
+        let nvim_clients = self.nvim_clients.clone();
+        let watched = uri.clone();
+        let forwarder = tokio::spawn(async move {
+            loop {
+                match changes.recv().await {
+                    Ok(changed) if changed != buffer_id => continue,
+                    // Missed events may include this buffer
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+                let peers = subscriptions
+                    .get(&watched)
+                    .map(|subscription| subscription.peers.clone())
+                    .unwrap_or_default();
+                for (key, peer) in peers {
+                    let notification = ResourceUpdatedNotificationParam {
+                        uri: watched.clone(),
+                    };
+                    if let Err(e) = peer.notify_resource_updated(notification).await {
+                        // The session is gone, stop notifying it
+                        debug!("Failed to notify update of {watched}: {e}");
+                        if let Some(mut subscription) = subscriptions.get_mut(&watched) {
+                            subscription.peers.remove(&key);
+                        }
+                    }
+                }
+                let Some(removed) = subscriptions
+                    .remove_if(&watched, |_, subscription| subscription.peers.is_empty())
+                else {
+                    continue;
+                };
+                // No session is left, stop receiving the changes from Neovim
+                if let Some(client) = nvim_clients.get(&connection_id)
+                    && let Err(e) = client.detach_buffer(buffer_id).await
+                {
+                    debug!("Failed to detach buffer {buffer_id}: {e}");
+                }
+                // Dropping the subscription aborts this task
+                drop(removed);
+                break;
+            }
+        });
+
+        match self.resource_subscriptions.entry(uri) {
+            // Subscribed concurrently, the other forwarder serves this peer too
+            Entry::Occupied(mut entry) => {
+                forwarder.abort();
+                entry.get_mut().peers.insert(self.session_id, peer);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(ResourceSubscription {
+                    peers: HashMap::from([(self.session_id, peer)]),
+                    forwarder,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop notifying this session, the buffer is detached once no session is left
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<(), McpError> {
+        if let Some(mut subscription) = self.resource_subscriptions.get_mut(uri) {
+            subscription.peers.remove(&self.session_id);
+        }
+        if self
+            .resource_subscriptions
+            .remove_if(uri, |_, subscription| subscription.peers.is_empty())
+            .is_none()
+        {
+            return Ok(());
+        }
+        if let Ok(ResourceUri::Buffer {
+            connection_id,
+            buffer_id,
+        }) = parse_resource_uri(uri)
+            && let Some(client) = self.nvim_clients.get(&connection_id)
+        {
+            client.detach_buffer(buffer_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Drop the subscriptions to the buffers of a connection, their forwarders
+    /// listen to a client that is gone
+    pub fn drop_connection_subscriptions(&self, connection_id: &str) {
+        self.resource_subscriptions.retain(|uri, _| {
+            !matches!(
+                parse_resource_uri(uri),
+                Ok(ResourceUri::Buffer { connection_id: id, .. }) if id == connection_id
+            )
+        });
+    }
+}
+
+/// Templates of the connection-scoped resources
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    vec![
+        resource_template(
+            "nvim-buffer://{connection_id}/{buffer_id}",
+            "Buffer Text",
+            "Current text of a buffer, including unsaved changes",
+            "text/plain",
+        ),
+        resource_template(
+            "nvim-diagnostics://{connection_id}/buffer/{buffer_id}",
+            "Buffer Diagnostics",
+            "Diagnostic messages for a buffer",
+            "application/json",
+        ),
+        resource_template(
+            "nvim-symbols://{connection_id}/{path}",
+            "Document Symbols",
+            "LSP document symbols of a project-relative or absolute path, \
+             from the configured default LSP client for its filetype",
+            "application/json",
+        ),
+    ]
+}
+
+/// Resource contents holding `value` as pretty-printed JSON
+fn json_contents<T: Serialize>(
+    value: &T,
+    uri: String,
+    what: &str,
+) -> Result<ResourceContents, McpError> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| {
+        McpError::internal_error(
+            format!("Failed to serialize {what}"),
+            Some(json!({"error": e.to_string()})),
+        )
+    })?;
+    Ok(ResourceContents::TextResourceContents {
+        uri,
+        mime_type: Some("application/json".to_string()),
+        text,
+        meta: None,
+    })
+}
+
+impl NeovimMcpServer {
+    async fn dispatch_tool_call(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_tool_policy(&request)?;
+        self.check_argument_paths(&request).await?;
+
+        let ct = context.ct.clone();
+        let tcc = ToolCallContext::new(self, request, context);
+
+        // Dropping the tool call cancels the LSP requests it is waiting for in Neovim
+        tokio::select! {
+            result = self.tool_router.call(tcc) => result,
+            _ = ct.cancelled() => Err(McpError::invalid_request("Request cancelled", None)),
+        }
+    }
+}
 
-// tool_handler macro automatically generates the necessary boilerplate
-#[tool_handler]
 impl ServerHandler for NeovimMcpServer {
     #[instrument(skip(self))]
     fn get_info(&self) -> ServerInfo {
@@ -19,13 +414,80 @@ impl ServerHandler for NeovimMcpServer {
         ServerInfo {
             instructions: Some(include_str!("../../docs/instructions.md").to_string()),
             capabilities: ServerCapabilities::builder()
+                .enable_completions()
                 .enable_tools()
                 .enable_resources()
+                .enable_resources_subscribe()
+                .enable_prompts()
                 .build(),
             ..Default::default()
         }
     }
 
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if !is_mutating_call(&request) {
+            return self.dispatch_tool_call(request, context).await;
+        }
+
+        let mut entry = AuditEntry::new(&request.name, request.arguments.as_ref());
+        entry.target = entry
+            .connection_id
+            .as_deref()
+            .and_then(|id| self.nvim_clients.get(id))
+            .and_then(|client| client.target());
+        let started = Instant::now();
+        let result = self.dispatch_tool_call(request, context).await;
+        self.audit_log
+            .record(entry.finish(&result, started.elapsed()));
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult::with_all_items(prompt_definitions()))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_prompt(
+        &self,
+        GetPromptRequestParam { name, arguments }: GetPromptRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        self.get_prompt_result(&name, arguments.as_ref()).await
+    }
+
+    #[instrument(skip(self))]
+    async fn complete(
+        &self,
+        CompleteRequestParam {
+            r#ref,
+            argument,
+            context,
+        }: CompleteRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        Ok(CompleteResult {
+            completion: self
+                .complete_argument(&r#ref, &argument, context.as_ref())
+                .await,
+        })
+    }
+
     #[instrument(skip(self))]
     async fn list_resources(
         &self,
@@ -38,13 +500,28 @@ impl ServerHandler for NeovimMcpServer {
             raw: RawResource {
                 uri: "nvim-connections://".to_string(),
                 name: "Active Neovim Connections".to_string(),
+                title: None,
                 description: Some("List of active Neovim connections".to_string()),
                 mime_type: Some("application/json".to_string()),
                 size: None,
+                icons: None,
             },
             annotations: None,
         }];
 
+        resources.push(Resource {
+            raw: RawResource {
+                uri: "nvim-audit://".to_string(),
+                name: "Audit Log".to_string(),
+                title: None,
+                description: Some("Tool calls that modified the editor".to_string()),
+                mime_type: Some("application/json".to_string()),
+                size: None,
+                icons: None,
+            },
+            annotations: None,
+        });
+
         // Add connection-specific workspace resources
         for connection_entry in self.nvim_clients.iter() {
             let connection_id = connection_entry.key().clone();
@@ -52,22 +529,69 @@ impl ServerHandler for NeovimMcpServer {
                 raw: RawResource {
                     uri: format!("nvim-diagnostics://{connection_id}/workspace"),
                     name: format!("Workspace Diagnostics ({connection_id})"),
+                    title: None,
                     description: Some(format!(
                         "Diagnostic messages for connection {connection_id}"
                     )),
                     mime_type: Some("application/json".to_string()),
                     size: None,
+                    icons: None,
+                },
+                annotations: None,
+            });
+            resources.push(Resource {
+                raw: RawResource {
+                    uri: format!("nvim-state://{connection_id}"),
+                    name: format!("Editor State ({connection_id})"),
+                    title: None,
+                    description: Some(format!(
+                        "Mode, windows, cursors and layout of connection {connection_id}"
+                    )),
+                    mime_type: Some("application/json".to_string()),
+                    size: None,
+                    icons: None,
                 },
                 annotations: None,
             });
         }
 
+        resources.extend(self.buffer_resources().await);
+
         Ok(ListResourcesResult {
             resources,
             next_cursor: None,
         })
     }
 
+    #[instrument(skip(self, context))]
+    async fn subscribe(
+        &self,
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.subscribe_resource(uri, context.peer).await
+    }
+
+    #[instrument(skip(self))]
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.unsubscribe_resource(&uri).await
+    }
+
+    #[instrument(skip(self))]
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(
+            resource_templates(),
+        ))
+    }
+
     #[instrument(skip(self))]
     async fn read_resource(
         &self,
@@ -76,8 +600,8 @@ impl ServerHandler for NeovimMcpServer {
     ) -> Result<ReadResourceResult, McpError> {
         debug!("Reading resource: {}", uri);
 
-        match uri.as_str() {
-            "nvim-connections://" => {
+        let contents = match parse_resource_uri(&uri)? {
+            ResourceUri::Connections => {
                 let connections: Vec<_> = self
                     .nvim_clients
                     .iter()
@@ -89,87 +613,62 @@ impl ServerHandler for NeovimMcpServer {
                         })
                     })
                     .collect();
-
-                Ok(ReadResourceResult {
-                    contents: vec![ResourceContents::text(
-                        serde_json::to_string_pretty(&connections).map_err(|e| {
-                            McpError::internal_error(
-                                "Failed to serialize connections",
-                                Some(json!({"error": e.to_string()})),
-                            )
-                        })?,
-                        uri,
-                    )],
-                })
+                json_contents(&connections, uri, "connections")?
             }
-            uri if uri.starts_with("nvim-diagnostics://") => {
-                // Parse connection_id from URI pattern using regex
-                let connection_diagnostics_regex = Regex::new(r"nvim-diagnostics://([^/]+)/(.+)")
-                    .map_err(|e| {
-                    McpError::internal_error(
-                        "Failed to compile regex",
-                        Some(json!({"error": e.to_string()})),
-                    )
-                })?;
-
-                if let Some(captures) = connection_diagnostics_regex.captures(uri) {
-                    let connection_id = captures.get(1).unwrap().as_str();
-                    let resource_type = captures.get(2).unwrap().as_str();
-
-                    let client = self.get_connection(connection_id)?;
-
-                    match resource_type {
-                        "workspace" => {
-                            let diagnostics = client.get_workspace_diagnostics().await?;
-                            Ok(ReadResourceResult {
-                                contents: vec![ResourceContents::text(
-                                    serde_json::to_string_pretty(&diagnostics).map_err(|e| {
-                                        McpError::internal_error(
-                                            "Failed to serialize workspace diagnostics",
-                                            Some(json!({"error": e.to_string()})),
-                                        )
-                                    })?,
-                                    uri,
-                                )],
-                            })
-                        }
-                        path if path.starts_with("buffer/") => {
-                            let buffer_id = path
-                                .strip_prefix("buffer/")
-                                .and_then(|s| s.parse::<u64>().ok())
-                                .ok_or_else(|| {
-                                    McpError::invalid_params("Invalid buffer ID", None)
-                                })?;
-
-                            let diagnostics = client.get_buffer_diagnostics(buffer_id).await?;
-                            Ok(ReadResourceResult {
-                                contents: vec![ResourceContents::text(
-                                    serde_json::to_string_pretty(&diagnostics).map_err(|e| {
-                                        McpError::internal_error(
-                                            "Failed to serialize buffer diagnostics",
-                                            Some(json!({"error": e.to_string()})),
-                                        )
-                                    })?,
-                                    uri,
-                                )],
-                            })
-                        }
-                        _ => Err(McpError::resource_not_found(
-                            "resource_not_found",
-                            Some(json!({"uri": uri})),
-                        )),
-                    }
-                } else {
-                    Err(McpError::resource_not_found(
-                        "resource_not_found",
-                        Some(json!({"uri": uri})),
-                    ))
+            ResourceUri::Audit => json_contents(&self.audit_log.entries(), uri, "audit log")?,
+            ResourceUri::WorkspaceDiagnostics { connection_id } => {
+                let client = self.get_connection(&connection_id)?;
+                let diagnostics = client.get_workspace_diagnostics().await?;
+                json_contents(&diagnostics, uri, "workspace diagnostics")?
+            }
+            ResourceUri::BufferDiagnostics {
+                connection_id,
+                buffer_id,
+            } => {
+                let client = self.get_connection(&connection_id)?;
+                let diagnostics = client.get_buffer_diagnostics(buffer_id).await?;
+                json_contents(&diagnostics, uri, "buffer diagnostics")?
+            }
+            ResourceUri::Buffer {
+                connection_id,
+                buffer_id,
+            } => {
+                let client = self.get_connection(&connection_id)?;
+                let document = client
+                    .read_document(&DocumentIdentifier::from_buffer_id(buffer_id))
+                    .await?;
+                ResourceContents::TextResourceContents {
+                    uri,
+                    mime_type: Some(filetype_mime_type(&document.filetype).to_string()),
+                    text: document.text,
+                    meta: None,
                 }
             }
-            _ => Err(McpError::resource_not_found(
-                "resource_not_found",
-                Some(json!({"uri": uri})),
-            )),
-        }
+            ResourceUri::Symbols {
+                connection_id,
+                document,
+            } => {
+                let client = self.get_connection(&connection_id)?;
+                // The path comes from the URI, so it is not covered by the tool policy
+                self.check_document_confined(client.value().as_ref(), &document)
+                    .await?;
+                let lsp_client_name = self
+                    .resolve_lsp_client_name(client.value().as_ref(), &document, None)
+                    .await?;
+                let symbols = client
+                    .lsp_document_symbols(&lsp_client_name, document)
+                    .await?;
+                json_contents(&symbols, uri, "document symbols")?
+            }
+            ResourceUri::State { connection_id } => {
+                let client = self.get_connection(&connection_id)?;
+                let state = client.get_editor_state().await?;
+                json_contents(&state, uri, "editor state")?
+            }
+        };
+
+        Ok(ReadResourceResult {
+            contents: vec![contents],
+        })
     }
 }
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::neovim::DocumentIdentifier;
+    use crate::server::resources::*;
+
+    #[test]
+    fn test_parse_resource_uri() {
+        assert_eq!(
+            parse_resource_uri("nvim-connections://").unwrap(),
+            ResourceUri::Connections
+        );
+        assert_eq!(
+            parse_resource_uri("nvim-diagnostics://abc/workspace").unwrap(),
+            ResourceUri::WorkspaceDiagnostics {
+                connection_id: "abc".to_string()
+            }
+        );
+        assert_eq!(
+            parse_resource_uri("nvim-diagnostics://abc/buffer/3").unwrap(),
+            ResourceUri::BufferDiagnostics {
+                connection_id: "abc".to_string(),
+                buffer_id: 3
+            }
+        );
+        assert_eq!(
+            parse_resource_uri("nvim-buffer://abc/7").unwrap(),
+            ResourceUri::Buffer {
+                connection_id: "abc".to_string(),
+                buffer_id: 7
+            }
+        );
+        assert_eq!(
+            parse_resource_uri("nvim-symbols://abc/src/my%20lib.rs").unwrap(),
+            ResourceUri::Symbols {
+                connection_id: "abc".to_string(),
+                document: DocumentIdentifier::ProjectRelativePath(PathBuf::from("src/my lib.rs"))
+            }
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            parse_resource_uri("nvim-symbols://abc//tmp/main.rs").unwrap(),
+            ResourceUri::Symbols {
+                connection_id: "abc".to_string(),
+                document: DocumentIdentifier::AbsolutePath(PathBuf::from("/tmp/main.rs"))
+            }
+        );
+        assert_eq!(
+            parse_resource_uri("nvim-state://abc").unwrap(),
+            ResourceUri::State {
+                connection_id: "abc".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_resource_uri_errors() {
+        for uri in [
+            "nvim-diagnostics://abc/unknown",
+            "nvim-diagnostics:///workspace",
+            "nvim-buffer://abc/",
+            "nvim-state://",
+            "nvim-state://abc/1",
+            "nvim-unknown://abc/1",
+            "file:///tmp/main.rs",
+        ] {
+            assert!(parse_resource_uri(uri).is_err(), "{uri} should be rejected");
+        }
+        let err = parse_resource_uri("nvim-buffer://abc/first").unwrap_err();
+        assert_eq!(err.message, "Invalid buffer ID");
+    }
+
+    #[test]
+    fn test_resource_templates() {
+        let templates: Vec<String> = resource_templates()
+            .into_iter()
+            .map(|t| t.raw.uri_template)
+            .collect();
+        assert_eq!(
+            templates,
+            vec![
+                "nvim-buffer://{connection_id}/{buffer_id}",
+                "nvim-diagnostics://{connection_id}/buffer/{buffer_id}",
+                "nvim-symbols://{connection_id}/{path}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filetype_mime_type() {
+        assert_eq!(filetype_mime_type("rust"), "text/x-rust");
+        assert_eq!(filetype_mime_type("json"), "application/json");
+        assert_eq!(filetype_mime_type("markdown"), "text/markdown");
+        assert_eq!(filetype_mime_type(""), "text/plain");
+        assert_eq!(filetype_mime_type("unknown"), "text/plain");
+    }
+}
@@ -1,15 +1,20 @@
 use rmcp::{
-    ErrorData as McpError,
-    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    ErrorData as McpError, Peer, RoleServer,
+    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
     model::*,
     schemars, tool, tool_router,
 };
-use tracing::instrument;
+use std::path::PathBuf;
+
+use tracing::{debug, instrument};
 
 use super::core::NeovimMcpServer;
+use super::policy::{CONFIRMABLE_TOOLS, MUTATING_TOOLS, check_exec_command, check_exec_lua};
+use crate::config::{ConfigError, SafetyConfig, ToolsConfig};
 use crate::neovim::{
-    CodeAction, DocumentIdentifier, FormattingOptions, NeovimClient, NeovimClientTrait, Position,
-    PrepareRenameResult, Range, WorkspaceEdit, string_or_struct,
+    CodeAction, ConfirmPreview, DocumentIdentifier, DocumentOutline, FormattingOptions,
+    NeovimClient, NeovimClientTrait, OutlineSource, Position, PrepareRenameResult, QuickfixAction,
+    QuickfixEntry, Range, SearchOptions, WorkspaceEdit, string_or_struct, with_lsp_timeout,
 };
 
 /// Connect to Neovim instance via unix socket or TCP
@@ -17,6 +22,21 @@ use crate::neovim::{
 pub struct ConnectNvimRequest {
     /// target can be a unix socket path or a TCP address
     pub target: String,
+    /// Reject tools that modify the editor on this connection (default: false)
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Spawn a headless Neovim instance owned by the server
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SpawnNvimRequest {
+    /// Working directory for the new Neovim instance (defaults to the server's cwd)
+    pub cwd: Option<PathBuf>,
+    /// Init file passed to Neovim via `-u` (defaults to the user's config, use "NONE" to skip it)
+    pub init_file: Option<PathBuf>,
+    /// Reject tools that modify the editor on this connection (default: false)
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 /// New parameter struct for connection-aware requests
@@ -44,6 +64,15 @@ pub struct ExecuteLuaRequest {
     pub code: String,
 }
 
+/// Ex command execution request
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExecuteCommandRequest {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Ex commands to execute, without the leading `:`; multiple lines run as a script
+    pub command: String,
+}
+
 /// Workspace symbols parameters
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WorkspaceSymbolsParams {
@@ -53,6 +82,9 @@ pub struct WorkspaceSymbolsParams {
     pub lsp_client_name: String,
     /// A query string to filter symbols by. Clients may send an empty string here to request all symbols.
     pub query: String,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Code Actions parameters
@@ -65,8 +97,9 @@ pub struct CodeActionsParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Range start position, line number starts from 0
     pub start_line: u64,
     /// Range start position, character number starts from 0
@@ -75,6 +108,9 @@ pub struct CodeActionsParams {
     pub end_line: u64,
     /// Range end position, character number starts from 0
     pub end_character: u64,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Hover parameters
@@ -87,12 +123,16 @@ pub struct HoverParam {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
     pub character: u64,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Document symbols parameters
@@ -105,8 +145,12 @@ pub struct DocumentSymbolsParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// References parameters
@@ -119,14 +163,18 @@ pub struct ReferencesParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
     pub character: u64,
     /// Include the declaration of the current symbol in the results
     pub include_declaration: bool,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Definition parameters
@@ -139,12 +187,16 @@ pub struct DefinitionParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
     pub character: u64,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Type definition parameters
@@ -157,12 +209,16 @@ pub struct TypeDefinitionParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
     pub character: u64,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Implementation parameters
@@ -175,12 +231,16 @@ pub struct ImplementationParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
     pub character: u64,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Declaration parameters
@@ -193,12 +253,16 @@ pub struct DeclarationParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
     pub character: u64,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Code action resolve parameters
@@ -213,6 +277,9 @@ pub struct ResolveCodeActionParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub code_action: CodeAction,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Apply workspace edit parameters
@@ -243,6 +310,125 @@ pub struct NavigateToFileParams {
     pub line: Option<u64>,
 }
 
+/// Cursor context parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CursorContextParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Number of lines to include above and below the cursor (default: 10)
+    #[serde(default = "default_context_lines")]
+    pub context_lines: u64,
+    /// Lsp client name for the enclosing symbol (defaults to the configured client for the buffer's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+fn default_context_lines() -> u64 {
+    10
+}
+
+/// Quickfix list parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct QuickfixListParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Window whose location list to use, 0 for the current window (defaults to the quickfix list)
+    #[serde(default)]
+    pub window_id: Option<u64>,
+}
+
+/// Set quickfix list parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetQuickfixListParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Window whose location list to set, 0 for the current window (defaults to the quickfix list)
+    #[serde(default)]
+    pub window_id: Option<u64>,
+    /// Title of the list, shown in the quickfix window
+    pub title: String,
+    /// LSP locations, diagnostics, or document ranges with 0-based LSP positions
+    pub items: Vec<QuickfixEntry>,
+    /// Create a new list, replace the current one or append to it (default: new)
+    #[serde(default)]
+    pub action: QuickfixAction,
+    /// Whether to open the quickfix or location list window (default: false)
+    #[serde(default)]
+    pub open: bool,
+}
+
+/// Workspace search parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SearchWorkspaceParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    #[serde(flatten)]
+    pub options: SearchOptions,
+}
+
+fn default_max_files() -> u64 {
+    50
+}
+
+/// Find files parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindFilesParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Fuzzy query such as `srvtools`, or a glob such as `**/*.rs` when it
+    /// contains `*`, `?`, `[` or `{`
+    pub query: String,
+    /// Maximum number of files to return (default: 50)
+    #[serde(default = "default_max_files")]
+    pub max_results: u64,
+}
+
+fn default_max_captures() -> u64 {
+    200
+}
+
+/// Tree-sitter query parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TreesitterQueryParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Universal document identifier
+    // Supports both string and struct deserialization.
+    // Compatible with Claude Code when using subscription.
+    #[serde(deserialize_with = "string_or_struct")]
+    pub document: DocumentIdentifier,
+    /// Tree-sitter query in the document's language, e.g. `(function_item name: (identifier) @name)`
+    pub query: String,
+    /// Only return captures overlapping this line or later, line number starts from 0
+    #[serde(default)]
+    pub start_line: Option<u64>,
+    /// Only return captures overlapping this line or earlier, line number starts from 0
+    #[serde(default)]
+    pub end_line: Option<u64>,
+    /// Maximum number of captures to return (default: 200)
+    #[serde(default = "default_max_captures")]
+    pub max_results: u64,
+}
+
+/// Tree-sitter node parameters
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TreesitterNodeAtParams {
+    /// Unique identifier for the target Neovim instance
+    pub connection_id: String,
+    /// Universal document identifier
+    // Supports both string and struct deserialization.
+    // Compatible with Claude Code when using subscription.
+    #[serde(deserialize_with = "string_or_struct")]
+    pub document: DocumentIdentifier,
+    /// Node position, line number starts from 0
+    pub line: u64,
+    /// Node position, character number starts from 0
+    pub character: u64,
+}
+
 /// Rename parameters
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct RenameParams {
@@ -253,8 +439,9 @@ pub struct RenameParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Symbol position, line number starts from 0
     pub line: u64,
     /// Symbol position, character number starts from 0
@@ -264,6 +451,9 @@ pub struct RenameParams {
     /// Whether to run prepare rename first to validate the position (default: true)
     #[serde(default = "default_prepare_first")]
     pub prepare_first: bool,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 fn default_prepare_first() -> bool {
@@ -280,13 +470,17 @@ pub struct DocumentFormattingParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// The formatting options
     pub options: FormattingOptions,
     /// Whether to apply the text edits automatically (default: false)
     #[serde(default)]
     pub apply_edits: bool,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Document range formatting parameters
@@ -299,8 +493,9 @@ pub struct DocumentRangeFormattingParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Range start position, line number starts from 0
     pub start_line: u64,
     /// Range start position, character number starts from 0
@@ -314,6 +509,9 @@ pub struct DocumentRangeFormattingParams {
     /// Whether to apply the text edits automatically (default: false)
     #[serde(default)]
     pub apply_edits: bool,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 /// Organize imports parameters
@@ -326,11 +524,15 @@ pub struct LspOrganizeImportsParams {
     // Compatible with Claude Code when using subscription.
     #[serde(deserialize_with = "string_or_struct")]
     pub document: DocumentIdentifier,
-    /// Lsp client name
-    pub lsp_client_name: String,
+    /// Lsp client name (defaults to the configured client for the document's filetype)
+    #[serde(default)]
+    pub lsp_client_name: Option<String>,
     /// Whether to apply the text edits automatically (default: true)
     #[serde(default = "default_true")]
     pub apply_edits: bool,
+    /// Timeout for the LSP requests in milliseconds, overrides the configured timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
 
 fn default_true() -> bool {
@@ -339,7 +541,10 @@ fn default_true() -> bool {
 
 #[tool_router]
 impl NeovimMcpServer {
-    #[tool(description = "Get available Neovim targets")]
+    #[tool(
+        description = "Get available Neovim targets",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn get_targets(&self) -> Result<CallToolResult, McpError> {
         let targets = super::core::find_get_all_targets(&self.socket_path);
@@ -353,11 +558,22 @@ impl NeovimMcpServer {
         Ok(CallToolResult::success(vec![Content::json(targets)?]))
     }
 
-    #[tool(description = "Connect to Neovim instance via unix socket(pipe)")]
+    #[tool(
+        description = "Connect to Neovim instance via unix socket(pipe)",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
     #[instrument(skip(self))]
     pub async fn connect(
         &self,
-        Parameters(ConnectNvimRequest { target: path }): Parameters<ConnectNvimRequest>,
+        Parameters(ConnectNvimRequest {
+            target: path,
+            read_only,
+        }): Parameters<ConnectNvimRequest>,
     ) -> Result<CallToolResult, McpError> {
         let connection_id = self.generate_shorter_connection_id(&path);
 
@@ -365,13 +581,20 @@ impl NeovimMcpServer {
         if let Some(mut old_client) = self.nvim_clients.get_mut(&connection_id) {
             let _ = old_client.disconnect().await;
         }
+        self.drop_connection_subscriptions(&connection_id);
 
         let mut client = NeovimClient::new();
+        client.set_lsp_config(self.lsp_config.clone());
         client.connect_path(&path).await?;
-        client.setup_diagnostics_changed_autocmd().await?;
+        if let Err(e) = client.setup_diagnostics_changed_autocmd().await {
+            // Kills a spawned Neovim and removes its socket
+            let _ = client.disconnect().await;
+            return Err(e.into());
+        }
 
         self.nvim_clients
             .insert(connection_id.clone(), Box::new(client));
+        self.mark_connection_read_only(&connection_id, read_only);
 
         Ok(CallToolResult::success(vec![Content::json(
             serde_json::json!({
@@ -382,11 +605,22 @@ impl NeovimMcpServer {
         )?]))
     }
 
-    #[tool(description = "Connect to Neovim instance via TCP")]
+    #[tool(
+        description = "Connect to Neovim instance via TCP",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = true
+        )
+    )]
     #[instrument(skip(self))]
     pub async fn connect_tcp(
         &self,
-        Parameters(ConnectNvimRequest { target: address }): Parameters<ConnectNvimRequest>,
+        Parameters(ConnectNvimRequest {
+            target: address,
+            read_only,
+        }): Parameters<ConnectNvimRequest>,
     ) -> Result<CallToolResult, McpError> {
         let connection_id = self.generate_shorter_connection_id(&address);
 
@@ -394,13 +628,20 @@ impl NeovimMcpServer {
         if let Some(mut old_client) = self.nvim_clients.get_mut(&connection_id) {
             let _ = old_client.disconnect().await;
         }
+        self.drop_connection_subscriptions(&connection_id);
 
         let mut client = NeovimClient::new();
+        client.set_lsp_config(self.lsp_config.clone());
         client.connect_tcp(&address).await?;
-        client.setup_diagnostics_changed_autocmd().await?;
+        if let Err(e) = client.setup_diagnostics_changed_autocmd().await {
+            // Kills a spawned Neovim and removes its socket
+            let _ = client.disconnect().await;
+            return Err(e.into());
+        }
 
         self.nvim_clients
             .insert(connection_id.clone(), Box::new(client));
+        self.mark_connection_read_only(&connection_id, read_only);
 
         Ok(CallToolResult::success(vec![Content::json(
             serde_json::json!({
@@ -411,7 +652,75 @@ impl NeovimMcpServer {
         )?]))
     }
 
-    #[tool(description = "Disconnect from Neovim instance")]
+    #[tool(
+        description = "Spawn a headless Neovim instance owned by the server and connect to it",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    #[instrument(skip(self))]
+    pub async fn spawn_nvim(
+        &self,
+        Parameters(SpawnNvimRequest {
+            cwd,
+            init_file,
+            read_only,
+        }): Parameters<SpawnNvimRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let cwd = match cwd {
+            Some(cwd) => cwd,
+            None => std::env::current_dir().map_err(|e| {
+                McpError::internal_error(format!("Failed to get current directory: {e}"), None)
+            })?,
+        };
+        if !cwd.is_dir() {
+            return Err(McpError::invalid_params(
+                format!("Working directory does not exist: {}", cwd.display()),
+                None,
+            ));
+        }
+        self.check_spawn_arguments(&cwd, init_file.as_deref())?;
+
+        let target = super::core::generate_spawn_target(&self.socket_path);
+        let connection_id = self.generate_shorter_connection_id(&target);
+
+        let mut client = NeovimClient::new();
+        client.set_lsp_config(self.lsp_config.clone());
+        let pid = client
+            .spawn_path(&target, &cwd, init_file.as_deref())
+            .await?;
+        if let Err(e) = client.setup_diagnostics_changed_autocmd().await {
+            // Kills a spawned Neovim and removes its socket
+            let _ = client.disconnect().await;
+            return Err(e.into());
+        }
+
+        self.nvim_clients
+            .insert(connection_id.clone(), Box::new(client));
+        self.mark_connection_read_only(&connection_id, read_only);
+
+        Ok(CallToolResult::success(vec![Content::json(
+            serde_json::json!({
+                "connection_id": connection_id,
+                "target": target,
+                "pid": pid,
+                "message": format!("Spawned headless Neovim in {}", cwd.display())
+            }),
+        )?]))
+    }
+
+    #[tool(
+        description = "Disconnect from Neovim instance",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
     #[instrument(skip(self))]
     pub async fn disconnect(
         &self,
@@ -424,6 +733,7 @@ impl NeovimMcpServer {
         };
 
         // Remove the connection from the map
+        self.drop_connection_subscriptions(&connection_id);
         if let Some((_, mut client)) = self.nvim_clients.remove(&connection_id) {
             if let Err(e) = client.disconnect().await {
                 return Err(McpError::internal_error(
@@ -446,7 +756,10 @@ impl NeovimMcpServer {
         }
     }
 
-    #[tool(description = "List all open buffers in Neovim")]
+    #[tool(
+        description = "List all open buffers in Neovim",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn list_buffers(
         &self,
@@ -457,17 +770,121 @@ impl NeovimMcpServer {
         Ok(CallToolResult::success(vec![Content::json(buffers)?]))
     }
 
-    #[tool(description = "Execute Lua code in Neovim")]
+    #[tool(
+        description = "Get the editor state: mode, current buffer, window and tab, cursor and visible lines of every window, window layout, working directory and alternate buffer",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
+    pub async fn get_editor_state(
+        &self,
+        Parameters(ConnectionRequest { connection_id }): Parameters<ConnectionRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let state = client.get_editor_state().await?;
+        Ok(CallToolResult::success(vec![Content::json(state)?]))
+    }
+
+    #[tool(
+        description = "Get the current or last visual selection (characterwise, linewise or blockwise) of the current buffer with its text, range and document",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn get_selection(
+        &self,
+        Parameters(ConnectionRequest { connection_id }): Parameters<ConnectionRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let selection = client.get_selection().await?;
+        Ok(CallToolResult::success(vec![Content::json(selection)?]))
+    }
+
+    #[tool(
+        description = "Get the cursor position, the lines around it and the innermost document symbol containing it",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn get_cursor_context(
+        &self,
+        Parameters(CursorContextParams {
+            connection_id,
+            context_lines,
+            lsp_client_name,
+            timeout_ms,
+        }): Parameters<CursorContextParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let mut context = client.get_cursor_context(context_lines).await?;
+
+        // The enclosing symbol is best effort, buffers without an LSP client have none
+        match self
+            .resolve_lsp_client_name(client.value().as_ref(), &context.document, lsp_client_name)
+            .await
+        {
+            Ok(lsp_client_name) => match with_lsp_timeout(
+                timeout_ms,
+                client.lsp_document_symbols(&lsp_client_name, context.document.clone()),
+            )
+            .await
+            {
+                Ok(symbols) => {
+                    context.symbol = symbols.and_then(|s| s.enclosing(&context.cursor.position))
+                }
+                Err(e) => debug!("No document symbols from {lsp_client_name}: {e}"),
+            },
+            Err(e) => debug!("No LSP client for the enclosing symbol: {}", e.message),
+        }
+        Ok(CallToolResult::success(vec![Content::json(context)?]))
+    }
+
+    #[tool(
+        description = "Execute Lua code in Neovim",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false,
+            open_world_hint = true
+        )
+    )]
+    #[instrument(skip(self, peer))]
     pub async fn exec_lua(
         &self,
         Parameters(ExecuteLuaRequest {
             connection_id,
             code,
         }): Parameters<ExecuteLuaRequest>,
+        peer: Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let result = client.execute_lua(&code).await?;
+        let policy = &self.exec_lua_policy;
+        if policy.is_enabled() {
+            check_exec_lua(policy, &code)?;
+        }
+        self.confirm_change(
+            "exec_lua",
+            client.value().as_ref(),
+            &peer,
+            "Execute Lua code",
+            ConfirmPreview::Code {
+                filetype: "lua".to_string(),
+                code: code.clone(),
+            },
+        )
+        .await?;
+        let result = if policy.is_enabled() {
+            if policy.is_sandboxed() {
+                client
+                    .execute_lua_sandboxed(
+                        &code,
+                        policy.allowed_api.as_deref(),
+                        &policy.denied_patterns(),
+                    )
+                    .await?
+            } else {
+                client.execute_lua(&code).await?
+            }
+        } else {
+            client.execute_lua(&code).await?
+        };
         Ok(CallToolResult::success(vec![Content::json(
             serde_json::json!({
                 "result": format!("{:?}", result)
@@ -475,7 +892,49 @@ impl NeovimMcpServer {
         )?]))
     }
 
-    #[tool(description = "Get buffer's diagnostics")]
+    #[tool(
+        description = "Execute Ex commands in Neovim and return their output, e.g. `messages` or `checkhealth`",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false,
+            open_world_hint = true
+        )
+    )]
+    #[instrument(skip(self, peer))]
+    pub async fn exec_command(
+        &self,
+        Parameters(ExecuteCommandRequest {
+            connection_id,
+            command,
+        }): Parameters<ExecuteCommandRequest>,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        if self.exec_lua_policy.is_enabled() {
+            check_exec_command(&self.exec_lua_policy, &command)?;
+        }
+        self.confirm_change(
+            "exec_command",
+            client.value().as_ref(),
+            &peer,
+            "Execute Ex command",
+            ConfirmPreview::Code {
+                filetype: "vim".to_string(),
+                code: command.clone(),
+            },
+        )
+        .await?;
+        let output = client.exec_command(&command).await?;
+        Ok(CallToolResult::success(vec![Content::json(
+            serde_json::json!({ "output": output }),
+        )?]))
+    }
+
+    #[tool(
+        description = "Get buffer's diagnostics",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn buffer_diagnostics(
         &self,
@@ -486,7 +945,10 @@ impl NeovimMcpServer {
         Ok(CallToolResult::success(vec![Content::json(diagnostics)?]))
     }
 
-    #[tool(description = "Get workspace's lsp clients")]
+    #[tool(
+        description = "Get workspace's lsp clients",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_clients(
         &self,
@@ -497,7 +959,10 @@ impl NeovimMcpServer {
         Ok(CallToolResult::success(vec![Content::json(lsp_clients)?]))
     }
 
-    #[tool(description = "Search workspace symbols by query")]
+    #[tool(
+        description = "Search workspace symbols by query",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_workspace_symbols(
         &self,
@@ -505,16 +970,22 @@ impl NeovimMcpServer {
             connection_id,
             lsp_client_name,
             query,
+            timeout_ms,
         }): Parameters<WorkspaceSymbolsParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let symbols = client
-            .lsp_workspace_symbols(&lsp_client_name, &query)
-            .await?;
+        let symbols = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_workspace_symbols(&lsp_client_name, &query),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(symbols)?]))
     }
 
-    #[tool(description = "Get LSP code actions")]
+    #[tool(
+        description = "Get LSP code actions",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_code_actions(
         &self,
@@ -526,9 +997,13 @@ impl NeovimMcpServer {
             start_character,
             end_line,
             end_character,
+            timeout_ms,
         }): Parameters<CodeActionsParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
+            .await?;
         let start = Position {
             line: start_line,
             character: start_character,
@@ -539,13 +1014,18 @@ impl NeovimMcpServer {
         };
         let range = Range { start, end };
 
-        let code_actions = client
-            .lsp_get_code_actions(&lsp_client_name, document, range)
-            .await?;
+        let code_actions = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_get_code_actions(&lsp_client_name, document, range),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(code_actions)?]))
     }
 
-    #[tool(description = "Get LSP hover information")]
+    #[tool(
+        description = "Get LSP hover information",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_hover(
         &self,
@@ -555,17 +1035,26 @@ impl NeovimMcpServer {
             lsp_client_name,
             line,
             character,
+            timeout_ms,
         }): Parameters<HoverParam>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let position = Position { line, character };
-        let hover = client
-            .lsp_hover(&lsp_client_name, document, position)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let position = Position { line, character };
+        let hover = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_hover(&lsp_client_name, document, position),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(hover)?]))
     }
 
-    #[tool(description = "Get document symbols")]
+    #[tool(
+        description = "Get document symbols",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_document_symbols(
         &self,
@@ -573,16 +1062,25 @@ impl NeovimMcpServer {
             connection_id,
             document,
             lsp_client_name,
+            timeout_ms,
         }): Parameters<DocumentSymbolsParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let symbols = client
-            .lsp_document_symbols(&lsp_client_name, document)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let symbols = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_document_symbols(&lsp_client_name, document),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(symbols)?]))
     }
 
-    #[tool(description = "Get LSP references")]
+    #[tool(
+        description = "Get LSP references",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_references(
         &self,
@@ -593,17 +1091,26 @@ impl NeovimMcpServer {
             line,
             character,
             include_declaration,
+            timeout_ms,
         }): Parameters<ReferencesParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let position = Position { line, character };
-        let references = client
-            .lsp_references(&lsp_client_name, document, position, include_declaration)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let position = Position { line, character };
+        let references = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_references(&lsp_client_name, document, position, include_declaration),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(references)?]))
     }
 
-    #[tool(description = "Get LSP definition")]
+    #[tool(
+        description = "Get LSP definition",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_definition(
         &self,
@@ -613,17 +1120,26 @@ impl NeovimMcpServer {
             lsp_client_name,
             line,
             character,
+            timeout_ms,
         }): Parameters<DefinitionParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let position = Position { line, character };
-        let definition = client
-            .lsp_definition(&lsp_client_name, document, position)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let position = Position { line, character };
+        let definition = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_definition(&lsp_client_name, document, position),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(definition)?]))
     }
 
-    #[tool(description = "Get LSP type definition")]
+    #[tool(
+        description = "Get LSP type definition",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_type_definition(
         &self,
@@ -633,19 +1149,28 @@ impl NeovimMcpServer {
             lsp_client_name,
             line,
             character,
+            timeout_ms,
         }): Parameters<TypeDefinitionParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let position = Position { line, character };
-        let type_definition = client
-            .lsp_type_definition(&lsp_client_name, document, position)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let position = Position { line, character };
+        let type_definition = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_type_definition(&lsp_client_name, document, position),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(
             type_definition,
         )?]))
     }
 
-    #[tool(description = "Get LSP implementation")]
+    #[tool(
+        description = "Get LSP implementation",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_implementations(
         &self,
@@ -655,19 +1180,28 @@ impl NeovimMcpServer {
             lsp_client_name,
             line,
             character,
+            timeout_ms,
         }): Parameters<ImplementationParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let position = Position { line, character };
-        let implementation = client
-            .lsp_implementation(&lsp_client_name, document, position)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let position = Position { line, character };
+        let implementation = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_implementation(&lsp_client_name, document, position),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(
             implementation,
         )?]))
     }
 
-    #[tool(description = "Get LSP declaration")]
+    #[tool(
+        description = "Get LSP declaration",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_declaration(
         &self,
@@ -677,17 +1211,26 @@ impl NeovimMcpServer {
             lsp_client_name,
             line,
             character,
+            timeout_ms,
         }): Parameters<DeclarationParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let position = Position { line, character };
-        let declaration = client
-            .lsp_declaration(&lsp_client_name, document, position)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let position = Position { line, character };
+        let declaration = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_declaration(&lsp_client_name, document, position),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(declaration)?]))
     }
 
-    #[tool(description = "Resolve a code action that may have incomplete data")]
+    #[tool(
+        description = "Resolve a code action that may have incomplete data",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_resolve_code_action(
         &self,
@@ -695,19 +1238,30 @@ impl NeovimMcpServer {
             connection_id,
             lsp_client_name,
             code_action,
+            timeout_ms,
         }): Parameters<ResolveCodeActionParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let resolved_action = client
-            .lsp_resolve_code_action(&lsp_client_name, code_action)
-            .await?;
+        let resolved_action = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_resolve_code_action(&lsp_client_name, code_action),
+        )
+        .await?;
         Ok(CallToolResult::success(vec![Content::json(
             resolved_action,
         )?]))
     }
 
-    #[tool(description = "Apply a workspace edit using the LSP workspace/applyEdit method")]
-    #[instrument(skip(self))]
+    #[tool(
+        description = "Apply a workspace edit using the LSP workspace/applyEdit method",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    #[instrument(skip(self, peer))]
     pub async fn lsp_apply_edit(
         &self,
         Parameters(ApplyWorkspaceEditParams {
@@ -715,16 +1269,36 @@ impl NeovimMcpServer {
             lsp_client_name,
             workspace_edit,
         }): Parameters<ApplyWorkspaceEditParams>,
+        peer: Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
+        self.confirm_change(
+            "lsp_apply_edit",
+            client.value().as_ref(),
+            &peer,
+            "Apply workspace edit",
+            ConfirmPreview::WorkspaceEdit {
+                client_name: lsp_client_name.clone(),
+                workspace_edit: workspace_edit.clone(),
+            },
+        )
+        .await?;
         client
             .lsp_apply_workspace_edit(&lsp_client_name, workspace_edit)
             .await?;
         Ok(CallToolResult::success(vec![Content::text("success")]))
     }
 
-    #[tool(description = "Rename symbol across workspace using LSP with optional validation")]
-    #[instrument(skip(self))]
+    #[tool(
+        description = "Rename symbol across workspace using LSP with optional validation",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    #[instrument(skip(self, peer))]
     pub async fn lsp_rename(
         &self,
         Parameters(RenameParams {
@@ -735,16 +1309,23 @@ impl NeovimMcpServer {
             character,
             new_name,
             prepare_first,
+            timeout_ms,
         }): Parameters<RenameParams>,
+        peer: Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
+            .await?;
         let position = Position { line, character };
 
         // Optionally run prepare rename first to validate the position
         if prepare_first {
-            match client
-                .lsp_prepare_rename(&lsp_client_name, document.clone(), position.clone())
-                .await
+            match with_lsp_timeout(
+                timeout_ms,
+                client.lsp_prepare_rename(&lsp_client_name, document.clone(), position.clone()),
+            )
+            .await
             {
                 Ok(Some(prepare_result)) => {
                     // Prepare rename was successful, we can proceed
@@ -777,18 +1358,34 @@ impl NeovimMcpServer {
         }
 
         // Proceed with the actual rename
-        let workspace_edit = client
-            .lsp_rename(&lsp_client_name, document, position, &new_name)
-            .await?;
+        let workspace_edit = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_rename(&lsp_client_name, document, position, &new_name),
+        )
+        .await?;
 
         if let Some(edit) = workspace_edit {
+            self.check_workspace_edit_confined(client.value().as_ref(), &edit)
+                .await?;
+            self.confirm_change(
+                "lsp_rename",
+                client.value().as_ref(),
+                &peer,
+                &format!("Rename to `{new_name}`"),
+                ConfirmPreview::WorkspaceEdit {
+                    client_name: lsp_client_name.clone(),
+                    workspace_edit: edit.clone(),
+                },
+            )
+            .await?;
             // Apply the workspace edit automatically
             client
-                .lsp_apply_workspace_edit(&lsp_client_name, edit)
+                .lsp_apply_workspace_edit(&lsp_client_name, edit.clone())
                 .await?;
-            Ok(CallToolResult::success(vec![Content::text(
-                "Rename completed successfully",
-            )]))
+            Ok(CallToolResult {
+                structured_content: Some(serde_json::json!({ "workspace_edit": edit })),
+                ..CallToolResult::success(vec![Content::text("Rename completed successfully")])
+            })
         } else {
             Err(McpError::invalid_request(
                 "Rename operation is not valid at this position".to_string(),
@@ -797,7 +1394,15 @@ impl NeovimMcpServer {
         }
     }
 
-    #[tool(description = "Format entire document using LSP with optional auto-apply")]
+    #[tool(
+        description = "Format entire document using LSP with optional auto-apply",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
     #[instrument(skip(self))]
     pub async fn lsp_formatting(
         &self,
@@ -807,12 +1412,18 @@ impl NeovimMcpServer {
             lsp_client_name,
             options,
             apply_edits,
+            timeout_ms,
         }): Parameters<DocumentFormattingParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
-        let text_edits = client
-            .lsp_formatting(&lsp_client_name, document.clone(), options)
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
             .await?;
+        let text_edits = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_formatting(&lsp_client_name, document.clone(), options),
+        )
+        .await?;
 
         if apply_edits {
             // Apply the text edits automatically
@@ -829,7 +1440,13 @@ impl NeovimMcpServer {
     }
 
     #[tool(
-        description = "Format a specific range in a document using LSP with optional auto-apply"
+        description = "Format a specific range in a document using LSP with optional auto-apply",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
     )]
     #[instrument(skip(self))]
     pub async fn lsp_range_formatting(
@@ -844,9 +1461,13 @@ impl NeovimMcpServer {
             end_character,
             options,
             apply_edits,
+            timeout_ms,
         }): Parameters<DocumentRangeFormattingParams>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
+            .await?;
         let start = Position {
             line: start_line,
             character: start_character,
@@ -857,9 +1478,11 @@ impl NeovimMcpServer {
         };
         let range = Range { start, end };
 
-        let text_edits = client
-            .lsp_range_formatting(&lsp_client_name, document.clone(), range, options)
-            .await?;
+        let text_edits = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_range_formatting(&lsp_client_name, document.clone(), range, options),
+        )
+        .await?;
 
         if apply_edits {
             // Apply the text edits automatically
@@ -875,8 +1498,16 @@ impl NeovimMcpServer {
         }
     }
 
-    #[tool(description = "Sort and organize imports")]
-    #[instrument(skip(self))]
+    #[tool(
+        description = "Sort and organize imports",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
+    )]
+    #[instrument(skip(self, peer))]
     pub async fn lsp_organize_imports(
         &self,
         Parameters(LspOrganizeImportsParams {
@@ -884,14 +1515,21 @@ impl NeovimMcpServer {
             document,
             lsp_client_name,
             apply_edits,
+            timeout_ms,
         }): Parameters<LspOrganizeImportsParams>,
+        peer: Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let client = self.get_connection(&connection_id)?;
+        let lsp_client_name = self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
+            .await?;
 
         // Get organize imports code actions for the entire document
-        let code_actions = client
-            .lsp_get_organize_imports_actions(&lsp_client_name, document)
-            .await?;
+        let code_actions = with_lsp_timeout(
+            timeout_ms,
+            client.lsp_get_organize_imports_actions(&lsp_client_name, document),
+        )
+        .await?;
 
         if code_actions.is_empty() {
             return Ok(CallToolResult::success(vec![Content::text(
@@ -911,19 +1549,35 @@ impl NeovimMcpServer {
         let resolved_action = if action.has_edit() {
             action
         } else {
-            client
-                .lsp_resolve_code_action(&lsp_client_name, action)
-                .await?
+            with_lsp_timeout(
+                timeout_ms,
+                client.lsp_resolve_code_action(&lsp_client_name, action),
+            )
+            .await?
         };
 
         // Apply the workspace edit
         if let Some(edit) = resolved_action.edit() {
+            self.check_workspace_edit_confined(client.value().as_ref(), edit)
+                .await?;
+            self.confirm_change(
+                "lsp_organize_imports",
+                client.value().as_ref(),
+                &peer,
+                "Organize imports",
+                ConfirmPreview::WorkspaceEdit {
+                    client_name: lsp_client_name.clone(),
+                    workspace_edit: edit.clone(),
+                },
+            )
+            .await?;
             client
                 .lsp_apply_workspace_edit(&lsp_client_name, edit.clone())
                 .await?;
-            Ok(CallToolResult::success(vec![Content::text(
-                "Imports organized successfully",
-            )]))
+            Ok(CallToolResult {
+                structured_content: Some(serde_json::json!({ "workspace_edit": edit })),
+                ..CallToolResult::success(vec![Content::text("Imports organized successfully")])
+            })
         } else {
             Err(McpError::invalid_request(
                 "Organize imports action does not contain workspace edit".to_string(),
@@ -933,7 +1587,183 @@ impl NeovimMcpServer {
     }
 
     #[tool(
-        description = "Navigate to file and jump to line with universal document identification"
+        description = "Get the quickfix list, or the location list of a window, with items resolved to path, LSP range, text and type",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn get_quickfix_list(
+        &self,
+        Parameters(QuickfixListParams {
+            connection_id,
+            window_id,
+        }): Parameters<QuickfixListParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let list = client.get_quickfix_list(window_id).await?;
+        Ok(CallToolResult::success(vec![Content::json(list)?]))
+    }
+
+    #[tool(
+        description = "Populate the quickfix list, or the location list of a window, with LSP locations (e.g. from lsp_references), diagnostics or document ranges, so the user can step through them with :cnext",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = false,
+            open_world_hint = false
+        )
+    )]
+    #[instrument(skip(self, items))]
+    pub async fn set_quickfix_list(
+        &self,
+        Parameters(SetQuickfixListParams {
+            connection_id,
+            window_id,
+            title,
+            items,
+            action,
+            open,
+        }): Parameters<SetQuickfixListParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        self.check_quickfix_items_confined(client.value().as_ref(), &items)
+            .await?;
+        client
+            .set_quickfix_list(window_id, &title, &items, action, open)
+            .await?;
+        let list = client.get_quickfix_list(window_id).await?;
+        Ok(CallToolResult::success(vec![Content::json(list)?]))
+    }
+
+    #[tool(
+        description = "Search the files of Neovim's working directory with ripgrep when 'grepprg' uses rg, otherwise :vimgrep. Modified buffers are searched instead of their files. Supports regular expressions or fixed strings, include/exclude globs and a result cap; matches are LSP locations plus the line text",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn search_workspace(
+        &self,
+        Parameters(SearchWorkspaceParams {
+            connection_id,
+            options,
+        }): Parameters<SearchWorkspaceParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let result = client.search_workspace(&options).await?;
+        Ok(CallToolResult::success(vec![Content::json(result)?]))
+    }
+
+    #[tool(
+        description = "Find files under the project root (git root, or working directory) by fuzzy query or glob, skipping files ignored by git. Returns paths relative to the working directory, ready to use as project_relative_path document identifiers",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn find_files(
+        &self,
+        Parameters(FindFilesParams {
+            connection_id,
+            query,
+            max_results,
+        }): Parameters<FindFilesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let found = client.find_files(&query, max_results).await?;
+        Ok(CallToolResult::success(vec![Content::json(found)?]))
+    }
+
+    #[tool(
+        description = "Run a tree-sitter query against a document, its buffer when loaded or the file on disk, without needing a language server. Returns the captures in document order with capture name, node type, range and text",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn treesitter_query(
+        &self,
+        Parameters(TreesitterQueryParams {
+            connection_id,
+            document,
+            query,
+            start_line,
+            end_line,
+            max_results,
+        }): Parameters<TreesitterQueryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let captures = client
+            .treesitter_query(&document, &query, start_line, end_line, max_results)
+            .await?;
+        Ok(CallToolResult::success(vec![Content::json(captures)?]))
+    }
+
+    #[tool(
+        description = "Get the tree-sitter syntax node at a position in a document and its ancestors up to the root, innermost first, with node type, parent field, range and text",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn treesitter_node_at(
+        &self,
+        Parameters(TreesitterNodeAtParams {
+            connection_id,
+            document,
+            line,
+            character,
+        }): Parameters<TreesitterNodeAtParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+        let position = Position { line, character };
+        let ancestry = client.treesitter_node_at(&document, &position).await?;
+        Ok(CallToolResult::success(vec![Content::json(ancestry)?]))
+    }
+
+    #[tool(
+        description = "Get the outline of a document: LSP document symbols when a language server provides them, otherwise DocumentSymbol-shaped symbols built from the tree-sitter tags or locals query. The source field tells which one was used",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    #[instrument(skip(self))]
+    pub async fn document_outline(
+        &self,
+        Parameters(DocumentSymbolsParams {
+            connection_id,
+            document,
+            lsp_client_name,
+            timeout_ms,
+        }): Parameters<DocumentSymbolsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = self.get_connection(&connection_id)?;
+
+        // Fall back to tree-sitter for buffers without an LSP client
+        match self
+            .resolve_lsp_client_name(client.value().as_ref(), &document, lsp_client_name)
+            .await
+        {
+            Ok(lsp_client_name) => match with_lsp_timeout(
+                timeout_ms,
+                client.lsp_document_symbols(&lsp_client_name, document.clone()),
+            )
+            .await
+            {
+                Ok(Some(symbols)) => {
+                    let outline = DocumentOutline {
+                        source: OutlineSource::Lsp,
+                        provider: lsp_client_name,
+                        symbols,
+                    };
+                    return Ok(CallToolResult::success(vec![Content::json(outline)?]));
+                }
+                Ok(None) => debug!("No document symbols from {lsp_client_name}"),
+                Err(e) => debug!("No document symbols from {lsp_client_name}: {e}"),
+            },
+            Err(e) => debug!("No LSP client for the document outline: {}", e.message),
+        }
+        let outline = client.treesitter_outline(&document).await?;
+        Ok(CallToolResult::success(vec![Content::json(outline)?]))
+    }
+
+    #[tool(
+        description = "Navigate to file and jump to line with universal document identification",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = false,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
     )]
     #[instrument(skip(self))]
     pub async fn navigate_to_file(
@@ -955,3 +1785,69 @@ impl NeovimMcpServer {
 pub fn build_tool_router() -> ToolRouter<NeovimMcpServer> {
     NeovimMcpServer::tool_router()
 }
+
+/// Build tool router for NeovimMcpServer, leaving out tools that are not enabled
+/// or disabled by name or glob pattern
+pub fn build_tool_router_with_config(
+    tools: &ToolsConfig,
+    safety: &SafetyConfig,
+) -> Result<ToolRouter<NeovimMcpServer>, ConfigError> {
+    let mut router = build_tool_router();
+    let names: Vec<String> = router.map.keys().map(|name| name.to_string()).collect();
+
+    if let Some(enabled) = &tools.enabled {
+        let enabled = tool_patterns("tools.enabled", enabled, &names)?;
+        for name in &names {
+            if !enabled.iter().any(|pattern| pattern.matches(name)) {
+                router.map.remove(name.as_str());
+            }
+        }
+    }
+    let disabled = tool_patterns("tools.disabled", &tools.disabled, &names)?;
+    for name in &names {
+        if disabled.iter().any(|pattern| pattern.matches(name)) {
+            router.map.remove(name.as_str());
+        }
+    }
+
+    for name in safety.confirm.tools.keys() {
+        if !CONFIRMABLE_TOOLS.contains(&name.as_str()) {
+            return Err(ConfigError::InvalidValue {
+                key: format!("safety.confirm.tools.{name}"),
+                message: format!("confirmation is supported for {CONFIRMABLE_TOOLS:?}"),
+            });
+        }
+    }
+
+    if safety.is_read_only() {
+        for name in MUTATING_TOOLS {
+            router.map.remove(*name);
+        }
+    }
+
+    Ok(router)
+}
+
+/// Compile tool name patterns, rejecting the ones that match no tool
+fn tool_patterns(
+    key: &str,
+    patterns: &[String],
+    names: &[String],
+) -> Result<Vec<glob::Pattern>, ConfigError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let compiled = glob::Pattern::new(pattern).map_err(|e| ConfigError::InvalidValue {
+                key: key.to_string(),
+                message: format!("invalid pattern {pattern:?}: {e}"),
+            })?;
+            if !names.iter().any(|name| compiled.matches(name)) {
+                return Err(ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    message: format!("unknown tool {pattern:?}"),
+                });
+            }
+            Ok(compiled)
+        })
+        .collect()
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::{ConfigError, SafetyConfig, ToolsConfig};
+    use crate::server::tools::*;
+
+    fn tool_names(tools: &ToolsConfig) -> Result<Vec<String>, ConfigError> {
+        let router = build_tool_router_with_config(tools, &SafetyConfig::default())?;
+        let mut names: Vec<String> = router
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    #[test]
+    fn test_enabled_tool_patterns() {
+        let names = tool_names(&ToolsConfig {
+            enabled: Some(vec!["lsp_*".to_string(), "connect".to_string()]),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(names.contains(&"connect".to_string()));
+        assert!(names.contains(&"lsp_hover".to_string()));
+        assert!(!names.contains(&"connect_tcp".to_string()));
+        assert!(!names.contains(&"exec_lua".to_string()));
+        assert!(
+            names
+                .iter()
+                .all(|n| n == "connect" || n.starts_with("lsp_"))
+        );
+    }
+
+    #[test]
+    fn test_disabled_tool_patterns() {
+        let names = tool_names(&ToolsConfig {
+            enabled: Some(vec!["lsp_*".to_string()]),
+            disabled: vec!["lsp_*formatting".to_string(), "lsp_rename".to_string()],
+        })
+        .unwrap();
+
+        assert!(names.contains(&"lsp_hover".to_string()));
+        for name in ["lsp_formatting", "lsp_range_formatting", "lsp_rename"] {
+            assert!(
+                !names.contains(&name.to_string()),
+                "{name} should be disabled"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_tool_patterns_are_rejected() {
+        for tools in [
+            ToolsConfig {
+                enabled: Some(vec!["hover".to_string()]),
+                ..Default::default()
+            },
+            ToolsConfig {
+                disabled: vec!["exec_*_lua".to_string()],
+                ..Default::default()
+            },
+        ] {
+            match tool_names(&tools) {
+                Err(ConfigError::InvalidValue { message, .. }) => {
+                    assert!(message.contains("unknown tool"), "{message}")
+                }
+                other => panic!("Expected invalid value, got {other:?}"),
+            }
+        }
+    }
+}